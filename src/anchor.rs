@@ -0,0 +1,100 @@
+//! Positioning sprites and text relative to the edges of the frame.
+
+/// A point in the frame, specified as fractions of the frame's width
+/// and height: (0.0, 0.0) is the top-left corner, and (1.0, 1.0) the
+/// bottom-right.
+///
+/// Used with [`Sprite::anchor`](struct.Sprite.html#method.anchor),
+/// [`Sprite::anchored_corners`](struct.Sprite.html#method.anchored_corners),
+/// and the equivalent functions of [`Text`](struct.Text.html), to
+/// place things relative to the edges of the frame instead of the
+/// top-left corner. The anchors are resolved against the frame size
+/// when the sprite or text is finished, so they keep their place
+/// when the window is resized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Anchor {
+    /// The horizontal position, 0.0 being the left edge and 1.0 the
+    /// right edge.
+    pub x: f32,
+    /// The vertical position, 0.0 being the top edge and 1.0 the
+    /// bottom edge.
+    pub y: f32,
+}
+
+impl Anchor {
+    /// The top-left corner of the frame.
+    pub const TOP_LEFT: Anchor = Anchor::new(0.0, 0.0);
+    /// The middle of the top edge of the frame.
+    pub const TOP: Anchor = Anchor::new(0.5, 0.0);
+    /// The top-right corner of the frame.
+    pub const TOP_RIGHT: Anchor = Anchor::new(1.0, 0.0);
+    /// The middle of the left edge of the frame.
+    pub const LEFT: Anchor = Anchor::new(0.0, 0.5);
+    /// The center of the frame.
+    pub const CENTER: Anchor = Anchor::new(0.5, 0.5);
+    /// The middle of the right edge of the frame.
+    pub const RIGHT: Anchor = Anchor::new(1.0, 0.5);
+    /// The bottom-left corner of the frame.
+    pub const BOTTOM_LEFT: Anchor = Anchor::new(0.0, 1.0);
+    /// The middle of the bottom edge of the frame.
+    pub const BOTTOM: Anchor = Anchor::new(0.5, 1.0);
+    /// The bottom-right corner of the frame.
+    pub const BOTTOM_RIGHT: Anchor = Anchor::new(1.0, 1.0);
+
+    /// Creates an anchor at the given fractions of the frame's width
+    /// and height.
+    pub const fn new(x: f32, y: f32) -> Anchor {
+        Anchor { x, y }
+    }
+}
+
+/// How the coordinates of a sprite or text are anchored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Anchoring {
+    /// The whole rectangle is anchored to one point: the same
+    /// fractional point of the rectangle is placed at the anchor,
+    /// offset by the rectangle's position.
+    Whole(Anchor),
+    /// The top-left and bottom-right corners are offsets from their
+    /// own anchors, so the rectangle stretches with the frame.
+    Corners(Anchor, Anchor),
+}
+
+impl Anchoring {
+    /// Resolves the anchored corners `(x0, y0, x1, y1)` into
+    /// coordinates in a frame of the given size.
+    pub fn resolve(
+        self,
+        (x0, y0, x1, y1): (f32, f32, f32, f32),
+        (frame_width, frame_height): (f32, f32),
+    ) -> (f32, f32, f32, f32) {
+        match self {
+            Anchoring::Whole(anchor) => {
+                let (width, height) = (x1 - x0, y1 - y0);
+                let x = anchor.x * (frame_width - width) + x0;
+                let y = anchor.y * (frame_height - height) + y0;
+                (x, y, x + width, y + height)
+            }
+            Anchoring::Corners(top_left, bottom_right) => (
+                top_left.x * frame_width + x0,
+                top_left.y * frame_height + y0,
+                bottom_right.x * frame_width + x1,
+                bottom_right.y * frame_height + y1,
+            ),
+        }
+    }
+}
+
+#[test]
+fn anchors_are_resolved_against_the_frame() {
+    let frame = (800.0, 600.0);
+    let coords = (-10.0, -10.0, 90.0, 40.0);
+    let whole = Anchoring::Whole(Anchor::BOTTOM_RIGHT).resolve(coords, frame);
+    assert_eq!(whole, (690.0, 540.0, 790.0, 590.0));
+    let centered = Anchoring::Whole(Anchor::CENTER).resolve((0.0, 0.0, 100.0, 50.0), frame);
+    assert_eq!(centered, (350.0, 275.0, 450.0, 325.0));
+
+    let bar = Anchoring::Corners(Anchor::TOP_LEFT, Anchor::TOP_RIGHT);
+    let bar = bar.resolve((10.0, 10.0, -10.0, 30.0), frame);
+    assert_eq!(bar, (10.0, 10.0, 790.0, 30.0));
+}
@@ -0,0 +1,365 @@
+//! Frame-based sprite animations.
+use crate::types::Region;
+
+/// Describes what happens when an animation reaches its last frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaybackMode {
+    /// Start over from the first frame.
+    Loop,
+    /// Play the frames backwards back to the first frame, then
+    /// forwards again, and so on.
+    PingPong,
+    /// Stay on the last frame.
+    Once,
+}
+
+/// A single frame of an [`Animation`](struct.Animation.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationFrame {
+    /// The region of the texture shown during this frame. Frames
+    /// that have been trimmed or rotated by a texture packer stay
+    /// aligned with each other, see [`Region`](struct.Region.html).
+    pub region: Region,
+    /// How long this frame is shown, in seconds.
+    pub duration: f32,
+    /// If set, an event with this tag is fired when the frame is
+    /// reached. See
+    /// [`AnimationPlayer::events`](struct.AnimationPlayer.html#method.events).
+    pub tag: Option<String>,
+}
+
+/// A sequence of frames from a spritesheet, played with an
+/// [`AnimationPlayer`](struct.AnimationPlayer.html).
+///
+/// # Example
+/// ```
+/// use fae::{Animation, PlaybackMode};
+/// // A walk cycle of 6 32x32 frames, in a grid of 4 columns.
+/// let mut walk = Animation::from_grid((0, 0), (32, 32), 4, 6, 0.1);
+/// walk.mode(PlaybackMode::Loop).tag_frame(2, "footstep").tag_frame(5, "footstep");
+/// assert_eq!(walk.frames[5].region, (32, 32, 32, 32).into());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    /// The frames of the animation, in order.
+    pub frames: Vec<AnimationFrame>,
+    /// What happens after the last frame.
+    pub mode: PlaybackMode,
+}
+
+impl Animation {
+    /// Creates a looping animation from `frame_count` frames in a
+    /// grid with `columns` columns, starting from `origin` (x, y),
+    /// with each frame being `frame_size` (width, height) pixels. The
+    /// frames go from left to right, top to bottom, and each is shown
+    /// for `frame_duration` seconds.
+    pub fn from_grid(
+        (origin_x, origin_y): (i32, i32),
+        (frame_width, frame_height): (i32, i32),
+        columns: i32,
+        frame_count: i32,
+        frame_duration: f32,
+    ) -> Animation {
+        let columns = columns.max(1);
+        let frames = (0..frame_count)
+            .map(|i| AnimationFrame {
+                region: (
+                    origin_x + i % columns * frame_width,
+                    origin_y + i / columns * frame_height,
+                    frame_width,
+                    frame_height,
+                )
+                    .into(),
+                duration: frame_duration,
+                tag: None,
+            })
+            .collect();
+        Animation {
+            frames,
+            mode: PlaybackMode::Loop,
+        }
+    }
+
+    /// Creates a looping animation from a list of (region, duration)
+    /// pairs. The regions are in actual pixels in the texture's
+    /// coordinate space, and the durations in seconds.
+    pub fn from_regions<R: Into<Region>, I: IntoIterator<Item = (R, f32)>>(frames: I) -> Animation {
+        let frames = frames
+            .into_iter()
+            .map(|(region, duration)| AnimationFrame {
+                region: region.into(),
+                duration,
+                tag: None,
+            })
+            .collect();
+        Animation {
+            frames,
+            mode: PlaybackMode::Loop,
+        }
+    }
+
+    /// Sets the playback mode.
+    pub fn mode(&mut self, mode: PlaybackMode) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Tags the frame, so that an event is fired when it is
+    /// reached. Does nothing if the frame doesn't exist.
+    pub fn tag_frame<S: Into<String>>(&mut self, frame: usize, tag: S) -> &mut Self {
+        if let Some(frame) = self.frames.get_mut(frame) {
+            frame.tag = Some(tag.into());
+        }
+        self
+    }
+
+    /// Returns the length of a single pass through the frames, in
+    /// seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+/// Plays an [`Animation`](struct.Animation.html), and applies the
+/// current frame to sprites.
+///
+/// # Example
+/// ```no_run
+/// # let mut ctx = fae::Context::new();
+/// # let spritesheet = fae::SpritesheetBuilder::default().build(&mut ctx);
+/// use fae::{Animation, AnimationPlayer};
+/// let mut walk = Animation::from_grid((0, 0), (32, 32), 4, 6, 0.1);
+/// walk.tag_frame(2, "footstep");
+/// let mut player = AnimationPlayer::new(walk);
+///
+/// // Every frame:
+/// # let (width, height, dpi_factor, delta_seconds) = (0.0, 0.0, 0.0, 0.0);
+/// player.advance(delta_seconds);
+/// for event in player.events() {
+///     if event == "footstep" {
+///         // Play a sound
+///     }
+/// }
+/// let mut ctx = ctx.start_frame(width, height, dpi_factor);
+/// spritesheet
+///     .draw(&mut ctx)
+///     .coordinates((100.0, 100.0, 64.0, 64.0))
+///     .animation(&player)
+///     .finish();
+/// ```
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer {
+    animation: Animation,
+    frame: usize,
+    /// How long the current frame has been shown, in seconds.
+    frame_time: f32,
+    /// 1 when going forwards, -1 when going backwards (in ping-pong).
+    direction: isize,
+    started: bool,
+    finished: bool,
+    /// The frames reached during the latest advance.
+    reached_frames: Vec<usize>,
+}
+
+impl AnimationPlayer {
+    /// Creates a player that starts from the first frame of the
+    /// animation.
+    pub fn new(animation: Animation) -> AnimationPlayer {
+        AnimationPlayer {
+            animation,
+            frame: 0,
+            frame_time: 0.0,
+            direction: 1,
+            started: false,
+            finished: false,
+            reached_frames: Vec::new(),
+        }
+    }
+
+    /// Returns the animation being played.
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    /// Replaces the animation being played, and restarts.
+    pub fn set_animation(&mut self, animation: Animation) {
+        self.animation = animation;
+        self.restart();
+    }
+
+    /// Starts the animation over from the first frame.
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.frame_time = 0.0;
+        self.direction = 1;
+        self.started = false;
+        self.finished = false;
+        self.reached_frames.clear();
+    }
+
+    /// Advances the animation by `seconds`. The tags of the frames
+    /// reached during this time can be read with
+    /// [`events`](#method.events) afterwards.
+    ///
+    /// When looping, whole cycles of the animation are skipped over
+    /// without reporting their frames as events, so a long stall
+    /// doesn't cause a flood of them. Non-finite values of `seconds`
+    /// are ignored.
+    pub fn advance(&mut self, seconds: f32) {
+        self.reached_frames.clear();
+        if self.animation.frames.is_empty() || !seconds.is_finite() {
+            return;
+        }
+        if !self.started {
+            self.started = true;
+            self.reached_frames.push(self.frame);
+        }
+        if self.finished || self.animation.duration() <= 0.0 {
+            return;
+        }
+
+        self.frame_time += seconds;
+        if let Some(cycle) = self.cycle_duration() {
+            self.frame_time %= cycle;
+        }
+        while self.frame_time >= self.animation.frames[self.frame].duration {
+            let duration = self.animation.frames[self.frame].duration;
+            if !self.step() {
+                self.finished = true;
+                self.frame_time = duration;
+                break;
+            }
+            self.frame_time -= duration;
+            self.reached_frames.push(self.frame);
+        }
+    }
+
+    /// Returns the time after which a looping animation is back in
+    /// the same state, or None if it doesn't loop.
+    fn cycle_duration(&self) -> Option<f32> {
+        let frames = &self.animation.frames;
+        match self.animation.mode {
+            PlaybackMode::Loop => Some(self.animation.duration()),
+            PlaybackMode::Once => None,
+            PlaybackMode::PingPong if frames.len() <= 2 => Some(self.animation.duration()),
+            PlaybackMode::PingPong => {
+                // The frames between the ends are shown twice per cycle.
+                let middle = &frames[1..frames.len() - 1];
+                let middle: f32 = middle.iter().map(|frame| frame.duration).sum();
+                Some(self.animation.duration() + middle)
+            }
+        }
+    }
+
+    /// Moves to the next frame according to the playback mode.
+    /// Returns false if the animation is over.
+    fn step(&mut self) -> bool {
+        let frame_count = self.animation.frames.len() as isize;
+        let frame = self.frame as isize;
+        self.frame = match self.animation.mode {
+            PlaybackMode::Loop => ((frame + 1) % frame_count) as usize,
+            PlaybackMode::Once if frame + 1 < frame_count => (frame + 1) as usize,
+            PlaybackMode::Once => return false,
+            PlaybackMode::PingPong if frame_count == 1 => 0,
+            PlaybackMode::PingPong => {
+                let next = frame + self.direction;
+                if next < 0 || next >= frame_count {
+                    self.direction = -self.direction;
+                }
+                (frame + self.direction) as usize
+            }
+        };
+        true
+    }
+
+    /// Returns the tags of the tagged frames reached during the
+    /// latest [`advance`](#method.advance), in order. The first frame
+    /// counts as reached on the first advance after creating or
+    /// restarting the player.
+    pub fn events(&self) -> impl Iterator<Item = &str> {
+        let frames = &self.animation.frames;
+        self.reached_frames
+            .iter()
+            .filter_map(move |i| frames[*i].tag.as_deref())
+    }
+
+    /// Returns the index of the current frame.
+    pub fn current_frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Returns the texture region of the current frame, or None if
+    /// the animation has no frames.
+    pub fn current_region(&self) -> Option<Region> {
+        self.animation
+            .frames
+            .get(self.frame)
+            .map(|frame| frame.region)
+    }
+
+    /// Returns true if the animation's playback mode is
+    /// [`Once`](enum.PlaybackMode.html#variant.Once) and the last
+    /// frame has been shown for its full duration.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[test]
+fn playback_modes_and_events() {
+    let mut animation = Animation::from_grid((0, 0), (8, 8), 3, 3, 1.0);
+    animation.tag_frame(0, "start").tag_frame(2, "end");
+    let frames_over = |animation: &Animation, steps: usize| {
+        let mut player = AnimationPlayer::new(animation.clone());
+        let mut frames = Vec::new();
+        for _ in 0..steps {
+            player.advance(1.0);
+            frames.push(player.current_frame());
+        }
+        (frames, player)
+    };
+
+    let (frames, _) = frames_over(&animation, 5);
+    assert_eq!(frames, vec![1, 2, 0, 1, 2]);
+
+    animation.mode(PlaybackMode::PingPong);
+    let (frames, _) = frames_over(&animation, 6);
+    assert_eq!(frames, vec![1, 2, 1, 0, 1, 2]);
+
+    animation.mode(PlaybackMode::Once);
+    let (frames, player) = frames_over(&animation, 4);
+    assert_eq!(frames, vec![1, 2, 2, 2]);
+    assert!(player.is_finished());
+
+    let mut player = AnimationPlayer::new(animation);
+    player.advance(2.5);
+    assert_eq!(player.events().collect::<Vec<_>>(), vec!["start", "end"]);
+    player.advance(0.1);
+    assert_eq!(player.events().count(), 0);
+}
+
+#[test]
+fn long_advances_skip_whole_cycles() {
+    let mut animation = Animation::from_grid((0, 0), (8, 8), 3, 3, 1.0);
+    animation.tag_frame(0, "start");
+    let mut player = AnimationPlayer::new(animation.clone());
+    player.advance(0.5);
+    player.advance(f32::INFINITY);
+    player.advance(f32::NAN);
+    assert_eq!(player.current_frame(), 0);
+    player.advance(3_000_000.0);
+    assert_eq!(player.current_frame(), 0);
+    assert_eq!(player.events().count(), 0);
+    player.advance(1.0);
+    assert_eq!(player.current_frame(), 1);
+
+    // A ping-pong cycle goes 0, 1, 2, 1, so it's 4 seconds long.
+    animation.mode(PlaybackMode::PingPong);
+    let mut player = AnimationPlayer::new(animation);
+    player.advance(4_000_001.5);
+    assert_eq!(player.current_frame(), 1);
+    player.advance(2.0);
+    assert_eq!(player.current_frame(), 1);
+    player.advance(1.0);
+    assert_eq!(player.current_frame(), 0);
+}
@@ -1,7 +1,15 @@
+use crate::api::SpritesheetBuilder;
+use crate::asset_loader::{AssetLoader, ImageSource, LoadingSpritesheet, DEFAULT_UPLOAD_BUDGET};
+use crate::capabilities::Capabilities;
+use crate::gl;
 use crate::gl_version::OpenGlVersion;
+use crate::post_process::{PostProcess, PostProcessor};
 use crate::renderer::Renderer;
 #[cfg(feature = "text")]
 use crate::text::TextRenderer;
+use crate::virtual_resolution::VirtualResolution;
+
+use std::path::PathBuf;
 
 /// The overarching state of the crate. Intended to live outside of
 /// the main game loop.
@@ -41,6 +49,18 @@ pub struct Context {
     pub(crate) renderer: Renderer,
     #[cfg(feature = "text")]
     pub(crate) text_renderers: Vec<TextRenderer>,
+    post_processor: Option<PostProcessor>,
+    virtual_resolution: Option<VirtualResolution>,
+    /// Used to upscale the frame when using a virtual resolution
+    /// without post-processing.
+    upscaler: Option<PostProcessor>,
+    /// The logical width, height, and the dpi factor of the window,
+    /// as passed to the latest `start_frame`.
+    window_size: (f32, f32, f32),
+    /// Created when the first spritesheet is loaded in the
+    /// background.
+    asset_loader: Option<AssetLoader>,
+    upload_budget: usize,
 }
 
 impl Context {
@@ -65,6 +85,12 @@ impl Context {
             renderer: Renderer::new(),
             #[cfg(feature = "text")]
             text_renderers: Vec::new(),
+            post_processor: None,
+            virtual_resolution: None,
+            upscaler: None,
+            window_size: (0.0, 0.0, 1.0),
+            asset_loader: None,
+            upload_budget: DEFAULT_UPLOAD_BUDGET,
         }
     }
 
@@ -76,7 +102,13 @@ impl Context {
 
     /// Returns the OpenGL version if it could be parsed.
     pub fn get_opengl_version(&self) -> &OpenGlVersion {
-        &self.renderer.version
+        &self.renderer.capabilities.version
+    }
+
+    /// Returns the limits and optional features of the OpenGL
+    /// context.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.renderer.capabilities
     }
 
     /// Tries to ensure that all the commands queued in the GPU have been processed.
@@ -95,8 +127,33 @@ impl Context {
     /// window, and dpi_factor is a multiplier, such that: `width *
     /// dpi_factor` is the window's width in physical pixels, and
     /// `height * dpi_factor` is the height in physical pixels.
+    ///
+    /// If a virtual resolution is set, the GraphicsContext's `width`
+    /// and `height` will be those of the virtual resolution instead,
+    /// and the `dpi_factor` will be 1.0. See
+    /// [`Context::set_virtual_resolution`](#method.set_virtual_resolution).
+    ///
+    /// With the `hot-reload` feature, this is also where the shader
+    /// and image files of spritesheets are checked for changes and
+    /// reloaded.
+    ///
+    /// Images loaded with
+    /// [`Context::load_spritesheet`](#method.load_spritesheet) are
+    /// also uploaded here, up to the budget set with
+    /// [`Context::set_texture_upload_budget`](#method.set_texture_upload_budget).
     pub fn start_frame(&mut self, width: f32, height: f32, dpi_factor: f32) -> GraphicsContext {
-        self.renderer.prepare_new_frame(dpi_factor);
+        #[cfg(feature = "hot-reload")]
+        self.renderer.reload_changed_files();
+        if let Some(mut asset_loader) = self.asset_loader.take() {
+            asset_loader.update(self, self.upload_budget);
+            self.asset_loader = Some(asset_loader);
+        }
+        self.window_size = (width, height, dpi_factor);
+        let (width, height, dpi_factor) = match self.virtual_resolution {
+            Some(resolution) => (resolution.width as f32, resolution.height as f32, 1.0),
+            None => (width, height, dpi_factor),
+        };
+        self.renderer.prepare_new_frame(dpi_factor, width, height);
 
         #[cfg(feature = "text")]
         for font in &mut self.text_renderers {
@@ -128,8 +185,199 @@ impl Context {
     /// This should generally be called after
     /// [`GraphicsContext::finish_frame`](struct.GraphicsContext.html#method.finish_frame),
     /// but can also be used to redraw the previous frame.
+    ///
+    /// If post-processing is enabled, the frame is first rendered
+    /// offscreen, and then drawn on the screen through the effects.
+    /// See [`Context::set_post_process`](#method.set_post_process).
+    ///
+    /// If a virtual resolution is set, the frame is rendered at that
+    /// resolution, and then scaled into the viewport according to
+    /// the resolution's [`Scaling`](enum.Scaling.html).
     pub fn render(&mut self, width: f32, height: f32, clear_color: Option<(f32, f32, f32, f32)>) {
-        self.renderer.render(width, height, clear_color);
+        if let Some(resolution) = self.virtual_resolution {
+            self.render_virtual(resolution, clear_color);
+        } else if let Some(post_processor) = &mut self.post_processor {
+            post_processor.render(&mut self.renderer, width, height, clear_color);
+        } else {
+            self.renderer.render(width, height, clear_color);
+        }
+    }
+
+    fn render_virtual(
+        &mut self,
+        resolution: VirtualResolution,
+        clear_color: Option<(f32, f32, f32, f32)>,
+    ) {
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        let [x, y, width, height] = viewport;
+        let area = resolution.get_frame_area(width as f32, height as f32);
+
+        let (r, g, b, a) = resolution.letterbox_color;
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Scissor(x, y, width, height);
+            gl::ClearColor(r, g, b, a);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::Disable(gl::SCISSOR_TEST);
+            // The area is from the top-left, the viewport from the bottom-left.
+            gl::Viewport(
+                x + area.x as i32,
+                y + height - (area.y + area.height) as i32,
+                area.width as i32,
+                area.height as i32,
+            );
+        }
+
+        if self.post_processor.is_none() && self.upscaler.is_none() {
+            let upscale = PostProcess {
+                effects: Vec::new(),
+                smoothing: false,
+            };
+            self.upscaler = PostProcessor::new(&self.renderer, upscale);
+        }
+        let (virtual_width, virtual_height) = (resolution.width as f32, resolution.height as f32);
+        if let Some(post_processor) = self.post_processor.as_mut().or(self.upscaler.as_mut()) {
+            post_processor.render(
+                &mut self.renderer,
+                virtual_width,
+                virtual_height,
+                clear_color,
+            );
+        } else {
+            // Offscreen rendering is not supported, so just draw
+            // straight into the area, without the crisp pixels.
+            self.renderer
+                .render(virtual_width, virtual_height, clear_color);
+        }
+
+        unsafe {
+            gl::Viewport(x, y, width, height);
+        }
+    }
+
+    /// Sets the resolution the frame is rendered at, or `None` to
+    /// render at the window's resolution (the default).
+    ///
+    /// When set, all drawing happens in virtual pixels: the
+    /// GraphicsContext's `width` and `height` will be the virtual
+    /// resolution's, and
+    /// [`Context::render`](#method.render) scales the frame into
+    /// the viewport, filling the rest with the letterbox color. Use
+    /// [`Context::window_to_virtual`](#method.window_to_virtual) to
+    /// convert mouse coordinates.
+    ///
+    /// If post-processing is enabled as well, the effects are
+    /// applied at the virtual resolution, and the last effect is
+    /// scaled up. In that case, the
+    /// [`PostProcess::smoothing`](struct.PostProcess.html#structfield.smoothing)
+    /// decides the filtering of the upscale, otherwise it is
+    /// nearest-neighbor.
+    pub fn set_virtual_resolution(&mut self, resolution: Option<VirtualResolution>) {
+        self.virtual_resolution = resolution;
+    }
+
+    /// Converts a point in the window (in logical pixels, eg. the
+    /// mouse position) to virtual pixels, based on the window size
+    /// passed to the latest
+    /// [`Context::start_frame`](#method.start_frame). Returns None if
+    /// the point is in the letterboxing area, outside the frame.
+    ///
+    /// If a virtual resolution is not set, the point is returned as
+    /// is.
+    pub fn window_to_virtual(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let resolution = match self.virtual_resolution {
+            Some(resolution) => resolution,
+            None => return Some((x, y)),
+        };
+        let (width, height, dpi_factor) = self.window_size;
+        let area = resolution.get_frame_area(width * dpi_factor, height * dpi_factor);
+        let (x, y) = ((x * dpi_factor - area.x), (y * dpi_factor - area.y));
+        if x < 0.0 || y < 0.0 || x >= area.width || y >= area.height {
+            None
+        } else {
+            Some((
+                x / area.width * resolution.width as f32,
+                y / area.height * resolution.height as f32,
+            ))
+        }
+    }
+
+    /// Starts loading a spritesheet from the image file at `path`,
+    /// built with the settings of `builder` (its image is
+    /// ignored). See [`LoadingSpritesheet`](struct.LoadingSpritesheet.html).
+    ///
+    /// The file is read and decoded on a worker thread, with
+    /// [`Image::from_bytes`](struct.Image.html#method.from_bytes),
+    /// and the texture is uploaded during the following calls to
+    /// [`Context::start_frame`](#method.start_frame). Images larger
+    /// than the upload budget are uploaded a few rows at a time,
+    /// over multiple frames.
+    pub fn load_spritesheet<P: Into<PathBuf>>(
+        &mut self,
+        path: P,
+        builder: &SpritesheetBuilder,
+    ) -> LoadingSpritesheet {
+        let source = ImageSource::File(path.into());
+        self.asset_loader
+            .get_or_insert_with(AssetLoader::new)
+            .load(source, builder)
+    }
+
+    /// Like [`Context::load_spritesheet`](#method.load_spritesheet),
+    /// but decodes the image from `bytes` instead of reading a file.
+    pub fn load_spritesheet_from_bytes(
+        &mut self,
+        bytes: Vec<u8>,
+        builder: &SpritesheetBuilder,
+    ) -> LoadingSpritesheet {
+        let source = ImageSource::Bytes(bytes);
+        self.asset_loader
+            .get_or_insert_with(AssetLoader::new)
+            .load(source, builder)
+    }
+
+    /// Sets how many bytes of image data loaded with
+    /// [`Context::load_spritesheet`](#method.load_spritesheet) can be
+    /// uploaded to the GPU per frame. The default is 4 MiB.
+    ///
+    /// At least one upload is done per frame regardless of the
+    /// budget, so that loading always progresses.
+    pub fn set_texture_upload_budget(&mut self, bytes_per_frame: usize) {
+        self.upload_budget = bytes_per_frame;
+    }
+
+    /// Sets the effects applied to the frame after rendering, or
+    /// disables post-processing if `None`. See
+    /// [`PostProcess`](struct.PostProcess.html).
+    ///
+    /// The effects are drawn into the viewport that is set when
+    /// calling [`Context::render`](#method.render), like the sprites
+    /// would be without post-processing.
+    ///
+    /// Returns false if post-processing is not supported by the
+    /// OpenGL context (framebuffer objects are missing), in which
+    /// case the frame will be rendered without any effects.
+    pub fn set_post_process(&mut self, post_process: Option<PostProcess>) -> bool {
+        self.post_processor = None;
+        if let Some(post_process) = post_process {
+            self.post_processor = PostProcessor::new(&self.renderer, post_process);
+            self.post_processor.is_some()
+        } else {
+            true
+        }
+    }
+
+    /// Sets a uniform of the effect at `effect_index` in the current
+    /// post-processing chain. Useful for animating effects. See
+    /// [`Effect::uniform`](struct.Effect.html#method.uniform).
+    pub fn set_effect_uniform(&mut self, effect_index: usize, name: &str, values: &[f32]) {
+        if let Some(post_processor) = &mut self.post_processor {
+            post_processor.set_uniform(effect_index, name, values);
+        }
     }
 }
 
@@ -6,21 +6,55 @@ mod spritesheet;
 #[cfg(feature = "text")]
 pub use font::Font;
 pub use graphics_context::{Context, GraphicsContext};
-pub use spritesheet::{AlphaBlending, Spritesheet, SpritesheetBuilder};
+pub use spritesheet::{AlphaBlending, BlendMode, Spritesheet, SpritesheetBuilder};
 
 // Re-exports from other parts of the crate
 pub mod errors {
     //! The errors that fae can return.
+    #[cfg(feature = "sheet-import")]
+    pub use crate::error::AtlasLoadingError;
+    #[cfg(feature = "bmp")]
+    pub use crate::error::BmpLoadingError;
+    #[cfg(feature = "dds")]
+    pub use crate::error::DdsLoadingError;
+    #[cfg(feature = "gif")]
+    pub use crate::error::GifLoadingError;
     pub use crate::error::ImageCreationError;
+    pub use crate::error::ImageManipulationError;
+    #[cfg(feature = "jpeg")]
+    pub use crate::error::JpegLoadingError;
+    #[cfg(feature = "ktx")]
+    pub use crate::error::KtxLoadingError;
+    #[cfg(feature = "png")]
+    pub use crate::error::PngEncodingError;
     #[cfg(feature = "png")]
     pub use crate::error::PngLoadingError;
+    #[cfg(feature = "qoi")]
+    pub use crate::error::QoiLoadingError;
+    pub use crate::error::SpritesheetCreationError;
+    #[cfg(feature = "tga")]
+    pub use crate::error::TgaLoadingError;
 }
+pub use crate::anchor::Anchor;
+pub use crate::animation::{Animation, AnimationFrame, AnimationPlayer, PlaybackMode};
+pub use crate::asset_loader::{LoadStatus, LoadingSpritesheet};
+#[cfg(feature = "sheet-import")]
+pub use crate::atlas::{Atlas, AtlasFrame, AtlasSlice, AtlasSliceKey};
+pub use crate::canvas::Canvas;
+pub use crate::capabilities::Capabilities;
 pub use crate::error::Error;
 pub use crate::gl_version::{OpenGlApi, OpenGlVersion};
-pub use crate::image::Image;
+#[cfg(feature = "hot-reload")]
+pub use crate::hot_reload::{ShaderFiles, ShaderPairFiles};
+pub use crate::image::{Image, ResizeFilter};
+pub use crate::particles::{EmissionShape, ParticleEmitter, ParticleSettings};
+pub use crate::post_process::{Effect, PostProcess};
 pub use crate::renderer::TextureWrapping;
 pub use crate::shaders::{ShaderPair, Shaders};
 pub use crate::sprite::Sprite;
 #[cfg(feature = "text")]
 pub use crate::text::{Alignment, Text};
-pub use crate::types::Rect;
+pub use crate::tilemap::{TileAnimation, TileLayout, Tilemap, Tileset};
+pub use crate::types::{Rect, Region};
+pub use crate::video::{VideoSpritesheet, YuvFormat, YuvMatrix, YuvPlane, YuvRange};
+pub use crate::virtual_resolution::{Scaling, VirtualResolution};
@@ -1,10 +1,16 @@
 use crate::api::{Context, GraphicsContext};
+use crate::error::SpritesheetCreationError;
 use crate::image::Image;
 use crate::sprite::Sprite;
 use crate::types::{Rect, RectPx};
 
 use crate::renderer::{DrawCallHandle, Shaders, TextureWrapping};
 
+#[cfg(feature = "hot-reload")]
+use crate::hot_reload::{load_image, ShaderFiles};
+#[cfg(feature = "hot-reload")]
+use std::path::PathBuf;
+
 /// Holds a texture for rendering.
 ///
 /// This struct is safe to clone in order to use elsewhere: the only
@@ -86,6 +92,11 @@ impl Spritesheet {
     /// or the `region` isn't completely contained within the texture,
     /// this function will do nothing and return false.
     ///
+    /// If the spritesheet was built with
+    /// [`pixel_buffer_uploads`](struct.SpritesheetBuilder.html#structfield.pixel_buffer_uploads),
+    /// the upload is asynchronous if the context supports pixel
+    /// buffer objects.
+    ///
     /// See also:
     /// [`Image::with_null_texture`](struct.Image.html#method.with_null_texture).
     pub fn upload_texture_region<R: Into<Rect>>(
@@ -110,10 +121,17 @@ impl Spritesheet {
             .upload_texture_region(&self.handle, region, image)
     }
 
-    /// *Modern OpenGL only.* Resize the spritesheet texture to a new
-    /// width and height. The previous contents of the texture are
-    /// preserved in the origin corner of the texture if
-    /// `preserve_contents` is `true`.
+    /// Resize the spritesheet texture to a new width and height. The
+    /// previous contents of the texture are preserved in the origin
+    /// corner of the texture if `preserve_contents` is `true`.
+    ///
+    /// In legacy mode, the contents can't be preserved, so if
+    /// `preserve_contents` is `true`, this function does nothing and
+    /// returns false.
+    ///
+    /// Does nothing and returns false if the texture is compressed or
+    /// was created outside of fae, see
+    /// [`SpritesheetBuilder::build_with_texture`](struct.SpritesheetBuilder.html#method.build_with_texture).
     ///
     /// See also:
     /// [`Spritesheet::upload_texture_region`](struct.Spritesheet.html#method.upload_texture_region).
@@ -127,6 +145,34 @@ impl Spritesheet {
         ctx.renderer
             .resize_texture(&self.handle, new_width, new_height, preserve_contents)
     }
+
+    /// Replaces the spritesheet's texture with an OpenGL texture
+    /// created outside of fae, e.g. one that a video frame or a 3D
+    /// scene has been rendered into. Can be called every frame, the
+    /// sprites are drawn with the texture that was set last when
+    /// [`Context::render`](struct.Context.html#method.render) is
+    /// called.
+    ///
+    /// The `width` and `height` should be the texture's size in
+    /// pixels, they're used to calculate the texture coordinates of
+    /// [`Sprite::texture_coordinates`](struct.Sprite.html#method.texture_coordinates).
+    /// The format is kept from the previous texture.
+    ///
+    /// The texture is not deleted by fae, and it should stay alive as
+    /// long as it's being drawn. If the spritesheet's previous texture
+    /// was created by fae, it is deleted. See also:
+    /// [`SpritesheetBuilder::build_with_texture`](struct.SpritesheetBuilder.html#method.build_with_texture).
+    pub fn set_external_texture(
+        &self,
+        ctx: &mut GraphicsContext,
+        texture: gl::types::GLuint,
+        width: i32,
+        height: i32,
+    ) {
+        let format = ctx.renderer.get_texture_format(&self.handle);
+        ctx.renderer
+            .set_external_texture(&self.handle, texture, (width, height), format);
+    }
 }
 
 /// Describes how a spritesheet should be blended with the background.
@@ -139,9 +185,32 @@ pub struct AlphaBlending {
     pub sort: bool,
 }
 
+/// Describes how the colors of a spritesheet's sprites are combined
+/// with the colors under them, when
+/// [`AlphaBlending::blend`](struct.AlphaBlending.html#structfield.blend)
+/// is true.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode {
+    /// Regular transparency: the sprite is laid over the background
+    /// based on its alpha. (`GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA`)
+    Alpha,
+    /// The sprite's color, multiplied by its alpha, is added to the
+    /// background. Good for light, fire and other glowing
+    /// effects. (`GL_SRC_ALPHA, GL_ONE`)
+    Additive,
+    /// The background is multiplied by the sprite's color, which can
+    /// only darken it. Good for shadows. (`GL_DST_COLOR, GL_ZERO`)
+    Multiply,
+}
+
 /// A builder for [`Spritesheet`](struct.Spritesheet.html).
 #[derive(Clone)]
 pub struct SpritesheetBuilder {
+    /// A name for the spritesheet, which its OpenGL objects are
+    /// labeled with, and which its draws are grouped under, in
+    /// graphics debuggers like RenderDoc. Only has an effect if the
+    /// context supports `KHR_debug`.
+    pub name: Option<String>,
     /// The texture used when drawing with this handle. None can be
     /// used if you want to just draw flat-color quads.
     pub image: Option<Image>,
@@ -157,6 +226,9 @@ pub struct SpritesheetBuilder {
     /// Internally, this controls whether `GL_BLEND` and back-to-front
     /// sorting are enabled.
     pub alpha_blending: AlphaBlending,
+    /// How the sprites are blended with the background, if
+    /// `alpha_blending` is enabled.
+    pub blend_mode: BlendMode,
     /// When drawing quads that are smaller than the texture provided,
     /// use linear (true) or nearest neighbor (false) smoothing when
     /// scaling? (Linear is probably always better.)
@@ -182,41 +254,259 @@ pub struct SpritesheetBuilder {
     /// Internally, this controls whether or not `GL_FRAMEBUFFER_SRGB`
     /// is enabled when drawing with this handle.
     pub srgb: bool,
+    /// Extra per-sprite attributes passed to the vertex shader, as
+    /// (attribute name, component count) pairs. The component count
+    /// should be between 1 and 4, corresponding to `float`, `vec2`,
+    /// `vec3` and `vec4`, and other counts make
+    /// [`try_build`](#method.try_build) fail. The values are set with
+    /// [`Sprite::custom`](struct.Sprite.html#method.custom).
+    ///
+    /// When the sprites are drawn with instancing (always outside of
+    /// legacy mode, see [`Shaders`](struct.Shaders.html)), these are
+    /// instanced attributes, and otherwise, they are per-vertex
+    /// attributes (with each vertex of the quad having the same
+    /// value). Either way, the
+    /// shaders should declare them as `in`/`attribute` variables in
+    /// the vertex shader, and pass them on to the fragment shader
+    /// if needed.
+    pub custom_attributes: Vec<(String, i32)>,
+    /// Whether uploads with
+    /// [`Spritesheet::upload_texture_region`](struct.Spritesheet.html#method.upload_texture_region)
+    /// are staged through a ring of pixel unpack buffers, which
+    /// allows the upload to happen asynchronously instead of
+    /// stalling the pipeline. Useful for textures that are updated
+    /// every frame, like video frames. Has no effect if the context
+    /// doesn't support pixel buffer objects (see
+    /// [`Capabilities::pixel_buffer_objects`](struct.Capabilities.html#structfield.pixel_buffer_objects)),
+    /// in which case uploads are synchronous.
+    pub pixel_buffer_uploads: bool,
+    /// Files to load the shaders from, overriding the relevant parts
+    /// of `shaders`. The files are polled for changes at the start of
+    /// each frame, and the shaders are recompiled when they change.
+    #[cfg(feature = "hot-reload")]
+    pub shader_files: Option<ShaderFiles>,
+    /// A PNG file to load the texture from, overriding `image`. The
+    /// file is polled for changes at the start of each frame, and
+    /// the texture is re-uploaded when it changes.
+    #[cfg(feature = "hot-reload")]
+    pub image_file: Option<PathBuf>,
 }
 
 impl Default for SpritesheetBuilder {
     fn default() -> SpritesheetBuilder {
         SpritesheetBuilder {
+            name: None,
             image: None,
             shaders: Shaders::default(),
             alpha_blending: AlphaBlending {
                 blend: true,
                 sort: true,
             },
+            blend_mode: BlendMode::Alpha,
             minification_smoothing: true,
             magnification_smoothing: true,
             wrap: (TextureWrapping::Clamp, TextureWrapping::Clamp),
             srgb: true,
+            custom_attributes: Vec::new(),
+            pixel_buffer_uploads: false,
+            #[cfg(feature = "hot-reload")]
+            shader_files: None,
+            #[cfg(feature = "hot-reload")]
+            image_file: None,
         }
     }
 }
 
 impl SpritesheetBuilder {
     /// Creates a new Spritesheet from this builder.
+    ///
+    /// If the image is compressed in a format the OpenGL context
+    /// doesn't support, the error is logged, and the spritesheet is
+    /// created without a texture. Similarly, if a custom attribute
+    /// is invalid, the spritesheet is created without any custom
+    /// attributes. Use [`try_build`](#method.try_build) to handle
+    /// these cases.
     pub fn build(&self, ctx: &mut Context) -> Spritesheet {
+        match self.try_build(ctx) {
+            Ok(spritesheet) => spritesheet,
+            Err(err) => {
+                log::error!("Could not create the spritesheet: {}", err);
+                let fallback = match err {
+                    SpritesheetCreationError::InvalidCustomAttribute(_, _) => SpritesheetBuilder {
+                        custom_attributes: Vec::new(),
+                        ..self.clone()
+                    },
+                    _ => SpritesheetBuilder {
+                        image: None,
+                        ..self.clone()
+                    },
+                };
+                fallback.build(ctx)
+            }
+        }
+    }
+
+    /// Creates a new Spritesheet from this builder.
+    ///
+    /// # Errors
+    ///
+    /// [`UnsupportedCompressedFormat`](enum.SpritesheetCreationError.html#variant.UnsupportedCompressedFormat)
+    /// is returned if the image is compressed (see
+    /// [`Image::is_compressed`](struct.Image.html#method.is_compressed))
+    /// in a format that the OpenGL context doesn't support, e.g. BC7
+    /// on OpenGL ES, or ETC2 on older desktop drivers.
+    ///
+    /// [`InvalidCustomAttribute`](enum.SpritesheetCreationError.html#variant.InvalidCustomAttribute)
+    /// is returned if one of the
+    /// [`custom_attributes`](#structfield.custom_attributes) doesn't
+    /// have 1-4 components.
+    pub fn try_build(&self, ctx: &mut Context) -> Result<Spritesheet, SpritesheetCreationError> {
+        self.validate_custom_attributes()?;
+        if let Some(image) = &self.image {
+            if image.is_compressed() && !ctx.renderer.supports_compressed_format(image.format) {
+                return Err(SpritesheetCreationError::UnsupportedCompressedFormat(
+                    image.format,
+                ));
+            }
+        }
+        Ok(self.build_unchecked(ctx))
+    }
+
+    /// Creates a new Spritesheet which draws with an OpenGL texture
+    /// created outside of fae, instead of `image`. The texture should
+    /// be `width` by `height` pixels, and have the internal format
+    /// `format`, e.g. `gl::RGBA8`.
+    ///
+    /// The texture's parameters, like filtering and wrapping, are
+    /// left as they are, so the smoothing and wrapping settings of the
+    /// builder are not applied to it. The texture is not deleted when
+    /// fae's [`Context`](struct.Context.html) is dropped, and
+    /// [`Spritesheet::resize_texture`](struct.Spritesheet.html#method.resize_texture)
+    /// doesn't work with it, but it can be replaced with
+    /// [`Spritesheet::set_external_texture`](struct.Spritesheet.html#method.set_external_texture).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # let mut ctx = fae::Context::new();
+    /// let mut preview_texture = 0;
+    /// unsafe {
+    ///     fae::gl::GenTextures(1, &mut preview_texture);
+    ///     // Allocate the texture, render into it with your own code, etc.
+    /// }
+    /// let preview = fae::SpritesheetBuilder::default()
+    ///     .build_with_texture(&mut ctx, preview_texture, 256, 256, fae::gl::RGBA8);
+    /// ```
+    pub fn build_with_texture(
+        &self,
+        ctx: &mut Context,
+        texture: gl::types::GLuint,
+        width: i32,
+        height: i32,
+        format: gl::types::GLuint,
+    ) -> Spritesheet {
+        let mut builder = self.without_image();
+        if let Err(err) = builder.validate_custom_attributes() {
+            log::error!("Could not create the spritesheet: {}", err);
+            builder.custom_attributes.clear();
+        }
+        let spritesheet = builder.build_unchecked(ctx);
+        ctx.renderer
+            .set_external_texture(&spritesheet.handle, texture, (width, height), format);
+        spritesheet
+    }
+
+    /// Returns a copy of this builder without the image, or the image
+    /// file it would be reloaded from, for spritesheets whose texture
+    /// is provided by fae.
+    pub(crate) fn without_image(&self) -> SpritesheetBuilder {
+        #[allow(unused_mut)]
+        let mut builder = SpritesheetBuilder {
+            image: None,
+            ..self.clone()
+        };
+        #[cfg(feature = "hot-reload")]
+        {
+            builder.image_file = None;
+        }
+        builder
+    }
+
+    /// Checks that the custom attributes have 1-4 components, which
+    /// is what `glVertexAttribPointer` accepts.
+    fn validate_custom_attributes(&self) -> Result<(), SpritesheetCreationError> {
+        for (name, components) in &self.custom_attributes {
+            if !(1..=4).contains(components) {
+                return Err(SpritesheetCreationError::InvalidCustomAttribute(
+                    name.clone(),
+                    *components,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn build_unchecked(&self, ctx: &mut Context) -> Spritesheet {
+        #[cfg(feature = "hot-reload")]
+        return self.build_hot_reloaded(ctx);
+        #[cfg(not(feature = "hot-reload"))]
+        return self.build_with(ctx, self.image.as_ref(), &self.shaders);
+    }
+
+    fn build_with(
+        &self,
+        ctx: &mut Context,
+        image: Option<&Image>,
+        shaders: &Shaders,
+    ) -> Spritesheet {
         Spritesheet {
             handle: ctx.renderer.create_draw_call(
-                self.image.as_ref(),
-                &self.shaders,
+                self.name.as_deref(),
+                image,
+                shaders,
                 self.alpha_blending,
+                self.blend_mode,
                 self.minification_smoothing,
                 self.magnification_smoothing,
                 self.wrap,
                 self.srgb,
+                &self.custom_attributes,
+                self.pixel_buffer_uploads,
             ),
         }
     }
 
+    #[cfg(feature = "hot-reload")]
+    fn build_hot_reloaded(&self, ctx: &mut Context) -> Spritesheet {
+        if self.shader_files.is_none() && self.image_file.is_none() {
+            return self.build_with(ctx, self.image.as_ref(), &self.shaders);
+        }
+
+        let shaders = match &self.shader_files {
+            Some(files) => files.load(&self.shaders).unwrap_or_else(|err| {
+                log::error!("Could not read shader files: {}", err);
+                self.shaders.clone()
+            }),
+            None => self.shaders.clone(),
+        };
+        let image = self.image_file.as_ref().and_then(|path| load_image(path));
+        let spritesheet = self.build_with(ctx, image.as_ref().or(self.image.as_ref()), &shaders);
+        ctx.renderer.watch_files(
+            &spritesheet.handle,
+            self.shaders.clone(),
+            self.custom_attributes.clone(),
+            self.shader_files.clone(),
+            self.image_file.clone(),
+        );
+        spritesheet
+    }
+
+    /// Sets the spritesheet's name, see
+    /// [`name`](#structfield.name).
+    pub fn name(&mut self, name: &str) -> &mut SpritesheetBuilder {
+        self.name = Some(name.to_string());
+        self
+    }
+
     /// Sets the spritesheet's texture.
     pub fn image(&mut self, image: Image) -> &mut SpritesheetBuilder {
         self.image = Some(image);
@@ -235,6 +525,12 @@ impl SpritesheetBuilder {
         self
     }
 
+    /// Sets the spritesheet's blend mode.
+    pub fn blend_mode(&mut self, blend_mode: BlendMode) -> &mut SpritesheetBuilder {
+        self.blend_mode = blend_mode;
+        self
+    }
+
     /// Sets the spritesheet's minification filter.
     pub fn minification_smoothing(&mut self, smoothing: bool) -> &mut SpritesheetBuilder {
         self.minification_smoothing = smoothing;
@@ -262,4 +558,78 @@ impl SpritesheetBuilder {
         self.srgb = srgb;
         self
     }
+
+    /// Declares a custom per-sprite attribute called `name`, with
+    /// `components` floats (1-4). See
+    /// [`custom_attributes`](#structfield.custom_attributes).
+    /// Other component counts make
+    /// [`try_build`](#method.try_build) fail.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # let mut ctx = fae::Context::new();
+    /// # let shaders = fae::Shaders::default();
+    /// // The shaders would contain an `in float flash;` (or
+    /// // `attribute float flash;` in legacy shaders) declaration.
+    /// let spritesheet = fae::SpritesheetBuilder::default()
+    ///     .shaders(shaders)
+    ///     .custom_attribute("flash", 1)
+    ///     .build(&mut ctx);
+    ///
+    /// # let (width, height, dpi_factor) = (0.0, 0.0, 0.0);
+    /// let mut ctx = ctx.start_frame(width, height, dpi_factor);
+    /// spritesheet.draw(&mut ctx)
+    ///     .coordinates((100.0, 100.0, 16.0, 16.0))
+    ///     .custom(&[0.5])
+    ///     .finish();
+    /// ```
+    pub fn custom_attribute(&mut self, name: &str, components: i32) -> &mut SpritesheetBuilder {
+        self.custom_attributes.push((name.to_string(), components));
+        self
+    }
+
+    /// Toggles staging texture uploads through pixel unpack
+    /// buffers. See
+    /// [`pixel_buffer_uploads`](#structfield.pixel_buffer_uploads).
+    pub fn pixel_buffer_uploads(&mut self, pixel_buffer_uploads: bool) -> &mut SpritesheetBuilder {
+        self.pixel_buffer_uploads = pixel_buffer_uploads;
+        self
+    }
+
+    /// Sets the files the spritesheet's shaders are loaded and
+    /// hot-reloaded from. See
+    /// [`shader_files`](#structfield.shader_files).
+    #[cfg(feature = "hot-reload")]
+    pub fn shader_files(&mut self, files: ShaderFiles) -> &mut SpritesheetBuilder {
+        self.shader_files = Some(files);
+        self
+    }
+
+    /// Sets the PNG file the spritesheet's texture is loaded and
+    /// hot-reloaded from. See
+    /// [`image_file`](#structfield.image_file).
+    #[cfg(feature = "hot-reload")]
+    pub fn image_file<P: Into<PathBuf>>(&mut self, path: P) -> &mut SpritesheetBuilder {
+        self.image_file = Some(path.into());
+        self
+    }
+}
+
+#[test]
+fn custom_attribute_component_counts_are_validated() {
+    let mut builder = SpritesheetBuilder::default();
+    builder
+        .custom_attribute("flash", 1)
+        .custom_attribute("tint", 4);
+    assert!(builder.validate_custom_attributes().is_ok());
+    for &components in &[0, -1, 16] {
+        let mut invalid = builder.clone();
+        invalid.custom_attribute("invalid", components);
+        match invalid.validate_custom_attributes() {
+            Err(SpritesheetCreationError::InvalidCustomAttribute(name, count)) => {
+                assert_eq!((name.as_str(), count), ("invalid", components));
+            }
+            result => panic!("expected InvalidCustomAttribute, got {:?}", result),
+        }
+    }
 }
@@ -0,0 +1,341 @@
+//! Decodes images on worker threads, and uploads the decoded images
+//! into spritesheets at the start of each frame, a limited amount of
+//! bytes at a time.
+
+use crate::api::{Context, Spritesheet, SpritesheetBuilder};
+use crate::error::{Error, SpritesheetCreationError};
+use crate::image::Image;
+use crate::types::RectPx;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The amount of bytes uploaded per frame, if the budget hasn't been
+/// set with
+/// [`Context::set_texture_upload_budget`](struct.Context.html#method.set_texture_upload_budget).
+pub(crate) const DEFAULT_UPLOAD_BUDGET: usize = 4 * 1024 * 1024;
+const MAX_WORKER_THREADS: usize = 4;
+
+/// Where the image of a spritesheet being loaded comes from.
+pub(crate) enum ImageSource {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+impl ImageSource {
+    fn decode(self) -> Result<Image, Error> {
+        match self {
+            ImageSource::File(path) => Image::from_bytes(&fs::read(path)?),
+            ImageSource::Bytes(bytes) => Image::from_bytes(&bytes),
+        }
+    }
+}
+
+struct DecodeJob {
+    id: u64,
+    source: ImageSource,
+}
+
+struct DecodedImage {
+    id: u64,
+    result: Result<Image, Error>,
+}
+
+/// The state of a [`LoadingSpritesheet`](struct.LoadingSpritesheet.html).
+#[derive(Clone, Debug)]
+pub enum LoadStatus {
+    /// The image is still being decoded, or waiting to be uploaded
+    /// to the GPU.
+    Loading,
+    /// The spritesheet has been created, and its texture is fully
+    /// uploaded.
+    Ready(Spritesheet),
+    /// The image couldn't be read or decoded.
+    Failed(Rc<Error>),
+}
+
+/// A handle to a spritesheet which is being loaded in the
+/// background. Returned by
+/// [`Context::load_spritesheet`](struct.Context.html#method.load_spritesheet).
+///
+/// The status is updated during
+/// [`Context::start_frame`](struct.Context.html#method.start_frame).
+///
+/// # Example
+/// ```no_run
+/// # let mut ctx = fae::Context::new();
+/// let loading = ctx.load_spritesheet("background.png", &fae::SpritesheetBuilder::default());
+/// # let (width, height, dpi_factor) = (0.0, 0.0, 0.0);
+/// loop {
+///     let mut graphics_ctx = ctx.start_frame(width, height, dpi_factor);
+///     if let Some(background) = loading.spritesheet() {
+///         background.draw(&mut graphics_ctx)
+///             .coordinates((0.0, 0.0, 640.0, 480.0))
+///             .finish();
+///     }
+///     graphics_ctx.finish_frame();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LoadingSpritesheet {
+    status: Rc<RefCell<LoadStatus>>,
+}
+
+impl LoadingSpritesheet {
+    /// Returns the current status of the load.
+    pub fn status(&self) -> LoadStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Returns the spritesheet, if it's ready.
+    pub fn spritesheet(&self) -> Option<Spritesheet> {
+        match &*self.status.borrow() {
+            LoadStatus::Ready(spritesheet) => Some(spritesheet.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the error that caused the load to fail, if it did.
+    pub fn error(&self) -> Option<Rc<Error>> {
+        match &*self.status.borrow() {
+            LoadStatus::Failed(err) => Some(err.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the spritesheet is still being loaded, ie. it
+    /// hasn't become ready or failed yet.
+    pub fn is_loading(&self) -> bool {
+        matches!(*self.status.borrow(), LoadStatus::Loading)
+    }
+}
+
+/// A decoded image waiting to be (fully) uploaded.
+struct PendingUpload {
+    image: Image,
+    builder: SpritesheetBuilder,
+    status: Rc<RefCell<LoadStatus>>,
+    /// Created when the image is uploaded in strips, over multiple
+    /// frames.
+    spritesheet: Option<Spritesheet>,
+    uploaded_rows: i32,
+}
+
+pub(crate) struct AssetLoader {
+    /// None if no worker threads could be spawned, in which case the
+    /// images are decoded on the calling thread.
+    jobs: Option<Sender<DecodeJob>>,
+    decoded_sender: Sender<DecodedImage>,
+    decoded: Receiver<DecodedImage>,
+    decoding: HashMap<u64, (SpritesheetBuilder, Rc<RefCell<LoadStatus>>)>,
+    uploads: VecDeque<PendingUpload>,
+    next_id: u64,
+}
+
+impl AssetLoader {
+    pub fn new() -> AssetLoader {
+        let (job_sender, job_receiver) = mpsc::channel::<DecodeJob>();
+        let (decoded_sender, decoded) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let thread_count = thread::available_parallelism()
+            .map(|n| n.get().saturating_sub(1))
+            .unwrap_or(1)
+            .clamp(1, MAX_WORKER_THREADS);
+        let mut spawned_threads = 0;
+        for i in 0..thread_count {
+            let job_receiver = job_receiver.clone();
+            let decoded_sender = decoded_sender.clone();
+            let spawn_result = thread::Builder::new()
+                .name(format!("fae-image-decoder-{}", i))
+                .spawn(move || loop {
+                    let job = match job_receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    // The channel closes when the loader is dropped.
+                    let DecodeJob { id, source } = match job {
+                        Ok(job) => job,
+                        Err(_) => return,
+                    };
+                    let result = source.decode();
+                    if decoded_sender.send(DecodedImage { id, result }).is_err() {
+                        return;
+                    }
+                });
+            match spawn_result {
+                Ok(_) => spawned_threads += 1,
+                Err(err) => log::warn!("Could not spawn an image decoding thread: {}", err),
+            }
+        }
+
+        AssetLoader {
+            jobs: if spawned_threads > 0 {
+                Some(job_sender)
+            } else {
+                None
+            },
+            decoded_sender,
+            decoded,
+            decoding: HashMap::new(),
+            uploads: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn load(
+        &mut self,
+        source: ImageSource,
+        builder: &SpritesheetBuilder,
+    ) -> LoadingSpritesheet {
+        let id = self.next_id;
+        self.next_id += 1;
+        let status = Rc::new(RefCell::new(LoadStatus::Loading));
+        self.decoding.insert(id, (builder.clone(), status.clone()));
+
+        let job = DecodeJob { id, source };
+        let job = match &self.jobs {
+            Some(jobs) => jobs.send(job).err().map(|err| err.0),
+            None => Some(job),
+        };
+        if let Some(DecodeJob { id, source }) = job {
+            let result = source.decode();
+            let _ = self.decoded_sender.send(DecodedImage { id, result });
+        }
+
+        LoadingSpritesheet { status }
+    }
+
+    /// Uploads decoded images into spritesheets, until `budget` bytes
+    /// have been uploaded. At least one upload is done if there's
+    /// anything to upload, so that large images are guaranteed to
+    /// progress even with a tiny budget.
+    pub fn update(&mut self, ctx: &mut Context, budget: usize) {
+        while let Ok(DecodedImage { id, result }) = self.decoded.try_recv() {
+            if let Some((builder, status)) = self.decoding.remove(&id) {
+                match result {
+                    Ok(image) => self.uploads.push_back(PendingUpload {
+                        image,
+                        builder,
+                        status,
+                        spritesheet: None,
+                        uploaded_rows: 0,
+                    }),
+                    Err(err) => *status.borrow_mut() = LoadStatus::Failed(Rc::new(err)),
+                }
+            }
+        }
+
+        let mut budget_left = budget;
+        let mut uploaded_anything = false;
+        while let Some(upload) = self.uploads.front_mut() {
+            let image = &upload.image;
+            // Compressed images and mip levels can't be uploaded in
+            // strips, so they're uploaded whole, possibly going over
+            // the budget.
+            let splittable = !image.is_compressed() && image.mipmaps.is_empty();
+            let size = image.pixels.len() + image.mipmaps.iter().map(Vec::len).sum::<usize>();
+            if upload.spritesheet.is_none()
+                && (size <= budget_left || (!splittable && !uploaded_anything))
+            {
+                let mut builder = upload.builder.clone();
+                *upload.status.borrow_mut() = match builder.image(image.clone()).try_build(ctx) {
+                    Ok(spritesheet) => LoadStatus::Ready(spritesheet),
+                    Err(err) => LoadStatus::Failed(Rc::new(err.into())),
+                };
+                budget_left = budget_left.saturating_sub(size);
+                uploaded_anything = true;
+                self.uploads.pop_front();
+                continue;
+            }
+
+            if !splittable {
+                break;
+            }
+            let row_size = image.pixels.len() / image.height.max(1) as usize;
+            let remaining_rows = image.height - upload.uploaded_rows;
+            let rows =
+                match rows_to_upload(row_size, remaining_rows, budget_left, uploaded_anything) {
+                    Some(rows) => rows,
+                    None => break,
+                };
+
+            let spritesheet = match &upload.spritesheet {
+                Some(spritesheet) => spritesheet.clone(),
+                None => {
+                    let mut empty_image =
+                        Image::with_null_texture(image.width, image.height, image.format);
+                    empty_image.pixel_type = image.pixel_type;
+                    let spritesheet = upload.builder.clone().image(empty_image).build(ctx);
+                    upload.spritesheet = Some(spritesheet.clone());
+                    spritesheet
+                }
+            };
+            let start = upload.uploaded_rows as usize * row_size;
+            let end = start + rows as usize * row_size;
+            let strip = Image {
+                pixels: image.pixels[start..end].to_vec(),
+                height: rows,
+                pixel_type: image.pixel_type,
+                ..Image::with_null_texture(image.width, 0, image.format)
+            };
+            let region = RectPx {
+                x: 0,
+                y: upload.uploaded_rows,
+                width: image.width,
+                height: rows,
+            };
+            if !ctx
+                .renderer
+                .upload_texture_region(&spritesheet.handle, region, &strip)
+            {
+                let err = SpritesheetCreationError::TextureUploadFailed;
+                *upload.status.borrow_mut() = LoadStatus::Failed(Rc::new(err.into()));
+                self.uploads.pop_front();
+                continue;
+            }
+            upload.uploaded_rows += rows;
+            budget_left = budget_left.saturating_sub(end - start);
+            uploaded_anything = true;
+
+            if upload.uploaded_rows >= image.height {
+                *upload.status.borrow_mut() = LoadStatus::Ready(spritesheet);
+                self.uploads.pop_front();
+            }
+        }
+    }
+}
+
+/// Returns how many rows of `row_size` bytes fit in the budget, at
+/// least one if nothing has been uploaded yet this frame, or None if
+/// none should be uploaded.
+fn rows_to_upload(
+    row_size: usize,
+    remaining_rows: i32,
+    budget_left: usize,
+    uploaded_anything: bool,
+) -> Option<i32> {
+    let rows_in_budget = (budget_left / row_size.max(1)).min(remaining_rows as usize) as i32;
+    if rows_in_budget > 0 {
+        Some(rows_in_budget)
+    } else if !uploaded_anything && remaining_rows > 0 {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn uploads_are_split_by_budget() {
+    assert_eq!(rows_to_upload(400, 100, 4000, false), Some(10));
+    assert_eq!(rows_to_upload(400, 5, 4000, true), Some(5));
+    assert_eq!(rows_to_upload(400, 100, 100, false), Some(1));
+    assert_eq!(rows_to_upload(400, 100, 100, true), None);
+    assert_eq!(rows_to_upload(400, 0, 4000, false), None);
+}
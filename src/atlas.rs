@@ -0,0 +1,426 @@
+//! Loading sprite sheet data exported by Aseprite and TexturePacker.
+use crate::animation::{Animation, AnimationFrame, PlaybackMode};
+use crate::api::{Context, Spritesheet, SpritesheetBuilder};
+use crate::error::{AtlasLoadingError, Error};
+use crate::image::Image;
+use crate::types::{Rect, Region};
+
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// The duration of frames that don't specify one, in seconds.
+const DEFAULT_FRAME_DURATION: f32 = 0.1;
+
+/// A frame (a named region) of a sprite sheet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasFrame {
+    /// The name of the frame. For array-formatted data, this is the
+    /// `filename` field, if it exists.
+    pub name: String,
+    /// The area of the texture (in actual pixels, in the texture's
+    /// coordinate space) the frame occupies. If the frame is
+    /// rotated, this is the rotated area, i.e. the width and height
+    /// are swapped compared to the sprite.
+    pub area: Rect,
+    /// Whether the frame has been rotated 90 degrees clockwise in the
+    /// texture to save space.
+    pub rotated: bool,
+    /// The position and size of the trimmed frame inside the original
+    /// image (`spriteSourceSize`). Equal to (0, 0, source width,
+    /// source height) if the frame isn't trimmed.
+    pub trimmed_area: Rect,
+    /// The size of the original image, before trimming
+    /// (`sourceSize`).
+    pub source_size: (f32, f32),
+    /// How long the frame is shown in animations, in seconds. The
+    /// default is 0.1 seconds, if the data doesn't specify it.
+    pub duration: f32,
+}
+
+impl AtlasFrame {
+    /// Returns the region of the texture the frame occupies, for
+    /// drawing it with
+    /// [`Sprite::region`](struct.Sprite.html#method.region).
+    pub fn region(&self) -> Region {
+        Region {
+            area: self.area,
+            rotated: self.rotated,
+            trim_offset: (self.trimmed_area.x, self.trimmed_area.y),
+            source_size: self.source_size,
+        }
+    }
+}
+
+/// A named area of the sprite (a slice in Aseprite), which can be
+/// different on different frames.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasSlice {
+    /// The name of the slice.
+    pub name: String,
+    /// The keyframes of the slice. Each key applies from its frame
+    /// until the next key.
+    pub keys: Vec<AtlasSliceKey>,
+}
+
+/// The state of an [`AtlasSlice`](struct.AtlasSlice.html) starting
+/// from a frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasSliceKey {
+    /// The index of the frame this key starts applying from.
+    pub frame: usize,
+    /// The area of the slice, relative to the top-left corner of the
+    /// original (untrimmed) image.
+    pub bounds: Rect,
+    /// The center area of a nine-slice, relative to `bounds`.
+    pub center: Option<Rect>,
+    /// The pivot point, relative to `bounds`.
+    pub pivot: Option<(f32, f32)>,
+}
+
+/// Sprite sheet data exported from Aseprite or TexturePacker.
+///
+/// Both the hash (`"frames": { "name": { ... } }`) and array
+/// (`"frames": [ { "filename": "name", ... } ]`) formats are
+/// supported. Aseprite's frame tags are turned into
+/// [`Animation`](struct.Animation.html)s, and its slices into
+/// [`AtlasSlice`](struct.AtlasSlice.html)s.
+///
+/// Requires the `sheet-import` feature.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), fae::Error> {
+/// # let mut ctx = fae::Context::new();
+/// use fae::{AnimationPlayer, Atlas, SpritesheetBuilder};
+/// // Loads the JSON, and the PNG it refers to, relative to the JSON file.
+/// let mut builder = SpritesheetBuilder::default();
+/// builder.magnification_smoothing(false);
+/// let (spritesheet, atlas) = Atlas::load(&mut ctx, "sprites/hero.json", &builder)?;
+/// let mut player = AnimationPlayer::new(atlas.animation("walk").unwrap().clone());
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Atlas {
+    /// The frames, in the same order as in the data, for both
+    /// array- and hash-formatted data.
+    pub frames: Vec<AtlasFrame>,
+    /// The animations, named by their tags.
+    pub animations: Vec<(String, Animation)>,
+    /// The slices.
+    pub slices: Vec<AtlasSlice>,
+    /// The path to the image, relative to the data (`meta.image`).
+    pub image: Option<String>,
+}
+
+impl Atlas {
+    /// Parses sprite sheet data from Aseprite's or TexturePacker's
+    /// JSON format.
+    pub fn from_json(json: &str) -> Result<Atlas, AtlasLoadingError> {
+        let root: Value = serde_json::from_str(json)?;
+        let frames = match root.get("frames") {
+            Some(Value::Object(frames)) => frames
+                .iter()
+                .map(|(name, frame)| parse_frame(name.clone(), frame))
+                .collect::<Result<Vec<AtlasFrame>, AtlasLoadingError>>()?,
+            Some(Value::Array(frames)) => frames
+                .iter()
+                .enumerate()
+                .map(|(i, frame)| {
+                    let name = match frame.get("filename").and_then(Value::as_str) {
+                        Some(name) => name.to_string(),
+                        None => i.to_string(),
+                    };
+                    parse_frame(name, frame)
+                })
+                .collect::<Result<Vec<AtlasFrame>, AtlasLoadingError>>()?,
+            _ => return Err(invalid("frames")),
+        };
+
+        let meta = root.get("meta").and_then(Value::as_object);
+        let get_meta_array = |name| {
+            meta.and_then(|meta| meta.get(name))
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or(&[])
+        };
+        let animations = get_meta_array("frameTags")
+            .iter()
+            .map(|tag| parse_tag(tag, &frames))
+            .collect::<Result<Vec<(String, Animation)>, AtlasLoadingError>>()?;
+        let slices = get_meta_array("slices")
+            .iter()
+            .map(parse_slice)
+            .collect::<Result<Vec<AtlasSlice>, AtlasLoadingError>>()?;
+        let image = meta
+            .and_then(|meta| meta.get("image"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        Ok(Atlas {
+            frames,
+            animations,
+            slices,
+            image,
+        })
+    }
+
+    /// Loads the sprite sheet data from the JSON file at `path`, and
+    /// creates a spritesheet from the PNG image it refers to, with
+    /// the settings of `builder`. The image path is relative to the
+    /// JSON file.
+    pub fn load<P: AsRef<Path>>(
+        ctx: &mut Context,
+        path: P,
+        builder: &SpritesheetBuilder,
+    ) -> Result<(Spritesheet, Atlas), Error> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(AtlasLoadingError::from)?;
+        let atlas = Atlas::from_json(&json)?;
+        let image_path = match &atlas.image {
+            Some(image) => path.with_file_name(image),
+            None => return Err(AtlasLoadingError::MissingImagePath.into()),
+        };
+        let bytes = fs::read(image_path).map_err(AtlasLoadingError::from)?;
+        let image = Image::with_png(&bytes)?;
+        let spritesheet = builder.clone().image(image).build(ctx);
+        Ok((spritesheet, atlas))
+    }
+
+    /// Returns the frame with the name.
+    pub fn frame(&self, name: &str) -> Option<&AtlasFrame> {
+        self.frames.iter().find(|frame| frame.name == name)
+    }
+
+    /// Returns the animation with the name.
+    pub fn animation(&self, name: &str) -> Option<&Animation> {
+        self.animations
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, animation)| animation)
+    }
+
+    /// Returns the slice with the name.
+    pub fn slice(&self, name: &str) -> Option<&AtlasSlice> {
+        self.slices.iter().find(|slice| slice.name == name)
+    }
+}
+
+fn parse_frame(name: String, frame: &Value) -> Result<AtlasFrame, AtlasLoadingError> {
+    let frame = frame.as_object().ok_or_else(|| invalid("frames"))?;
+    let rotated = frame
+        .get("rotated")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let mut area = get_rect(frame, "frame")?;
+    // The frame's size is the size of the sprite, not the area in the
+    // texture.
+    if rotated {
+        std::mem::swap(&mut area.width, &mut area.height);
+    }
+    let (sprite_width, sprite_height) = if rotated {
+        (area.height, area.width)
+    } else {
+        (area.width, area.height)
+    };
+    let source_size = match frame.get("sourceSize").and_then(Value::as_object) {
+        Some(size) => (get_f32(size, "w")?, get_f32(size, "h")?),
+        None => (sprite_width, sprite_height),
+    };
+    let trimmed_area = if frame.contains_key("spriteSourceSize") {
+        get_rect(frame, "spriteSourceSize")?
+    } else {
+        (0.0, 0.0, sprite_width, sprite_height).into()
+    };
+    let duration = match frame.get("duration").and_then(Value::as_f64) {
+        Some(milliseconds) => milliseconds as f32 / 1000.0,
+        None => DEFAULT_FRAME_DURATION,
+    };
+    Ok(AtlasFrame {
+        name,
+        area,
+        rotated,
+        trimmed_area,
+        source_size,
+        duration,
+    })
+}
+
+fn parse_tag(tag: &Value, frames: &[AtlasFrame]) -> Result<(String, Animation), AtlasLoadingError> {
+    let tag = tag.as_object().ok_or_else(|| invalid("frameTags"))?;
+    let name = get_str(tag, "name")?.to_string();
+    let from = get_f32(tag, "from")? as usize;
+    let to = get_f32(tag, "to")? as usize;
+    if from > to || to >= frames.len() {
+        return Err(invalid("frameTags.to"));
+    }
+    let mut animation_frames: Vec<AnimationFrame> = frames[from..=to]
+        .iter()
+        .map(|frame| AnimationFrame {
+            region: frame.region(),
+            duration: frame.duration,
+            tag: None,
+        })
+        .collect();
+    let direction = tag.get("direction").and_then(Value::as_str);
+    let mode = match direction {
+        Some("pingpong") | Some("pingpong_reverse") => PlaybackMode::PingPong,
+        _ => PlaybackMode::Loop,
+    };
+    if let Some("reverse") | Some("pingpong_reverse") = direction {
+        animation_frames.reverse();
+    }
+    Ok((
+        name,
+        Animation {
+            frames: animation_frames,
+            mode,
+        },
+    ))
+}
+
+fn parse_slice(slice: &Value) -> Result<AtlasSlice, AtlasLoadingError> {
+    let slice = slice.as_object().ok_or_else(|| invalid("slices"))?;
+    let name = get_str(slice, "name")?.to_string();
+    let keys = match slice.get("keys") {
+        Some(Value::Array(keys)) => keys,
+        _ => return Err(invalid("slices.keys")),
+    };
+    let keys = keys
+        .iter()
+        .map(|key| {
+            let key = key.as_object().ok_or_else(|| invalid("slices.keys"))?;
+            let pivot = match key.get("pivot").and_then(Value::as_object) {
+                Some(pivot) => Some((get_f32(pivot, "x")?, get_f32(pivot, "y")?)),
+                None => None,
+            };
+            let center = if key.contains_key("center") {
+                Some(get_rect(key, "center")?)
+            } else {
+                None
+            };
+            Ok(AtlasSliceKey {
+                frame: get_f32(key, "frame")? as usize,
+                bounds: get_rect(key, "bounds")?,
+                center,
+                pivot,
+            })
+        })
+        .collect::<Result<Vec<AtlasSliceKey>, AtlasLoadingError>>()?;
+    Ok(AtlasSlice { name, keys })
+}
+
+fn invalid(field: &str) -> AtlasLoadingError {
+    AtlasLoadingError::InvalidField(field.to_string())
+}
+
+fn get_f32(object: &Map<String, Value>, field: &str) -> Result<f32, AtlasLoadingError> {
+    match object.get(field).and_then(Value::as_f64) {
+        Some(value) => Ok(value as f32),
+        None => Err(invalid(field)),
+    }
+}
+
+fn get_str<'a>(object: &'a Map<String, Value>, field: &str) -> Result<&'a str, AtlasLoadingError> {
+    object
+        .get(field)
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(field))
+}
+
+/// Parses a `{ "x": 0, "y": 0, "w": 0, "h": 0 }` object.
+fn get_rect(object: &Map<String, Value>, field: &str) -> Result<Rect, AtlasLoadingError> {
+    let rect = object
+        .get(field)
+        .and_then(Value::as_object)
+        .ok_or_else(|| invalid(field))?;
+    Ok(Rect {
+        x: get_f32(rect, "x")?,
+        y: get_f32(rect, "y")?,
+        width: get_f32(rect, "w")?,
+        height: get_f32(rect, "h")?,
+    })
+}
+
+#[test]
+fn aseprite_and_texture_packer_data_is_parsed() {
+    let aseprite = r##"{
+        "frames": [
+            { "filename": "hero 0.aseprite", "frame": { "x": 0, "y": 0, "w": 16, "h": 16 },
+              "rotated": false, "trimmed": false,
+              "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 },
+              "sourceSize": { "w": 16, "h": 16 }, "duration": 100 },
+            { "filename": "hero 1.aseprite", "frame": { "x": 16, "y": 0, "w": 16, "h": 16 },
+              "rotated": false, "trimmed": false,
+              "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 },
+              "sourceSize": { "w": 16, "h": 16 }, "duration": 250 }
+        ],
+        "meta": {
+            "image": "hero.png",
+            "frameTags": [ { "name": "walk", "from": 0, "to": 1, "direction": "reverse" } ],
+            "slices": [ { "name": "button", "color": "#0000ffff", "keys": [
+                { "frame": 0, "bounds": { "x": 0, "y": 0, "w": 16, "h": 16 },
+                  "center": { "x": 4, "y": 4, "w": 8, "h": 8 } }
+            ] } ]
+        }
+    }"##;
+    let atlas = Atlas::from_json(aseprite).unwrap();
+    assert_eq!(atlas.image.as_deref(), Some("hero.png"));
+    assert_eq!(atlas.frames[1].name, "hero 1.aseprite");
+    let walk = atlas.animation("walk").unwrap();
+    assert_eq!(walk.frames[0].region, (16, 0, 16, 16).into());
+    assert_eq!(walk.frames[0].duration, 0.25);
+    let center = atlas.slice("button").unwrap().keys[0].center;
+    assert_eq!(center, Some((4, 4, 8, 8).into()));
+
+    let texture_packer = r#"{
+        "frames": {
+            "coin.png": { "frame": { "x": 2, "y": 2, "w": 10, "h": 20 },
+                "rotated": true, "trimmed": true,
+                "spriteSourceSize": { "x": 3, "y": 1, "w": 10, "h": 20 },
+                "sourceSize": { "w": 16, "h": 24 } }
+        },
+        "meta": { "image": "items.png", "size": { "w": 64, "h": 64 } }
+    }"#;
+    let atlas = Atlas::from_json(texture_packer).unwrap();
+    let coin = atlas.frame("coin.png").unwrap();
+    assert!(coin.rotated);
+    assert_eq!(coin.area, (2, 2, 20, 10).into());
+    assert_eq!(coin.trimmed_area, (3, 1, 10, 20).into());
+    assert_eq!(coin.source_size, (16.0, 24.0));
+    assert_eq!(coin.region().trimmed_size(), (10.0, 20.0));
+
+    assert!(Atlas::from_json(r#"{ "meta": {} }"#).is_err());
+}
+
+#[test]
+fn hash_frames_keep_their_order() {
+    // Sorted by name, "hero 10" would come before "hero 2".
+    let frames: Vec<String> = (0..12)
+        .map(|i| {
+            format!(
+                r#""hero {}.aseprite": {{ "frame": {{ "x": {}, "y": 0, "w": 16, "h": 16 }},
+                    "rotated": false, "trimmed": false,
+                    "spriteSourceSize": {{ "x": 0, "y": 0, "w": 16, "h": 16 }},
+                    "sourceSize": {{ "w": 16, "h": 16 }}, "duration": 100 }}"#,
+                i,
+                i * 16
+            )
+        })
+        .collect();
+    let json = format!(
+        r#"{{ "frames": {{ {} }}, "meta": {{
+            "frameTags": [ {{ "name": "walk", "from": 0, "to": 11, "direction": "forward" }} ]
+        }} }}"#,
+        frames.join(",")
+    );
+    let atlas = Atlas::from_json(&json).unwrap();
+    let walk = atlas.animation("walk").unwrap();
+    let xs: Vec<f32> = walk
+        .frames
+        .iter()
+        .map(|frame| frame.region.area.x)
+        .collect();
+    let expected: Vec<f32> = (0..12).map(|i| i as f32 * 16.0).collect();
+    assert_eq!(xs, expected);
+}
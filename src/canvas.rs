@@ -0,0 +1,365 @@
+//! An RGBA image kept on the CPU and mirrored into a spritesheet,
+//! uploading only the parts that have changed.
+use crate::api::{Context, GraphicsContext, Spritesheet, SpritesheetBuilder};
+use crate::error::ImageManipulationError;
+use crate::gl;
+use crate::image::Image;
+use crate::sprite::Sprite;
+use crate::types::{Rect, RectPx};
+
+/// The amount of pixels that uploading a separate region is assumed
+/// to cost, in addition to its pixels. Dirty regions are merged when
+/// the merged region is smaller than the regions and this overhead.
+const UPLOAD_OVERHEAD_PIXELS: i64 = 1024;
+/// The most regions uploaded per flush. Past this, the regions that
+/// waste the least pixels when merged are merged.
+const MAX_UPLOADS: usize = 16;
+
+/// A software-rendered texture: an RGBA image that can be drawn into
+/// on the CPU, and a spritesheet that the changes are uploaded into
+/// with [`flush`](#method.flush).
+///
+/// The canvas keeps track of the areas that have been drawn into
+/// since the last flush, and only uploads those, merging nearby areas
+/// to avoid making lots of small uploads.
+///
+/// # Example
+/// ```no_run
+/// # let mut ctx = fae::Context::new();
+/// use fae::{Canvas, SpritesheetBuilder};
+///
+/// let mut canvas = Canvas::new(&mut ctx, &SpritesheetBuilder::default(), 256, 256, [0xFF; 4]);
+///
+/// // In rendering code:
+/// # let (width, height, dpi_factor) = (0.0, 0.0, 0.0);
+/// let mut ctx = ctx.start_frame(width, height, dpi_factor);
+/// canvas.fill_rect((10, 10, 4, 4), [0x22, 0x22, 0x22, 0xFF]);
+/// canvas.set_pixel(100, 20, [0xFF, 0x00, 0x00, 0xFF]);
+/// canvas.flush(&mut ctx);
+/// canvas.draw(&mut ctx)
+///     .coordinates((0.0, 0.0, 256.0, 256.0))
+///     .texture_coordinates((0, 0, 256, 256))
+///     .finish();
+/// ```
+#[derive(Debug)]
+pub struct Canvas {
+    image: Image,
+    spritesheet: Spritesheet,
+    dirty: DirtyRegions,
+}
+
+impl Canvas {
+    /// Creates a `width` x `height` canvas filled with `color`, given
+    /// as RGBA. The spritesheet is created with the settings of the
+    /// `builder`, apart from its image.
+    pub fn new(
+        ctx: &mut Context,
+        builder: &SpritesheetBuilder,
+        width: i32,
+        height: i32,
+        color: [u8; 4],
+    ) -> Canvas {
+        let (width, height) = (width.max(0), height.max(0));
+        let image = rgba_image(width, height, color);
+        let builder = SpritesheetBuilder {
+            image: Some(image.clone()),
+            ..builder.without_image()
+        };
+        Canvas {
+            spritesheet: builder.build(ctx),
+            dirty: DirtyRegions::new(width, height),
+            image,
+        }
+    }
+
+    /// Returns the color of the pixel at (x, y) as RGBA, or None if
+    /// the coordinates are out of bounds.
+    pub fn pixel(&self, x: i32, y: i32) -> Option<[u8; 4]> {
+        let i = self.pixel_index(x, y)?;
+        let p = &self.image.pixels[i..i + 4];
+        Some([p[0], p[1], p[2], p[3]])
+    }
+
+    /// Sets the color of the pixel at (x, y). Does nothing if the
+    /// coordinates are out of bounds.
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: [u8; 4]) {
+        if let Some(i) = self.pixel_index(x, y) {
+            self.image.pixels[i..i + 4].copy_from_slice(&color);
+            self.dirty.add((x, y, 1, 1).into());
+        }
+    }
+
+    /// Fills the area with `color`. The area's coordinates are
+    /// rounded down to whole pixels, and the parts outside the canvas
+    /// are left out.
+    pub fn fill_rect<R: Into<Rect>>(&mut self, area: R, color: [u8; 4]) {
+        let area = self.dirty.clip(floor_rect(area.into()));
+        for y in area.y..area.y + area.height {
+            let start = ((y * self.image.width + area.x) * 4) as usize;
+            let end = start + area.width as usize * 4;
+            for pixel in self.image.pixels[start..end].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+        self.dirty.add(area);
+    }
+
+    /// Fills the whole canvas with `color`.
+    pub fn clear(&mut self, color: [u8; 4]) {
+        let (width, height) = (self.image.width, self.image.height);
+        self.fill_rect((0, 0, width, height), color);
+    }
+
+    /// Copies `image` into the canvas, with its top-left corner at
+    /// (x, y). See [`Image::blit`](struct.Image.html#method.blit).
+    pub fn blit(&mut self, image: &Image, x: i32, y: i32) -> Result<(), ImageManipulationError> {
+        self.image.blit(image, x, y)?;
+        self.dirty.add((x, y, image.width, image.height).into());
+        Ok(())
+    }
+
+    /// Marks an area as changed, so that it's uploaded in the next
+    /// flush. Only needed after modifying the pixels through
+    /// [`image_mut`](#method.image_mut).
+    pub fn mark_dirty<R: Into<Rect>>(&mut self, area: R) {
+        self.dirty.add(floor_rect(area.into()));
+    }
+
+    /// Returns the image that holds the canvas's pixels, in
+    /// `SRGB_ALPHA` format.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Returns the image that holds the canvas's pixels, for drawing
+    /// into it directly. The changed areas should be marked with
+    /// [`mark_dirty`](#method.mark_dirty). The image should not be
+    /// resized, or converted into another format.
+    pub fn image_mut(&mut self) -> &mut Image {
+        &mut self.image
+    }
+
+    /// Uploads the areas that have changed since the last flush into
+    /// the spritesheet. Returns the amount of uploads made.
+    pub fn flush(&mut self, ctx: &mut GraphicsContext) -> usize {
+        let regions = self.dirty.take_merged();
+        for &region in &regions {
+            let pixels = copy_region(&self.image, region);
+            self.spritesheet.upload_texture_region(ctx, region, &pixels);
+        }
+        regions.len()
+    }
+
+    /// Creates a Sprite struct for drawing the canvas, as it was at
+    /// the last flush. See
+    /// [`Spritesheet::draw`](struct.Spritesheet.html#method.draw).
+    pub fn draw<'a, 'b>(&'b self, ctx: &'a mut GraphicsContext) -> Sprite<'a, 'b> {
+        self.spritesheet.draw(ctx)
+    }
+
+    /// Returns the spritesheet that the canvas is uploaded into.
+    pub fn spritesheet(&self) -> &Spritesheet {
+        &self.spritesheet
+    }
+
+    fn pixel_index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.image.width || y >= self.image.height {
+            None
+        } else {
+            Some(((y * self.image.width + x) * 4) as usize)
+        }
+    }
+}
+
+fn rgba_image(width: i32, height: i32, color: [u8; 4]) -> Image {
+    Image {
+        pixels: color.repeat((width * height) as usize),
+        null_data: false,
+        ..Image::with_null_texture(width, height, gl::SRGB_ALPHA)
+    }
+}
+
+fn floor_rect(rect: Rect) -> RectPx {
+    RectPx {
+        x: rect.x.floor() as i32,
+        y: rect.y.floor() as i32,
+        width: rect.width.floor() as i32,
+        height: rect.height.floor() as i32,
+    }
+}
+
+/// Copies the pixels of `region` out of an RGBA image.
+fn copy_region(image: &Image, region: RectPx) -> Image {
+    let row_size = region.width as usize * 4;
+    let mut pixels = Vec::with_capacity(row_size * region.height as usize);
+    for y in region.y..region.y + region.height {
+        let start = ((y * image.width + region.x) * 4) as usize;
+        pixels.extend_from_slice(&image.pixels[start..start + row_size]);
+    }
+    Image {
+        pixels,
+        null_data: false,
+        ..Image::with_null_texture(region.width, region.height, image.format)
+    }
+}
+
+/// The areas of a canvas that have changed since the last flush.
+#[derive(Clone, Debug)]
+struct DirtyRegions {
+    width: i32,
+    height: i32,
+    regions: Vec<RectPx>,
+}
+
+impl DirtyRegions {
+    fn new(width: i32, height: i32) -> DirtyRegions {
+        DirtyRegions {
+            width,
+            height,
+            regions: Vec::new(),
+        }
+    }
+
+    /// Clamps the region to the canvas's bounds. The result can be
+    /// empty, but not negative.
+    fn clip(&self, region: RectPx) -> RectPx {
+        let x0 = region.x.clamp(0, self.width);
+        let y0 = region.y.clamp(0, self.height);
+        let x1 = (region.x + region.width.max(0)).clamp(x0, self.width);
+        let y1 = (region.y + region.height.max(0)).clamp(y0, self.height);
+        RectPx {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+        }
+    }
+
+    fn add(&mut self, region: RectPx) {
+        let region = self.clip(region);
+        if region.width == 0 || region.height == 0 {
+            return;
+        }
+        if self.regions.iter().any(|&other| contains(other, region)) {
+            return;
+        }
+        self.regions.retain(|&other| !contains(region, other));
+        self.regions.push(region);
+        // Keep the list short even if the canvas isn't flushed for a
+        // while.
+        while self.regions.len() > MAX_UPLOADS * 4 {
+            self.merge_cheapest_pair();
+        }
+    }
+
+    /// Returns the regions to upload, and clears the list. Regions
+    /// are merged while it's cheaper to upload them together, and
+    /// until there's at most `MAX_UPLOADS` of them.
+    fn take_merged(&mut self) -> Vec<RectPx> {
+        while self.regions.len() > 1 {
+            let (i, j, waste) = self.cheapest_pair();
+            if waste > UPLOAD_OVERHEAD_PIXELS && self.regions.len() <= MAX_UPLOADS {
+                break;
+            }
+            self.merge(i, j);
+        }
+        std::mem::take(&mut self.regions)
+    }
+
+    /// Returns the pair of regions that uploads the least amount of
+    /// extra pixels when merged, and that amount.
+    fn cheapest_pair(&self) -> (usize, usize, i64) {
+        let mut cheapest = (0, 1, i64::MAX);
+        for (i, &a) in self.regions.iter().enumerate() {
+            for (j, &b) in self.regions.iter().enumerate().skip(i + 1) {
+                let waste = area(union(a, b)) - area(a) - area(b);
+                if waste < cheapest.2 {
+                    cheapest = (i, j, waste);
+                }
+            }
+        }
+        cheapest
+    }
+
+    fn merge_cheapest_pair(&mut self) {
+        let (i, j, _) = self.cheapest_pair();
+        self.merge(i, j);
+    }
+
+    fn merge(&mut self, i: usize, j: usize) {
+        let merged = union(self.regions[i], self.regions.swap_remove(j));
+        self.regions[i] = merged;
+        // The merged region can swallow others.
+        self.regions
+            .retain(|&other| other == merged || !contains(merged, other));
+    }
+}
+
+fn area(region: RectPx) -> i64 {
+    region.width as i64 * region.height as i64
+}
+
+fn contains(outer: RectPx, inner: RectPx) -> bool {
+    inner.x >= outer.x
+        && inner.y >= outer.y
+        && inner.x + inner.width <= outer.x + outer.width
+        && inner.y + inner.height <= outer.y + outer.height
+}
+
+fn union(a: RectPx, b: RectPx) -> RectPx {
+    let x0 = a.x.min(b.x);
+    let y0 = a.y.min(b.y);
+    let x1 = (a.x + a.width).max(b.x + b.width);
+    let y1 = (a.y + a.height).max(b.y + b.height);
+    RectPx {
+        x: x0,
+        y: y0,
+        width: x1 - x0,
+        height: y1 - y0,
+    }
+}
+
+#[test]
+fn dirty_regions_are_clipped_and_merged() {
+    let mut dirty = DirtyRegions::new(512, 512);
+    dirty.add((-10, -10, 20, 20).into());
+    dirty.add((2, 2, 4, 4).into());
+    dirty.add((600, 0, 10, 10).into());
+    assert_eq!(dirty.regions, vec![(0, 0, 10, 10).into()]);
+
+    // Neighboring pixels are uploaded together, far away areas
+    // separately.
+    for x in 10..20 {
+        dirty.add((x, 5, 1, 1).into());
+    }
+    dirty.add((400, 400, 50, 50).into());
+    let mut merged = dirty.take_merged();
+    merged.sort_by_key(|region| region.x);
+    let expected: Vec<RectPx> = vec![(0, 0, 20, 10).into(), (400, 400, 50, 50).into()];
+    assert_eq!(merged, expected);
+    assert!(dirty.take_merged().is_empty());
+
+    // Scattered pixels are merged down to the upload limit.
+    for i in 0..100 {
+        dirty.add((i * 5 % 512, i * 97 % 512, 1, 1).into());
+    }
+    assert!(dirty.regions.len() <= MAX_UPLOADS * 4);
+    let merged = dirty.take_merged();
+    assert!(merged.len() <= MAX_UPLOADS);
+    for i in 0..100 {
+        let pixel = (i * 5 % 512, i * 97 % 512, 1, 1).into();
+        assert!(merged.iter().any(|&region| contains(region, pixel)));
+    }
+}
+
+#[test]
+fn dirty_regions_are_copied_out_of_the_image() {
+    let mut image = rgba_image(4, 3, [0; 4]);
+    for (i, pixel) in image.pixels.chunks_exact_mut(4).enumerate() {
+        pixel[0] = i as u8;
+    }
+    let region = copy_region(&image, (1, 1, 2, 2).into());
+    assert_eq!((region.width, region.height), (2, 2));
+    let reds: Vec<u8> = region.pixels.chunks_exact(4).map(|p| p[0]).collect();
+    assert_eq!(reds, vec![5, 6, 9, 10]);
+}
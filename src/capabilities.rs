@@ -0,0 +1,207 @@
+//! Querying the limits and optional features of the OpenGL context.
+use crate::gl;
+use crate::gl::types::*;
+use crate::gl_version::{self, OpenGlApi, OpenGlVersion};
+use crate::image::compressed;
+
+use std::ffi::CStr;
+
+// From EXT_texture_filter_anisotropic (core in OpenGL 4.6), not
+// included in the 4.5 bindings.
+const MAX_TEXTURE_MAX_ANISOTROPY: GLuint = 0x84FF;
+
+/// Describes what the current OpenGL context supports.
+///
+/// Returned by
+/// [`Context::capabilities`](struct.Context.html#method.capabilities).
+/// The features are detected from the version and the extensions of
+/// the context, so a `true` means that fae can use the feature, not
+/// necessarily that it does.
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    /// The version of the context.
+    pub version: OpenGlVersion,
+    /// The newest GLSL version supported by the context, as it would
+    /// be written in a `#version` line, e.g. 330 or 100. None if the
+    /// version string couldn't be parsed.
+    pub glsl_version: Option<u32>,
+    /// The names of the extensions supported by the context.
+    pub extensions: Vec<String>,
+    /// The width and height of the largest texture the context can
+    /// create, `GL_MAX_TEXTURE_SIZE`.
+    pub max_texture_size: i32,
+    /// The amount of textures a fragment shader can sample,
+    /// `GL_MAX_TEXTURE_IMAGE_UNITS`.
+    pub max_texture_units: i32,
+    /// The highest anisotropic filtering level the context supports,
+    /// if it supports anisotropic filtering at all.
+    pub max_anisotropy: Option<f32>,
+    /// The block-compressed texture formats that textures can be
+    /// created with, see
+    /// [`Image::is_compressed`](struct.Image.html#method.is_compressed).
+    pub compressed_formats: Vec<GLuint>,
+    /// Whether `GL_FRAMEBUFFER_SRGB` can be toggled, for converting
+    /// the linear colors output by the shaders into sRGB.
+    pub srgb_framebuffer: bool,
+    /// Whether vertex attributes can advance per instance
+    /// (`glVertexAttribDivisor`), which the instanced draw calls of
+    /// fae rely on.
+    pub instanced_arrays: bool,
+    /// Whether vertex array objects (`glGenVertexArrays`) can be
+    /// created, to store the vertex attribute setup of a buffer.
+    pub vertex_array_objects: bool,
+    /// Whether textures can be uploaded asynchronously through
+    /// pixel unpack buffers, see
+    /// [`SpritesheetBuilder::pixel_buffer_uploads`](struct.SpritesheetBuilder.html#structfield.pixel_buffer_uploads).
+    pub pixel_buffer_objects: bool,
+    /// Whether the context can report errors and other messages with
+    /// a callback, through `KHR_debug` or an equivalent.
+    pub debug_output: bool,
+    /// Whether textures with sizes that aren't powers of two support
+    /// mipmaps and repeating. Without this, they can only be drawn
+    /// clamped and without mipmaps.
+    pub npot_textures: bool,
+}
+
+impl Capabilities {
+    /// Queries the capabilities of the current thread's OpenGL
+    /// context.
+    pub(crate) fn query() -> Capabilities {
+        let version = gl_version::get_version();
+        let extensions = gl_version::get_extensions(&version);
+        let compressed_formats = compressed::get_supported_formats(&version, &extensions);
+
+        let get_integer = |name: GLuint| {
+            let mut value = 0;
+            unsafe { gl::GetIntegerv(name, &mut value) };
+            value
+        };
+        let max_texture_size = get_integer(gl::MAX_TEXTURE_SIZE);
+        let max_texture_units = get_integer(gl::MAX_TEXTURE_IMAGE_UNITS);
+        let glsl_version = unsafe {
+            let version_str = gl::GetString(gl::SHADING_LANGUAGE_VERSION);
+            if version_str.is_null() {
+                None
+            } else {
+                parse_glsl_version(&CStr::from_ptr(version_str as *const _).to_string_lossy())
+            }
+        };
+
+        let mut capabilities = Capabilities {
+            version,
+            glsl_version,
+            extensions,
+            max_texture_size,
+            max_texture_units,
+            max_anisotropy: None,
+            compressed_formats,
+            srgb_framebuffer: false,
+            instanced_arrays: false,
+            vertex_array_objects: false,
+            pixel_buffer_objects: false,
+            debug_output: false,
+            npot_textures: false,
+        };
+        capabilities.detect_features();
+        if capabilities.at_least(OpenGlApi::Desktop, 4, 6)
+            || capabilities.has_extension("GL_EXT_texture_filter_anisotropic")
+            || capabilities.has_extension("GL_ARB_texture_filter_anisotropic")
+        {
+            let mut max_anisotropy = 0.0;
+            unsafe { gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy) };
+            capabilities.max_anisotropy = Some(max_anisotropy);
+        }
+        capabilities
+    }
+
+    /// Returns true if the context supports the extension, e.g.
+    /// `"GL_KHR_debug"`.
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|extension| extension == name)
+    }
+
+    /// Returns true if the context supports the block-compressed
+    /// texture format.
+    pub fn supports_compressed_format(&self, format: GLuint) -> bool {
+        self.compressed_formats.contains(&format)
+    }
+
+    /// Returns true if the context is of the `api`, and at least the
+    /// version `major`.`minor`.
+    pub(crate) fn at_least(&self, api: OpenGlApi, major: u8, minor: u8) -> bool {
+        match self.version {
+            OpenGlVersion::Available {
+                api: current_api,
+                major: current_major,
+                minor: current_minor,
+            } => current_api == api && (current_major, current_minor) >= (major, minor),
+            OpenGlVersion::Unavailable { .. } => false,
+        }
+    }
+
+    /// Sets the feature flags based on the version and extensions.
+    fn detect_features(&mut self) {
+        let desktop = |major, minor| self.at_least(OpenGlApi::Desktop, major, minor);
+        let es = |major, minor| self.at_least(OpenGlApi::ES, major, minor);
+        let has = |name| self.has_extension(name);
+
+        let srgb_framebuffer = desktop(3, 0)
+            || has("GL_ARB_framebuffer_sRGB")
+            || has("GL_EXT_framebuffer_sRGB")
+            || has("GL_EXT_sRGB_write_control");
+        let instanced_arrays = desktop(3, 3)
+            || es(3, 0)
+            || has("GL_ARB_instanced_arrays")
+            || has("GL_EXT_instanced_arrays")
+            || has("GL_ANGLE_instanced_arrays");
+        let vertex_array_objects = desktop(3, 0)
+            || es(3, 0)
+            || has("GL_ARB_vertex_array_object")
+            || has("GL_OES_vertex_array_object")
+            || has("GL_APPLE_vertex_array_object");
+        // The uploads map the buffers with glMapBufferRange.
+        let pixel_buffer_objects = desktop(3, 0)
+            || es(3, 0)
+            || ((desktop(2, 1) || has("GL_ARB_pixel_buffer_object"))
+                && has("GL_ARB_map_buffer_range"));
+        let debug_output =
+            desktop(4, 3) || es(3, 2) || has("GL_KHR_debug") || has("GL_ARB_debug_output");
+        let npot_textures = desktop(2, 0)
+            || es(3, 0)
+            || has("GL_ARB_texture_non_power_of_two")
+            || has("GL_OES_texture_npot");
+
+        self.srgb_framebuffer = srgb_framebuffer;
+        self.instanced_arrays = instanced_arrays;
+        self.vertex_array_objects = vertex_array_objects;
+        self.pixel_buffer_objects = pixel_buffer_objects;
+        self.debug_output = debug_output;
+        self.npot_textures = npot_textures;
+    }
+}
+
+/// Parses the GLSL version out of `GL_SHADING_LANGUAGE_VERSION`,
+/// e.g. "4.60 NVIDIA" into 460, and "OpenGL ES GLSL ES 3.00" into
+/// 300.
+fn parse_glsl_version(version_str: &str) -> Option<u32> {
+    let start = version_str.find(|c: char| c.is_ascii_digit())?;
+    let mut split = version_str[start..].split('.');
+    let major: u32 = split.next()?.parse().ok()?;
+    let minor: String = split
+        .next()?
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    // "1.1" means 110, as does "1.10".
+    let minor: u32 = format!("{:0<2}", minor).get(..2)?.parse().ok()?;
+    Some(major * 100 + minor)
+}
+
+#[test]
+fn glsl_versions_are_parsed() {
+    assert_eq!(parse_glsl_version("4.60 NVIDIA"), Some(460));
+    assert_eq!(parse_glsl_version("OpenGL ES GLSL ES 3.00"), Some(300));
+    assert_eq!(parse_glsl_version("OpenGL ES GLSL ES 1.0.17"), Some(100));
+    assert_eq!(parse_glsl_version("1.20"), Some(120));
+    assert_eq!(parse_glsl_version("not a version"), None);
+}
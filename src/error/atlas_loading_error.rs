@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Describes errors related to loading Aseprite and TexturePacker
+/// sprite sheet data.
+#[derive(Debug)]
+pub enum AtlasLoadingError {
+    /// The JSON or image file couldn't be read.
+    Io(io::Error),
+    /// The data isn't valid JSON.
+    Json(serde_json::Error),
+    /// The JSON is missing a required field, or a field has the
+    /// wrong type. Contains the name of the field.
+    InvalidField(String),
+    /// The JSON doesn't name the image of the sprite sheet
+    /// (`meta.image`), so it couldn't be loaded.
+    MissingImagePath,
+}
+
+impl fmt::Display for AtlasLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use AtlasLoadingError::*;
+        match self {
+            Io(err) => err.fmt(f),
+            Json(err) => err.fmt(f),
+            InvalidField(field) => write!(f, "missing or invalid field: {}", field),
+            MissingImagePath => write!(f, "the sprite sheet data doesn't specify an image"),
+        }
+    }
+}
+
+impl Error for AtlasLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AtlasLoadingError::Io(err) => Some(err),
+            AtlasLoadingError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for AtlasLoadingError {
+    fn from(other: io::Error) -> AtlasLoadingError {
+        AtlasLoadingError::Io(other)
+    }
+}
+
+impl From<serde_json::Error> for AtlasLoadingError {
+    fn from(other: serde_json::Error) -> AtlasLoadingError {
+        AtlasLoadingError::Json(other)
+    }
+}
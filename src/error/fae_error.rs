@@ -1,27 +1,102 @@
+#[cfg(feature = "sheet-import")]
+use crate::error::AtlasLoadingError;
+#[cfg(feature = "bmp")]
+use crate::error::BmpLoadingError;
+#[cfg(feature = "dds")]
+use crate::error::DdsLoadingError;
+#[cfg(feature = "gif")]
+use crate::error::GifLoadingError;
 use crate::error::ImageCreationError;
+use crate::error::ImageManipulationError;
+#[cfg(feature = "jpeg")]
+use crate::error::JpegLoadingError;
+#[cfg(feature = "ktx")]
+use crate::error::KtxLoadingError;
+#[cfg(feature = "png")]
+use crate::error::PngEncodingError;
 #[cfg(feature = "png")]
 use crate::error::PngLoadingError;
+#[cfg(feature = "qoi")]
+use crate::error::QoiLoadingError;
+use crate::error::SpritesheetCreationError;
+#[cfg(feature = "tga")]
+use crate::error::TgaLoadingError;
 
 use std::fmt;
+use std::io;
 
 /// A generic error type that wraps fae's other error types in the
 /// [`fae::errors`](errors/index.html) module.
 #[derive(Debug)]
 pub enum Error {
+    /// Reading a file failed.
+    Io(io::Error),
     /// See [`ImageCreationError`](enum.ImageCreationError.html).
     ImageCreationError(ImageCreationError),
+    /// See [`ImageManipulationError`](enum.ImageManipulationError.html).
+    ImageManipulationError(ImageManipulationError),
+    /// See [`SpritesheetCreationError`](enum.SpritesheetCreationError.html).
+    SpritesheetCreationError(SpritesheetCreationError),
     /// See [`PngLoadingError`](enum.PngLoadingError.html).
     #[cfg(feature = "png")]
     PngLoadingError(PngLoadingError),
+    /// See [`PngEncodingError`](enum.PngEncodingError.html).
+    #[cfg(feature = "png")]
+    PngEncodingError(PngEncodingError),
+    /// See [`JpegLoadingError`](enum.JpegLoadingError.html).
+    #[cfg(feature = "jpeg")]
+    JpegLoadingError(JpegLoadingError),
+    /// See [`GifLoadingError`](enum.GifLoadingError.html).
+    #[cfg(feature = "gif")]
+    GifLoadingError(GifLoadingError),
+    /// See [`QoiLoadingError`](enum.QoiLoadingError.html).
+    #[cfg(feature = "qoi")]
+    QoiLoadingError(QoiLoadingError),
+    /// See [`BmpLoadingError`](enum.BmpLoadingError.html).
+    #[cfg(feature = "bmp")]
+    BmpLoadingError(BmpLoadingError),
+    /// See [`TgaLoadingError`](enum.TgaLoadingError.html).
+    #[cfg(feature = "tga")]
+    TgaLoadingError(TgaLoadingError),
+    /// See [`KtxLoadingError`](enum.KtxLoadingError.html).
+    #[cfg(feature = "ktx")]
+    KtxLoadingError(KtxLoadingError),
+    /// See [`DdsLoadingError`](enum.DdsLoadingError.html).
+    #[cfg(feature = "dds")]
+    DdsLoadingError(DdsLoadingError),
+    /// See [`AtlasLoadingError`](enum.AtlasLoadingError.html).
+    #[cfg(feature = "sheet-import")]
+    AtlasLoadingError(AtlasLoadingError),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Error::*;
         match self {
+            Io(err) => err.fmt(f),
             ImageCreationError(err) => err.fmt(f),
+            ImageManipulationError(err) => err.fmt(f),
+            SpritesheetCreationError(err) => err.fmt(f),
             #[cfg(feature = "png")]
             PngLoadingError(err) => err.fmt(f),
+            #[cfg(feature = "png")]
+            PngEncodingError(err) => err.fmt(f),
+            #[cfg(feature = "jpeg")]
+            JpegLoadingError(err) => err.fmt(f),
+            #[cfg(feature = "gif")]
+            GifLoadingError(err) => err.fmt(f),
+            #[cfg(feature = "qoi")]
+            QoiLoadingError(err) => err.fmt(f),
+            #[cfg(feature = "bmp")]
+            BmpLoadingError(err) => err.fmt(f),
+            #[cfg(feature = "tga")]
+            TgaLoadingError(err) => err.fmt(f),
+            #[cfg(feature = "ktx")]
+            KtxLoadingError(err) => err.fmt(f),
+            #[cfg(feature = "dds")]
+            DdsLoadingError(err) => err.fmt(f),
+            #[cfg(feature = "sheet-import")]
+            AtlasLoadingError(err) => err.fmt(f),
         }
     }
 }
@@ -30,22 +105,124 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         use Error::*;
         match self {
+            Io(err) => Some(err),
             ImageCreationError(err) => Some(err),
+            ImageManipulationError(err) => Some(err),
+            SpritesheetCreationError(err) => Some(err),
             #[cfg(feature = "png")]
             PngLoadingError(err) => Some(err),
+            #[cfg(feature = "png")]
+            PngEncodingError(err) => Some(err),
+            #[cfg(feature = "jpeg")]
+            JpegLoadingError(err) => Some(err),
+            #[cfg(feature = "gif")]
+            GifLoadingError(err) => Some(err),
+            #[cfg(feature = "qoi")]
+            QoiLoadingError(err) => Some(err),
+            #[cfg(feature = "bmp")]
+            BmpLoadingError(err) => Some(err),
+            #[cfg(feature = "tga")]
+            TgaLoadingError(err) => Some(err),
+            #[cfg(feature = "ktx")]
+            KtxLoadingError(err) => Some(err),
+            #[cfg(feature = "dds")]
+            DdsLoadingError(err) => Some(err),
+            #[cfg(feature = "sheet-import")]
+            AtlasLoadingError(err) => Some(err),
         }
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
 impl From<ImageCreationError> for Error {
     fn from(error: ImageCreationError) -> Error {
         Error::ImageCreationError(error)
     }
 }
 
+impl From<ImageManipulationError> for Error {
+    fn from(error: ImageManipulationError) -> Error {
+        Error::ImageManipulationError(error)
+    }
+}
+
+impl From<SpritesheetCreationError> for Error {
+    fn from(error: SpritesheetCreationError) -> Error {
+        Error::SpritesheetCreationError(error)
+    }
+}
+
 #[cfg(feature = "png")]
 impl From<PngLoadingError> for Error {
     fn from(error: PngLoadingError) -> Error {
         Error::PngLoadingError(error)
     }
 }
+
+#[cfg(feature = "png")]
+impl From<PngEncodingError> for Error {
+    fn from(error: PngEncodingError) -> Error {
+        Error::PngEncodingError(error)
+    }
+}
+
+#[cfg(feature = "jpeg")]
+impl From<JpegLoadingError> for Error {
+    fn from(error: JpegLoadingError) -> Error {
+        Error::JpegLoadingError(error)
+    }
+}
+
+#[cfg(feature = "gif")]
+impl From<GifLoadingError> for Error {
+    fn from(error: GifLoadingError) -> Error {
+        Error::GifLoadingError(error)
+    }
+}
+
+#[cfg(feature = "qoi")]
+impl From<QoiLoadingError> for Error {
+    fn from(error: QoiLoadingError) -> Error {
+        Error::QoiLoadingError(error)
+    }
+}
+
+#[cfg(feature = "bmp")]
+impl From<BmpLoadingError> for Error {
+    fn from(error: BmpLoadingError) -> Error {
+        Error::BmpLoadingError(error)
+    }
+}
+
+#[cfg(feature = "tga")]
+impl From<TgaLoadingError> for Error {
+    fn from(error: TgaLoadingError) -> Error {
+        Error::TgaLoadingError(error)
+    }
+}
+
+#[cfg(feature = "ktx")]
+impl From<KtxLoadingError> for Error {
+    fn from(error: KtxLoadingError) -> Error {
+        Error::KtxLoadingError(error)
+    }
+}
+
+#[cfg(feature = "dds")]
+impl From<DdsLoadingError> for Error {
+    fn from(error: DdsLoadingError) -> Error {
+        Error::DdsLoadingError(error)
+    }
+}
+
+#[cfg(feature = "sheet-import")]
+impl From<AtlasLoadingError> for Error {
+    fn from(error: AtlasLoadingError) -> Error {
+        Error::AtlasLoadingError(error)
+    }
+}
@@ -0,0 +1,40 @@
+use std::error::Error;
+use std::fmt;
+
+/// Describes errors related to parsing BMP images.
+#[derive(Debug)]
+pub enum BmpLoadingError {
+    /// The data ended before the whole image could be read.
+    UnexpectedEof,
+    /// The data doesn't start with a BMP header.
+    InvalidHeader,
+    /// Only 1, 4, 8, 24 and 32 bits per pixel are supported.
+    UnsupportedBitDepth(u16),
+    /// Only uncompressed (`BI_RGB`) and `BI_BITFIELDS` images are
+    /// supported, the run-length encoded ones are not.
+    UnsupportedCompression(u32),
+}
+
+impl fmt::Display for BmpLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use BmpLoadingError::*;
+        match self {
+            UnexpectedEof => write!(f, "unexpected end of bmp data"),
+            InvalidHeader => write!(f, "invalid bmp header"),
+            UnsupportedBitDepth(bit_depth) => write!(
+                f,
+                "unsupported bit depth (not 1, 4, 8, 24 or 32): {}",
+                bit_depth
+            ),
+            UnsupportedCompression(compression) => {
+                write!(f, "unsupported bmp compression method: {}", compression)
+            }
+        }
+    }
+}
+
+impl Error for BmpLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
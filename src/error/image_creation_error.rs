@@ -8,6 +8,11 @@ pub enum ImageCreationError {
     /// components (only red, green, blue and alpha components are
     /// supported) or no components at all.
     InvalidColorComponentCount(usize),
+    /// The image format couldn't be recognized from the data.
+    UnrecognizedFormat,
+    /// The image is in a format whose cargo feature is not enabled.
+    /// Contains the name of the feature.
+    FormatNotEnabled(&'static str),
 }
 
 impl fmt::Display for ImageCreationError {
@@ -16,6 +21,12 @@ impl fmt::Display for ImageCreationError {
             ImageCreationError::InvalidColorComponentCount(count) => {
                 write!(f, "unsupported color component count (not 1-4): {}", count)
             }
+            ImageCreationError::UnrecognizedFormat => write!(f, "unrecognized image format"),
+            ImageCreationError::FormatNotEnabled(feature) => write!(
+                f,
+                "image format not supported without the `{}` feature",
+                feature
+            ),
         }
     }
 }
@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::fmt;
+
+/// Describes errors related to parsing DDS images.
+#[derive(Debug)]
+pub enum DdsLoadingError {
+    /// The data doesn't start with the DDS magic number and header.
+    InvalidHeader,
+    /// The data ended before the whole image could be read.
+    UnexpectedEof,
+    /// The texture is a cube map, an array texture or a volume
+    /// texture, while only 2D textures are supported.
+    UnsupportedTextureType,
+    /// The FourCC code of the pixel format isn't `DXT1`, `DXT3`,
+    /// `DXT5`, `ATI1`, `BC4U`, `ATI2`, `BC5U` or `DX10`.
+    UnsupportedFourCc([u8; 4]),
+    /// The DXGI format of a `DX10` texture isn't one of the
+    /// supported BC1-BC5, BC6H, BC7 or 8-bit RGBA/BGRA formats.
+    UnsupportedDxgiFormat(u32),
+    /// The texture is uncompressed, but not 32 bits per pixel.
+    UnsupportedPixelFormat,
+}
+
+impl fmt::Display for DdsLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DdsLoadingError::*;
+        match self {
+            InvalidHeader => write!(f, "invalid dds header"),
+            UnexpectedEof => write!(f, "unexpected end of dds data"),
+            UnsupportedTextureType => write!(f, "only 2d dds textures are supported"),
+            UnsupportedFourCc(four_cc) => write!(
+                f,
+                "unsupported dds fourcc: {}",
+                String::from_utf8_lossy(four_cc)
+            ),
+            UnsupportedDxgiFormat(format) => write!(f, "unsupported dxgi format: {}", format),
+            UnsupportedPixelFormat => write!(f, "unsupported uncompressed dds pixel format"),
+        }
+    }
+}
+
+impl Error for DdsLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::fmt;
+
+/// Describes errors related to parsing GIF images.
+#[derive(Debug)]
+pub enum GifLoadingError {
+    /// The GIF doesn't contain any frames.
+    NoFrames,
+    /// The GIF's logical screen is too large to decode, over 50 MB
+    /// in RGBA. Contains the width and height.
+    ScreenTooLarge(usize, usize),
+    /// The first frame of the GIF has no pixels.
+    EmptyFrame,
+    /// If the data isn't a valid GIF image, this will describe the
+    /// details.
+    GifError(gif::DecodingError),
+}
+
+impl fmt::Display for GifLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use GifLoadingError::*;
+        match self {
+            NoFrames => write!(f, "the gif doesn't contain any frames"),
+            ScreenTooLarge(width, height) => {
+                write!(f, "the gif's screen is too large: {}x{}", width, height)
+            }
+            EmptyFrame => write!(f, "the gif's first frame is empty"),
+            GifError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for GifLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GifLoadingError::GifError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<gif::DecodingError> for GifLoadingError {
+    fn from(other: gif::DecodingError) -> GifLoadingError {
+        GifLoadingError::GifError(other)
+    }
+}
@@ -0,0 +1,44 @@
+use std::error::Error;
+use std::fmt;
+
+/// Describes errors related to parsing JPEG images.
+#[derive(Debug)]
+pub enum JpegLoadingError {
+    /// Only grayscale and RGB images are supported, CMYK images are
+    /// not.
+    UnsupportedPixelFormat(jpeg_decoder::PixelFormat),
+    /// If the data isn't a valid JPEG image, this will describe the
+    /// details.
+    JpegError(jpeg_decoder::Error),
+}
+
+impl fmt::Display for JpegLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use JpegLoadingError::*;
+        match self {
+            UnsupportedPixelFormat(pixel_format) => {
+                write!(
+                    f,
+                    "unsupported pixel format (not L8 or RGB24): {:?}",
+                    pixel_format
+                )
+            }
+            JpegError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for JpegLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JpegLoadingError::JpegError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<jpeg_decoder::Error> for JpegLoadingError {
+    fn from(other: jpeg_decoder::Error) -> JpegLoadingError {
+        JpegLoadingError::JpegError(other)
+    }
+}
@@ -0,0 +1,40 @@
+use std::error::Error;
+use std::fmt;
+
+/// Describes errors related to parsing KTX images.
+#[derive(Debug)]
+pub enum KtxLoadingError {
+    /// The data doesn't start with the KTX 1.1 identifier.
+    InvalidIdentifier,
+    /// The data ended before the whole image could be read.
+    UnexpectedEof,
+    /// The texture is a cube map, an array texture or a 3D texture,
+    /// while only 2D textures are supported.
+    UnsupportedTextureType,
+    /// The texture's internal format (`glInternalFormat`) isn't one
+    /// of the supported compressed formats, or for uncompressed
+    /// textures, the format and type (`glFormat` and `glType`) aren't
+    /// supported.
+    UnsupportedFormat(u32),
+    /// The size of a mip level doesn't match its dimensions.
+    InvalidImageSize,
+}
+
+impl fmt::Display for KtxLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use KtxLoadingError::*;
+        match self {
+            InvalidIdentifier => write!(f, "not a ktx 1.1 file"),
+            UnexpectedEof => write!(f, "unexpected end of ktx data"),
+            UnsupportedTextureType => write!(f, "only 2d ktx textures are supported"),
+            UnsupportedFormat(format) => write!(f, "unsupported ktx format: 0x{:X}", format),
+            InvalidImageSize => write!(f, "ktx mip level size doesn't match its dimensions"),
+        }
+    }
+}
+
+impl Error for KtxLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
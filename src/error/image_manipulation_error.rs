@@ -0,0 +1,40 @@
+use crate::gl::types::GLuint;
+use std::error::Error;
+use std::fmt;
+
+/// Describes errors during the manipulation of an image's pixels.
+#[derive(Debug)]
+pub enum ImageManipulationError {
+    /// The image's format or pixel type isn't supported. Contains the
+    /// format and the pixel type. Supported formats are `RED`, `RG`,
+    /// `RGB`, `RGBA`, `SRGB` and `SRGB_ALPHA`, with `UNSIGNED_BYTE` or
+    /// `UNSIGNED_SHORT` pixels.
+    UnsupportedFormat(GLuint, GLuint),
+    /// The image doesn't have pixel data (it was created with
+    /// [`Image::with_null_texture`](../struct.Image.html#method.with_null_texture)).
+    NullData,
+    /// The length of the image's pixel data doesn't match its width,
+    /// height and format.
+    InvalidPixelCount,
+}
+
+impl fmt::Display for ImageManipulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ImageManipulationError::*;
+        match self {
+            UnsupportedFormat(format, pixel_type) => write!(
+                f,
+                "unsupported image format {:#X} with pixel type {:#X}",
+                format, pixel_type
+            ),
+            NullData => write!(f, "the image doesn't have pixel data"),
+            InvalidPixelCount => write!(f, "the image's pixel data is the wrong size"),
+        }
+    }
+}
+
+impl Error for ImageManipulationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
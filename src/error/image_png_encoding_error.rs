@@ -0,0 +1,58 @@
+use crate::error::ImageManipulationError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Describes errors related to encoding images as PNG.
+#[derive(Debug)]
+pub enum PngEncodingError {
+    /// The image's pixels can't be read, e.g. because its format isn't
+    /// supported. PNG encoding supports the same formats as the image
+    /// manipulation functions.
+    InvalidImage(ImageManipulationError),
+    /// The file couldn't be written.
+    Io(io::Error),
+    /// The `png` crate failed to encode the image, this will describe
+    /// the details.
+    PngError(png::EncodingError),
+}
+
+impl fmt::Display for PngEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PngEncodingError::*;
+        match self {
+            InvalidImage(err) => err.fmt(f),
+            Io(err) => err.fmt(f),
+            PngError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for PngEncodingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use PngEncodingError::*;
+        match self {
+            InvalidImage(err) => Some(err),
+            Io(err) => Some(err),
+            PngError(err) => Some(err),
+        }
+    }
+}
+
+impl From<ImageManipulationError> for PngEncodingError {
+    fn from(other: ImageManipulationError) -> PngEncodingError {
+        PngEncodingError::InvalidImage(other)
+    }
+}
+
+impl From<io::Error> for PngEncodingError {
+    fn from(other: io::Error) -> PngEncodingError {
+        PngEncodingError::Io(other)
+    }
+}
+
+impl From<png::EncodingError> for PngEncodingError {
+    fn from(other: png::EncodingError) -> PngEncodingError {
+        PngEncodingError::PngError(other)
+    }
+}
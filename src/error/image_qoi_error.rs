@@ -0,0 +1,32 @@
+use std::error::Error;
+use std::fmt;
+
+/// Describes errors related to parsing QOI images.
+#[derive(Debug)]
+pub enum QoiLoadingError {
+    /// If the data isn't a valid QOI image, this will describe the
+    /// details.
+    QoiError(qoi::Error),
+}
+
+impl fmt::Display for QoiLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QoiLoadingError::QoiError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for QoiLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            QoiLoadingError::QoiError(err) => Some(err),
+        }
+    }
+}
+
+impl From<qoi::Error> for QoiLoadingError {
+    fn from(other: qoi::Error) -> QoiLoadingError {
+        QoiLoadingError::QoiError(other)
+    }
+}
@@ -0,0 +1,37 @@
+use std::error::Error;
+use std::fmt;
+
+/// Describes errors related to parsing TGA images.
+#[derive(Debug)]
+pub enum TgaLoadingError {
+    /// The data ended before the whole image could be read.
+    UnexpectedEof,
+    /// The image type isn't color-mapped, true-color or grayscale
+    /// (either uncompressed or run-length encoded).
+    UnsupportedImageType(u8),
+    /// The pixel depth (or color map entry size) isn't supported for
+    /// the image type: 8 and 16 bits are supported for grayscale
+    /// images, and 15, 16, 24 and 32 bits for color.
+    UnsupportedBitDepth(u8),
+}
+
+impl fmt::Display for TgaLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TgaLoadingError::*;
+        match self {
+            UnexpectedEof => write!(f, "unexpected end of tga data"),
+            UnsupportedImageType(image_type) => {
+                write!(f, "unsupported tga image type: {}", image_type)
+            }
+            UnsupportedBitDepth(bit_depth) => {
+                write!(f, "unsupported tga bit depth: {}", bit_depth)
+            }
+        }
+    }
+}
+
+impl Error for TgaLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
@@ -6,7 +6,57 @@ pub use fae_error::Error;
 mod image_creation_error;
 pub use image_creation_error::ImageCreationError;
 
+mod image_manipulation_error;
+pub use image_manipulation_error::ImageManipulationError;
+
+mod spritesheet_creation_error;
+pub use spritesheet_creation_error::SpritesheetCreationError;
+
 #[cfg(feature = "png")]
 mod image_png_error;
 #[cfg(feature = "png")]
 pub use image_png_error::PngLoadingError;
+#[cfg(feature = "png")]
+mod image_png_encoding_error;
+#[cfg(feature = "png")]
+pub use image_png_encoding_error::PngEncodingError;
+
+#[cfg(feature = "jpeg")]
+mod image_jpeg_error;
+#[cfg(feature = "jpeg")]
+pub use image_jpeg_error::JpegLoadingError;
+
+#[cfg(feature = "gif")]
+mod image_gif_error;
+#[cfg(feature = "gif")]
+pub use image_gif_error::GifLoadingError;
+
+#[cfg(feature = "qoi")]
+mod image_qoi_error;
+#[cfg(feature = "qoi")]
+pub use image_qoi_error::QoiLoadingError;
+
+#[cfg(feature = "bmp")]
+mod image_bmp_error;
+#[cfg(feature = "bmp")]
+pub use image_bmp_error::BmpLoadingError;
+
+#[cfg(feature = "tga")]
+mod image_tga_error;
+#[cfg(feature = "tga")]
+pub use image_tga_error::TgaLoadingError;
+
+#[cfg(feature = "ktx")]
+mod image_ktx_error;
+#[cfg(feature = "ktx")]
+pub use image_ktx_error::KtxLoadingError;
+
+#[cfg(feature = "dds")]
+mod image_dds_error;
+#[cfg(feature = "dds")]
+pub use image_dds_error::DdsLoadingError;
+
+#[cfg(feature = "sheet-import")]
+mod atlas_loading_error;
+#[cfg(feature = "sheet-import")]
+pub use atlas_loading_error::AtlasLoadingError;
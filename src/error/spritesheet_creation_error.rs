@@ -0,0 +1,44 @@
+use crate::gl::types::*;
+use std::error::Error;
+use std::fmt;
+
+/// Describes errors related to creating a
+/// [`Spritesheet`](struct.Spritesheet.html).
+#[derive(Debug)]
+pub enum SpritesheetCreationError {
+    /// The image is compressed in a format which isn't supported by
+    /// the current OpenGL context. Contains the format.
+    UnsupportedCompressedFormat(GLuint),
+    /// A custom attribute was declared with a component count
+    /// outside of 1-4. Contains the attribute's name and component
+    /// count.
+    InvalidCustomAttribute(String, i32),
+    /// The image couldn't be uploaded into the spritesheet's texture,
+    /// because their sizes or formats don't match.
+    TextureUploadFailed,
+}
+
+impl fmt::Display for SpritesheetCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SpritesheetCreationError::*;
+        match self {
+            UnsupportedCompressedFormat(format) => write!(
+                f,
+                "compressed texture format 0x{:X} is not supported by the opengl context",
+                format
+            ),
+            InvalidCustomAttribute(name, components) => write!(
+                f,
+                "custom attribute '{}' has {} components, should be 1-4",
+                name, components
+            ),
+            TextureUploadFailed => write!(f, "could not upload the image into the texture"),
+        }
+    }
+}
+
+impl Error for SpritesheetCreationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
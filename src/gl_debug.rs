@@ -0,0 +1,166 @@
+//! `KHR_debug` integration: forwarding the context's debug messages
+//! to the `log` crate, and naming objects and draw calls for
+//! graphics debuggers like RenderDoc and apitrace.
+use crate::capabilities::Capabilities;
+use crate::gl;
+use crate::gl::types::*;
+use crate::gl_version::OpenGlApi;
+
+use std::ffi::{c_void, CStr, CString};
+
+/// Installs a debug message callback that logs the messages of the
+/// current thread's OpenGL context, if the context supports
+/// `KHR_debug` or `ARB_debug_output`. Returns whether object labels
+/// and debug groups are supported, which requires `KHR_debug`.
+pub(crate) fn install(capabilities: &Capabilities) -> bool {
+    let khr_debug = capabilities.at_least(OpenGlApi::Desktop, 4, 3)
+        || capabilities.at_least(OpenGlApi::ES, 3, 2)
+        || capabilities.has_extension("GL_KHR_debug");
+
+    if capabilities.debug_output && gl::DebugMessageCallback::is_loaded() {
+        unsafe {
+            gl::DebugMessageCallback(Some(log_debug_message), std::ptr::null());
+            if khr_debug {
+                // ARB_debug_output doesn't have this toggle, it's
+                // always on in debug contexts.
+                gl::Enable(gl::DEBUG_OUTPUT);
+            }
+            if cfg!(debug_assertions) {
+                // Makes the callback run inside the offending GL call,
+                // so that it shows up in backtraces.
+                gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+            }
+        }
+        // Some drivers don't support the toggles, which shouldn't
+        // trip print_gl_errors later.
+        unsafe { gl::GetError() };
+        log::debug!("Installed the OpenGL debug message callback.");
+    }
+
+    khr_debug
+        && gl::ObjectLabel::is_loaded()
+        && gl::PushDebugGroup::is_loaded()
+        && gl::PopDebugGroup::is_loaded()
+}
+
+/// Names the object `name` of type `identifier` (e.g. `gl::TEXTURE`)
+/// `label`. Objects with the name 0 are skipped, as they're either
+/// defaults or weren't created.
+pub(crate) fn label(identifier: GLenum, name: GLuint, label: &str) {
+    if name == 0 {
+        return;
+    }
+    let label = to_c_string(label);
+    unsafe {
+        gl::ObjectLabel(identifier, name, -1, label.as_ptr());
+    }
+}
+
+/// Starts a debug group called `message`, which lasts until the
+/// matching [`pop_group`](fn.pop_group.html).
+pub(crate) fn push_group(message: &str) {
+    let message = to_c_string(message);
+    unsafe {
+        gl::PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, -1, message.as_ptr());
+    }
+}
+
+/// Ends the latest debug group started with
+/// [`push_group`](fn.push_group.html).
+pub(crate) fn pop_group() {
+    unsafe {
+        gl::PopDebugGroup();
+    }
+}
+
+fn to_c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+extern "system" fn log_debug_message(
+    source: GLenum,
+    message_type: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    _length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    // fae's own debug groups are echoed back as messages, which
+    // would just be noise in the logs.
+    if message_type == gl::DEBUG_TYPE_PUSH_GROUP || message_type == gl::DEBUG_TYPE_POP_GROUP {
+        return;
+    }
+    let level = get_log_level(message_type, severity);
+    if !log::log_enabled!(level) || message.is_null() {
+        return;
+    }
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    log::log!(
+        level,
+        "OpenGL {} ({}, {}, id {}): {}",
+        get_type_name(message_type),
+        get_source_name(source),
+        get_severity_name(severity),
+        id,
+        message.trim_end(),
+    );
+}
+
+fn get_log_level(message_type: GLenum, severity: GLenum) -> log::Level {
+    match (message_type, severity) {
+        (gl::DEBUG_TYPE_ERROR, _) | (_, gl::DEBUG_SEVERITY_HIGH) => log::Level::Error,
+        (_, gl::DEBUG_SEVERITY_MEDIUM) => log::Level::Warn,
+        (_, gl::DEBUG_SEVERITY_LOW) => log::Level::Info,
+        _ => log::Level::Debug,
+    }
+}
+
+fn get_type_name(message_type: GLenum) -> &'static str {
+    match message_type {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability issue",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance issue",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        _ => "message",
+    }
+}
+
+fn get_source_name(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+fn get_severity_name(severity: GLenum) -> &'static str {
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => "high",
+        gl::DEBUG_SEVERITY_MEDIUM => "medium",
+        gl::DEBUG_SEVERITY_LOW => "low",
+        _ => "notification",
+    }
+}
+
+#[test]
+fn debug_messages_are_logged_by_severity() {
+    let level = |t, s| get_log_level(t, s);
+    assert_eq!(
+        level(gl::DEBUG_TYPE_ERROR, gl::DEBUG_SEVERITY_LOW),
+        log::Level::Error
+    );
+    assert_eq!(
+        level(gl::DEBUG_TYPE_PERFORMANCE, gl::DEBUG_SEVERITY_MEDIUM),
+        log::Level::Warn
+    );
+    assert_eq!(
+        level(gl::DEBUG_TYPE_OTHER, gl::DEBUG_SEVERITY_NOTIFICATION),
+        log::Level::Debug
+    );
+}
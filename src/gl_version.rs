@@ -1,5 +1,6 @@
 //! OpenGL version parsing.
 use crate::gl;
+use crate::gl::types::*;
 use std::ffi::CStr;
 
 /// Represents the two different variants of OpenGL.
@@ -69,6 +70,45 @@ pub(crate) fn get_version() -> OpenGlVersion {
     }
 }
 
+/// Returns the names of the extensions supported by the current
+/// thread's OpenGL context.
+pub(crate) fn get_extensions(version: &OpenGlVersion) -> Vec<String> {
+    let indexed = match version {
+        OpenGlVersion::Available { major, .. } => *major >= 3,
+        OpenGlVersion::Unavailable { .. } => false,
+    };
+    unsafe {
+        if indexed && gl::GetStringi::is_loaded() {
+            // GL_EXTENSIONS isn't available via glGetString in core
+            // profiles, so the extensions are queried one by one.
+            let mut count = 0;
+            gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+            (0..count.max(0) as GLuint)
+                .filter_map(|i| {
+                    let name = gl::GetStringi(gl::EXTENSIONS, i);
+                    if name.is_null() {
+                        None
+                    } else {
+                        Some(
+                            CStr::from_ptr(name as *const _)
+                                .to_string_lossy()
+                                .into_owned(),
+                        )
+                    }
+                })
+                .collect()
+        } else {
+            let names = gl::GetString(gl::EXTENSIONS);
+            if names.is_null() {
+                Vec::new()
+            } else {
+                let names = CStr::from_ptr(names as *const _).to_string_lossy();
+                names.split_whitespace().map(String::from).collect()
+            }
+        }
+    }
+}
+
 // Sorry for the mess, but OpenGL version strings are unreliable, and
 // I'm not sure *how* unreliable. Here's my attempt at a robust way of
 // parsing the version. Returns (opengl es?, major version, minor version).
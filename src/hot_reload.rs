@@ -0,0 +1,304 @@
+//! Reloading shaders and images from files during development.
+use crate::gl_version::OpenGlApi;
+use crate::image::Image;
+use crate::shaders::{ShaderPair, Shaders};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Contains the paths to the shader files of a spritesheet, which are
+/// polled for changes every frame.
+///
+/// Passed to the renderer with
+/// [`SpritesheetBuilder::shader_files`](struct.SpritesheetBuilder.html#method.shader_files).
+/// The layout mirrors [`Shaders`](struct.Shaders.html): the shaders
+/// that don't have a path are taken from the spritesheet's `shaders`,
+/// so you can, for example, only load the fragment shaders from
+/// files. The same rules about the version preprocessor apply to
+/// the shader files as well.
+///
+/// # Example
+/// ```no_run
+/// # let mut ctx = fae::Context::new();
+/// use fae::{ShaderFiles, SpritesheetBuilder};
+///
+/// let mut files = ShaderFiles::default();
+/// files.shader_330.fragment_shader = Some("shaders/flash.frag".into());
+/// files.shader_300_es.fragment_shader = Some("shaders/flash.frag".into());
+///
+/// // Editing shaders/flash.frag while the program is running will
+/// // recompile the shaders at the start of the next frame.
+/// let spritesheet = SpritesheetBuilder::default()
+///     .shader_files(files)
+///     .build(&mut ctx);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ShaderFiles {
+    /// The paths to the `#version 330` shaders.
+    pub shader_330: ShaderPairFiles,
+    /// The paths to the `#version 110` shaders.
+    pub shader_110: ShaderPairFiles,
+    /// The paths to the `#version 300 es` shaders.
+    pub shader_300_es: ShaderPairFiles,
+    /// The paths to the `#version 100` shaders.
+    pub shader_100_es: ShaderPairFiles,
+}
+
+/// Contains the paths to a vertex shader and a fragment shader.
+///
+/// See also: [`ShaderFiles`](struct.ShaderFiles.html).
+#[derive(Clone, Debug, Default)]
+pub struct ShaderPairFiles {
+    /// The path to the vertex shader file.
+    pub vertex_shader: Option<PathBuf>,
+    /// The path to the fragment shader file.
+    pub fragment_shader: Option<PathBuf>,
+}
+
+impl ShaderFiles {
+    /// Returns a copy of `base` with the shaders replaced by the
+    /// contents of the files that have been specified.
+    pub(crate) fn load(&self, base: &Shaders) -> Result<Shaders, io::Error> {
+        let mut shaders = base.clone();
+        self.shader_330.load(&mut shaders.shader_330)?;
+        self.shader_110.load(&mut shaders.shader_110)?;
+        self.shader_300_es.load(&mut shaders.shader_300_es)?;
+        self.shader_100_es.load(&mut shaders.shader_100_es)?;
+        Ok(shaders)
+    }
+
+    /// Returns the paths of the files that are used with the given
+    /// api and legacy-ness.
+    fn active_paths(&self, api: OpenGlApi, legacy: bool) -> Vec<PathBuf> {
+        let pair = match (api, legacy) {
+            (OpenGlApi::Desktop, false) => &self.shader_330,
+            (OpenGlApi::Desktop, true) => &self.shader_110,
+            (OpenGlApi::ES, false) => &self.shader_300_es,
+            (OpenGlApi::ES, true) => &self.shader_100_es,
+        };
+        let paths = [&pair.vertex_shader, &pair.fragment_shader];
+        paths.iter().filter_map(|path| (*path).clone()).collect()
+    }
+}
+
+impl ShaderPairFiles {
+    fn load(&self, pair: &mut ShaderPair) -> Result<(), io::Error> {
+        if let Some(path) = &self.vertex_shader {
+            pair.vertex_shader = fs::read_to_string(path)?;
+        }
+        if let Some(path) = &self.fragment_shader {
+            pair.fragment_shader = fs::read_to_string(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Loads a PNG image from `path`, logging the error if that fails.
+pub(crate) fn load_image(path: &Path) -> Option<Image> {
+    let result = fs::read(path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| Image::with_png(&bytes).map_err(|err| err.to_string()));
+    match result {
+        Ok(image) => Some(image),
+        Err(err) => {
+            log::error!("Could not load image '{}': {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// A file whose modification time is tracked.
+#[derive(Clone, Debug)]
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl WatchedFile {
+    fn new(path: PathBuf) -> WatchedFile {
+        let modified = modified_time(&path);
+        WatchedFile { path, modified }
+    }
+
+    /// Returns true if the file has been modified since the last call.
+    fn poll(&mut self) -> bool {
+        let modified = modified_time(&self.path);
+        if modified.is_some() && modified != self.modified {
+            self.modified = modified;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// The files a draw call was created from, and the information
+/// needed to recreate the shaders from them.
+#[derive(Clone, Debug)]
+pub(crate) struct HotReload {
+    pub shaders: Shaders,
+    pub custom_attributes: Vec<(String, i32)>,
+    pub shader_files: Option<ShaderFiles>,
+    shader_watches: Vec<WatchedFile>,
+    image_watch: Option<WatchedFile>,
+}
+
+impl HotReload {
+    pub fn new(
+        api: OpenGlApi,
+        legacy: bool,
+        shaders: Shaders,
+        custom_attributes: Vec<(String, i32)>,
+        shader_files: Option<ShaderFiles>,
+        image_file: Option<PathBuf>,
+    ) -> HotReload {
+        let shader_watches = match &shader_files {
+            Some(files) => files.active_paths(api, legacy),
+            None => Vec::new(),
+        };
+        HotReload {
+            shaders,
+            custom_attributes,
+            shader_files,
+            shader_watches: shader_watches.into_iter().map(WatchedFile::new).collect(),
+            image_watch: image_file.map(WatchedFile::new),
+        }
+    }
+
+    /// Returns true if any of the shader files have changed since the
+    /// last call.
+    fn shaders_changed(&mut self) -> bool {
+        // Poll all of them, to avoid reloading again next frame
+        // because of a file that was changed at the same time.
+        let mut changed = false;
+        for watch in &mut self.shader_watches {
+            changed |= watch.poll();
+        }
+        changed
+    }
+
+    /// Reloads the shaders if any of the shader files have changed
+    /// since the last call, and compiles them with `compile`. Returns
+    /// the new program if that succeeded. If the files can't be read
+    /// or the shaders fail to compile, the errors are logged and None
+    /// is returned, so the previous program can be kept in use.
+    pub fn reload_shaders<P, F>(
+        &mut self,
+        api: OpenGlApi,
+        legacy: bool,
+        instanced: bool,
+        compile: F,
+    ) -> Option<P>
+    where
+        F: FnOnce(&str, &str, &[(String, i32)]) -> Result<P, Vec<String>>,
+    {
+        if !self.shaders_changed() {
+            return None;
+        }
+        let shaders = match &self.shader_files {
+            Some(files) => files.load(&self.shaders),
+            None => Ok(self.shaders.clone()),
+        };
+        let shaders = match shaders {
+            Ok(shaders) => shaders,
+            Err(err) => {
+                log::error!("Could not read shader files: {}", err);
+                return None;
+            }
+        };
+        let vert = shaders.create_vert_string(api, legacy, instanced);
+        let frag = shaders.create_frag_string(api, legacy, instanced);
+        match compile(&vert, &frag, &self.custom_attributes) {
+            Ok(program) => Some(program),
+            Err(errors) => {
+                for error in &errors {
+                    log::error!("{}", error);
+                }
+                log::error!("Shader reload failed, keeping the previous shaders.");
+                None
+            }
+        }
+    }
+
+    /// Returns the path of the image file if it has changed since the
+    /// last call.
+    pub fn changed_image(&mut self) -> Option<&Path> {
+        let watch = self.image_watch.as_mut()?;
+        if watch.poll() {
+            Some(&watch.path)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+fn write_with_mtime(path: &Path, contents: &str, seconds: u64) {
+    let file = fs::File::create(path).unwrap();
+    io::Write::write_all(&mut &file, contents.as_bytes()).unwrap();
+    let mtime = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds);
+    file.set_modified(mtime).unwrap();
+}
+
+#[test]
+fn changed_files_are_reported_once() {
+    let dir = std::env::temp_dir().join(format!("fae-watch-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("image.png");
+    write_with_mtime(&path, "", 1_000_000);
+
+    let mut watch = WatchedFile::new(path.clone());
+    assert!(!watch.poll());
+    write_with_mtime(&path, "", 2_000_000);
+    assert!(watch.poll());
+    assert!(!watch.poll());
+
+    // Missing files aren't reported as changes.
+    fs::remove_file(&path).unwrap();
+    assert!(!watch.poll());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failed_shader_reloads_keep_the_previous_program() {
+    let dir = std::env::temp_dir().join(format!("fae-reload-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("flash.frag");
+    write_with_mtime(&path, "void main() {}", 1_000_000);
+
+    let mut files = ShaderFiles::default();
+    files.shader_330.fragment_shader = Some(path.clone());
+    let attributes = vec![("flash".to_string(), 1)];
+    let mut hot_reload = HotReload::new(
+        OpenGlApi::Desktop,
+        false,
+        Shaders::default(),
+        attributes,
+        Some(files),
+        None,
+    );
+    let compile = |_: &str, frag: &str, attributes: &[(String, i32)]| {
+        assert_eq!(attributes.len(), 1);
+        if frag.contains("syntax error") {
+            Err(vec!["syntax error".to_string()])
+        } else {
+            Ok(frag.to_string())
+        }
+    };
+    let mut reload = || hot_reload.reload_shaders(OpenGlApi::Desktop, false, true, compile);
+
+    assert_eq!(reload(), None);
+    write_with_mtime(&path, "syntax error", 2_000_000);
+    assert_eq!(reload(), None);
+    write_with_mtime(&path, "void main() { }", 3_000_000);
+    let program = reload().unwrap();
+    assert!(program.starts_with("#version 330") && program.contains("void main() { }"));
+    assert_eq!(reload(), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
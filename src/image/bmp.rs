@@ -0,0 +1,270 @@
+use crate::error::BmpLoadingError;
+use crate::gl;
+use crate::image::Image;
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+impl Image {
+    /// Parses a BMP image and makes an `Image` out of it.
+    ///
+    /// Uncompressed images with 1, 4 or 8 bits per pixel (with a
+    /// palette) or 16, 24 or 32 bits per pixel are supported, as are
+    /// images with custom channel masks (`BI_BITFIELDS`). Images with
+    /// an alpha channel will have the `SRGB_ALPHA` format, the rest
+    /// `SRGB`.
+    ///
+    /// Requires the `bmp` feature.
+    ///
+    /// # Errors
+    ///
+    /// See [`BmpLoadingError`](enum.BmpLoadingError.html).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tile = fae::Image::with_bmp(&std::fs::read("tile.bmp")?)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_bmp(bytes: &[u8]) -> Result<Image, BmpLoadingError> {
+        if !bytes.starts_with(b"BM") {
+            return Err(BmpLoadingError::InvalidHeader);
+        }
+        let data_offset = read_u32(bytes, 10)? as usize;
+        let header_size = read_u32(bytes, 14)? as usize;
+        let (width, height, bit_count, compression, colors_used) = if header_size == 12 {
+            let width = read_u16(bytes, 18)? as i32;
+            let height = read_u16(bytes, 20)? as i32;
+            (width, height, read_u16(bytes, 24)?, BI_RGB, 0)
+        } else if header_size >= 40 {
+            let width = read_u32(bytes, 18)? as i32;
+            let height = read_u32(bytes, 22)? as i32;
+            let compression = read_u32(bytes, 30)?;
+            (
+                width,
+                height,
+                read_u16(bytes, 28)?,
+                compression,
+                read_u32(bytes, 46)?,
+            )
+        } else {
+            return Err(BmpLoadingError::InvalidHeader);
+        };
+        // Positive heights mean that the rows are stored bottom-up.
+        let bottom_up = height > 0;
+        if width <= 0 || height == 0 {
+            return Err(BmpLoadingError::InvalidHeader);
+        }
+        let (width, height) = (width as usize, height.unsigned_abs() as usize);
+        match bit_count {
+            1 | 4 | 8 | 16 | 24 | 32 => {}
+            _ => return Err(BmpLoadingError::UnsupportedBitDepth(bit_count)),
+        }
+
+        // The channel masks are right after the 40 byte header, either
+        // as an extension of it or as a part of a later header.
+        let masks = match compression {
+            BI_RGB if bit_count == 16 => [0x7C00, 0x03E0, 0x001F, 0],
+            BI_RGB => [0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0],
+            BI_BITFIELDS | BI_ALPHABITFIELDS => {
+                let has_alpha_mask = compression == BI_ALPHABITFIELDS || header_size >= 56;
+                [
+                    read_u32(bytes, 54)?,
+                    read_u32(bytes, 58)?,
+                    read_u32(bytes, 62)?,
+                    if has_alpha_mask {
+                        read_u32(bytes, 66)?
+                    } else {
+                        0
+                    },
+                ]
+            }
+            compression => return Err(BmpLoadingError::UnsupportedCompression(compression)),
+        };
+
+        let palette = if bit_count <= 8 {
+            let mut offset = 14 + header_size;
+            if header_size == 40 && compression == BI_BITFIELDS {
+                offset += 12;
+            } else if header_size == 40 && compression == BI_ALPHABITFIELDS {
+                offset += 16;
+            }
+            let entry_size = if header_size == 12 { 3 } else { 4 };
+            let count = match colors_used {
+                0 => 1 << bit_count,
+                count => count as usize,
+            };
+            let end = count
+                .checked_mul(entry_size)
+                .and_then(|size| size.checked_add(offset))
+                .ok_or(BmpLoadingError::UnexpectedEof)?;
+            bytes
+                .get(offset..end)
+                .ok_or(BmpLoadingError::UnexpectedEof)?
+                .chunks_exact(entry_size)
+                .map(|bgr| [bgr[2], bgr[1], bgr[0]])
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let channels = if masks[3] != 0 { 4 } else { 3 };
+        let bits_per_pixel = bit_count as usize;
+        let row_bits = width
+            .checked_mul(bits_per_pixel)
+            .ok_or(BmpLoadingError::InvalidHeader)?;
+        let row_length = row_bits.div_ceil(8);
+        let stride = row_bits.div_ceil(32) * 4;
+        // Check that all the rows are there before reserving space
+        // for the pixels, so the header can't request huge buffers.
+        let data_end = stride
+            .checked_mul(height - 1)
+            .and_then(|size| size.checked_add(row_length))
+            .and_then(|size| size.checked_add(data_offset))
+            .ok_or(BmpLoadingError::UnexpectedEof)?;
+        if data_end > bytes.len() {
+            return Err(BmpLoadingError::UnexpectedEof);
+        }
+        let mut pixels = Vec::with_capacity(width * height * channels);
+        for y in 0..height {
+            let row_start = data_offset + if bottom_up { height - 1 - y } else { y } * stride;
+            let row = bytes
+                .get(row_start..row_start + row_length)
+                .ok_or(BmpLoadingError::UnexpectedEof)?;
+            for x in 0..width {
+                match bit_count {
+                    1 | 4 | 8 => {
+                        let bit = x * bits_per_pixel;
+                        let shift = 8 - bits_per_pixel - bit % 8;
+                        let index = (row[bit / 8] >> shift) as usize & ((1 << bits_per_pixel) - 1);
+                        let color = palette.get(index).unwrap_or(&[0, 0, 0]);
+                        pixels.extend_from_slice(color);
+                    }
+                    24 => pixels.extend_from_slice(&[row[x * 3 + 2], row[x * 3 + 1], row[x * 3]]),
+                    _ => {
+                        let value = if bit_count == 16 {
+                            u32::from(u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]))
+                        } else {
+                            let i = x * 4;
+                            u32::from_le_bytes([row[i], row[i + 1], row[i + 2], row[i + 3]])
+                        };
+                        for &mask in &masks[..channels] {
+                            pixels.push(extract_channel(value, mask));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Image {
+            pixels,
+            width: width as i32,
+            height: height as i32,
+            format: if channels == 4 {
+                gl::SRGB_ALPHA
+            } else {
+                gl::SRGB
+            },
+            pixel_type: gl::UNSIGNED_BYTE,
+            null_data: false,
+            mipmaps: Vec::new(),
+        })
+    }
+}
+
+/// Extracts the bits of `value` under `mask`, scaled to 0-255.
+fn extract_channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let max = u64::from(mask >> shift);
+    let channel = u64::from((value & mask) >> shift);
+    ((channel * 255 + max / 2) / max) as u8
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, BmpLoadingError> {
+    match bytes.get(offset..offset + 2) {
+        Some(b) => Ok(u16::from_le_bytes([b[0], b[1]])),
+        None => Err(BmpLoadingError::UnexpectedEof),
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, BmpLoadingError> {
+    match bytes.get(offset..offset + 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        None => Err(BmpLoadingError::UnexpectedEof),
+    }
+}
+
+#[test]
+fn bmp_images_are_decoded() {
+    let bmp = |width: i32, height: i32, bit_count: u16, palette: &[u8], data: &[u8]| {
+        let data_offset = 54 + palette.len() as u32;
+        let mut bytes = b"BM".to_vec();
+        bytes.extend_from_slice(&(data_offset + data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&data_offset.to_le_bytes());
+        bytes.extend_from_slice(&40u32.to_le_bytes());
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&bit_count.to_le_bytes());
+        bytes.extend_from_slice(&[0; 24]);
+        bytes.extend_from_slice(palette);
+        bytes.extend_from_slice(data);
+        bytes
+    };
+
+    // 2x2, 24 bits per pixel, bottom-up rows padded to 8 bytes.
+    let image = Image::with_bmp(&bmp(
+        2,
+        2,
+        24,
+        &[],
+        &[0, 0, 255, 0, 255, 0, 0, 0, 255, 0, 0, 255, 255, 255, 0, 0],
+    ))
+    .unwrap();
+    assert_eq!(image.format, gl::SRGB);
+    assert_eq!(
+        image.pixels,
+        vec![0, 0, 255, 255, 255, 255, 255, 0, 0, 0, 255, 0]
+    );
+
+    // 3x1, 1 bit per pixel, top-down.
+    let palette = [0, 0, 0, 0, 255, 128, 0, 0];
+    let image = Image::with_bmp(&bmp(3, -1, 1, &palette, &[0b1010_0000, 0, 0, 0])).unwrap();
+    assert_eq!(image.pixels, vec![0, 128, 255, 0, 0, 0, 0, 128, 255]);
+
+    assert!(Image::with_bmp(&bmp(1, 1, 2, &[], &[0; 4])).is_err());
+    assert!(Image::with_bmp(&bmp(4, 4, 24, &[], &[0; 4])).is_err());
+}
+
+#[test]
+fn malformed_bmp_headers_are_rejected() {
+    let header = |width: i32, height: i32| {
+        let mut bytes = b"BM".to_vec();
+        bytes.extend_from_slice(&54u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&54u32.to_le_bytes());
+        bytes.extend_from_slice(&40u32.to_le_bytes());
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&24u16.to_le_bytes());
+        bytes.extend_from_slice(&[0; 24]);
+        bytes
+    };
+    assert_eq!(header(1, 1).len(), 54);
+
+    match Image::with_bmp(&header(2_000_000, 2_000_000)) {
+        Err(BmpLoadingError::UnexpectedEof) => {}
+        result => panic!("expected UnexpectedEof, got {:?}", result.map(|_| ())),
+    }
+    match Image::with_bmp(&header(-4, 4)) {
+        Err(BmpLoadingError::InvalidHeader) => {}
+        result => panic!("expected InvalidHeader, got {:?}", result.map(|_| ())),
+    }
+    assert!(Image::with_bmp(&header(i32::MAX, i32::MIN)).is_err());
+}
@@ -0,0 +1,196 @@
+//! Block-compressed texture formats, and detecting which ones the
+//! current OpenGL context supports.
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::gl_version::{OpenGlApi, OpenGlVersion};
+use crate::image::Image;
+
+// From EXT_texture_compression_s3tc and EXT_texture_sRGB, which are
+// not included in the core profile bindings.
+pub(crate) const COMPRESSED_RGB_S3TC_DXT1_EXT: GLuint = 0x83F0;
+pub(crate) const COMPRESSED_RGBA_S3TC_DXT1_EXT: GLuint = 0x83F1;
+pub(crate) const COMPRESSED_RGBA_S3TC_DXT3_EXT: GLuint = 0x83F2;
+pub(crate) const COMPRESSED_RGBA_S3TC_DXT5_EXT: GLuint = 0x83F3;
+pub(crate) const COMPRESSED_SRGB_S3TC_DXT1_EXT: GLuint = 0x8C4C;
+pub(crate) const COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT: GLuint = 0x8C4D;
+pub(crate) const COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT: GLuint = 0x8C4E;
+pub(crate) const COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT: GLuint = 0x8C4F;
+// From OES_compressed_ETC1_RGB8_texture.
+pub(crate) const ETC1_RGB8_OES: GLuint = 0x8D64;
+
+const S3TC_FORMATS: [GLuint; 4] = [
+    COMPRESSED_RGB_S3TC_DXT1_EXT,
+    COMPRESSED_RGBA_S3TC_DXT1_EXT,
+    COMPRESSED_RGBA_S3TC_DXT3_EXT,
+    COMPRESSED_RGBA_S3TC_DXT5_EXT,
+];
+const S3TC_SRGB_FORMATS: [GLuint; 4] = [
+    COMPRESSED_SRGB_S3TC_DXT1_EXT,
+    COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT,
+    COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT,
+    COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+];
+const RGTC_FORMATS: [GLuint; 4] = [
+    gl::COMPRESSED_RED_RGTC1,
+    gl::COMPRESSED_SIGNED_RED_RGTC1,
+    gl::COMPRESSED_RG_RGTC2,
+    gl::COMPRESSED_SIGNED_RG_RGTC2,
+];
+const BPTC_FORMATS: [GLuint; 4] = [
+    gl::COMPRESSED_RGBA_BPTC_UNORM,
+    gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+    gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+    gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+];
+const ETC2_FORMATS: [GLuint; 10] = [
+    gl::COMPRESSED_RGB8_ETC2,
+    gl::COMPRESSED_SRGB8_ETC2,
+    gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+    gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+    gl::COMPRESSED_RGBA8_ETC2_EAC,
+    gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+    gl::COMPRESSED_R11_EAC,
+    gl::COMPRESSED_SIGNED_R11_EAC,
+    gl::COMPRESSED_RG11_EAC,
+    gl::COMPRESSED_SIGNED_RG11_EAC,
+];
+
+/// Returns the size of a 4x4 block in bytes, if `format` is one of
+/// the supported block-compressed formats.
+pub(crate) fn block_size(format: GLuint) -> Option<usize> {
+    match format {
+        COMPRESSED_RGB_S3TC_DXT1_EXT
+        | COMPRESSED_RGBA_S3TC_DXT1_EXT
+        | COMPRESSED_SRGB_S3TC_DXT1_EXT
+        | COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT
+        | gl::COMPRESSED_RED_RGTC1
+        | gl::COMPRESSED_SIGNED_RED_RGTC1
+        | ETC1_RGB8_OES
+        | gl::COMPRESSED_RGB8_ETC2
+        | gl::COMPRESSED_SRGB8_ETC2
+        | gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2
+        | gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2
+        | gl::COMPRESSED_R11_EAC
+        | gl::COMPRESSED_SIGNED_R11_EAC => Some(8),
+        COMPRESSED_RGBA_S3TC_DXT3_EXT
+        | COMPRESSED_RGBA_S3TC_DXT5_EXT
+        | COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT
+        | COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT
+        | gl::COMPRESSED_RG_RGTC2
+        | gl::COMPRESSED_SIGNED_RG_RGTC2
+        | gl::COMPRESSED_RGBA_BPTC_UNORM
+        | gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM
+        | gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT
+        | gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT
+        | gl::COMPRESSED_RGBA8_ETC2_EAC
+        | gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC
+        | gl::COMPRESSED_RG11_EAC
+        | gl::COMPRESSED_SIGNED_RG11_EAC => Some(16),
+        _ => None,
+    }
+}
+
+/// Returns the size of a `width` x `height` image in the
+/// block-compressed `format`, in bytes.
+pub(crate) fn compressed_size(format: GLuint, width: i32, height: i32) -> Option<usize> {
+    let blocks = |size: i32| (size.max(1) as usize).div_ceil(4);
+    block_size(format).map(|block_size| blocks(width) * blocks(height) * block_size)
+}
+
+/// Returns the size of a mip level, the base level being 0.
+pub(crate) fn mip_size(width: i32, height: i32, level: usize) -> (i32, i32) {
+    let shrink = |size: i32| (size >> level.min(31)).max(1);
+    (shrink(width), shrink(height))
+}
+
+/// Returns the amount of mip levels in a full mipmap chain for a
+/// `width` x `height` image, down to 1x1.
+pub(crate) fn mip_level_count(width: i32, height: i32) -> usize {
+    (32 - width.max(height).max(1).leading_zeros()) as usize
+}
+
+/// Returns the compressed formats the current context can create
+/// textures with: the ones listed in `GL_COMPRESSED_TEXTURE_FORMATS`,
+/// and the ones implied by the version and the extensions.
+pub(crate) fn get_supported_formats(version: &OpenGlVersion, extensions: &[String]) -> Vec<GLuint> {
+    let mut formats = Vec::new();
+    unsafe {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_COMPRESSED_TEXTURE_FORMATS, &mut count);
+        if count > 0 {
+            let mut listed = vec![0; count as usize];
+            gl::GetIntegerv(gl::COMPRESSED_TEXTURE_FORMATS, listed.as_mut_ptr());
+            formats.extend(listed.into_iter().map(|format| format as GLuint));
+        }
+    }
+
+    let has = |name: &str| extensions.iter().any(|extension| extension == name);
+    let at_least = |api: OpenGlApi, min_major: u8, min_minor: u8| match version {
+        OpenGlVersion::Available {
+            api: current_api,
+            major,
+            minor,
+        } => *current_api == api && (*major, *minor) >= (min_major, min_minor),
+        _ => false,
+    };
+
+    let s3tc = has("GL_EXT_texture_compression_s3tc");
+    if s3tc {
+        formats.extend(&S3TC_FORMATS);
+    }
+    if s3tc && (has("GL_EXT_texture_sRGB") || has("GL_EXT_texture_compression_s3tc_srgb")) {
+        formats.extend(&S3TC_SRGB_FORMATS);
+    }
+    if at_least(OpenGlApi::Desktop, 3, 0)
+        || has("GL_ARB_texture_compression_rgtc")
+        || has("GL_EXT_texture_compression_rgtc")
+    {
+        formats.extend(&RGTC_FORMATS);
+    }
+    if at_least(OpenGlApi::Desktop, 4, 2)
+        || has("GL_ARB_texture_compression_bptc")
+        || has("GL_EXT_texture_compression_bptc")
+    {
+        formats.extend(&BPTC_FORMATS);
+    }
+    if at_least(OpenGlApi::ES, 3, 0)
+        || at_least(OpenGlApi::Desktop, 4, 3)
+        || has("GL_ARB_ES3_compatibility")
+    {
+        formats.extend(&ETC2_FORMATS);
+    }
+    if has("GL_OES_compressed_ETC1_RGB8_texture") {
+        formats.push(ETC1_RGB8_OES);
+    }
+
+    formats.sort_unstable();
+    formats.dedup();
+    formats
+}
+
+impl Image {
+    /// Returns true if the image is in a block-compressed format,
+    /// e.g. loaded from a KTX or DDS file. Compressed images can't be
+    /// manipulated, but they can be uploaded to the GPU as is, if the
+    /// OpenGL context supports the format.
+    pub fn is_compressed(&self) -> bool {
+        block_size(self.format).is_some()
+    }
+}
+
+#[test]
+fn compressed_sizes_are_rounded_up_to_blocks() {
+    let dxt1 = COMPRESSED_RGBA_S3TC_DXT1_EXT;
+    assert_eq!(compressed_size(dxt1, 4, 4), Some(8));
+    assert_eq!(compressed_size(dxt1, 5, 1), Some(16));
+    assert_eq!(
+        compressed_size(gl::COMPRESSED_RGBA_BPTC_UNORM, 8, 8),
+        Some(64)
+    );
+    assert_eq!(compressed_size(gl::RGBA, 4, 4), None);
+    assert_eq!(mip_size(16, 4, 3), (2, 1));
+    assert_eq!(mip_level_count(16, 4), 5);
+    assert_eq!(mip_level_count(17, 1), 5);
+    assert_eq!(mip_level_count(1, 1), 1);
+}
@@ -0,0 +1,279 @@
+use crate::error::DdsLoadingError;
+use crate::gl;
+use crate::gl::types::*;
+use crate::image::compressed::{
+    self, COMPRESSED_RGBA_S3TC_DXT1_EXT, COMPRESSED_RGBA_S3TC_DXT3_EXT,
+    COMPRESSED_RGBA_S3TC_DXT5_EXT, COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT,
+    COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT, COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+};
+use crate::image::Image;
+
+const HEADER_SIZE: usize = 128;
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDSD_MIPMAPCOUNT: u32 = 0x2_0000;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+const D3D10_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+const RGBA_MASKS: [u32; 4] = [0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000];
+const BGRA_MASKS: [u32; 4] = [0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000];
+
+/// How the pixels of a DDS file are stored.
+#[derive(Clone, Copy)]
+enum PixelFormat {
+    Compressed(GLuint),
+    /// 32-bit pixels, with the red, green, blue and alpha channels
+    /// masked by the masks. Converted into the format when loaded.
+    Uncompressed {
+        masks: [u32; 4],
+        format: GLuint,
+    },
+}
+
+impl Image {
+    /// Parses a DDS texture and makes an `Image` out of it, including
+    /// its mip levels.
+    ///
+    /// Textures compressed as BC1 (`DXT1`), BC2 (`DXT3`), BC3
+    /// (`DXT5`), BC4, BC5, BC6H or BC7 are kept compressed, and
+    /// uploaded to the GPU as is (see
+    /// [`is_compressed`](#method.is_compressed)). Uncompressed
+    /// textures are supported if they have 32 bits per pixel, and are
+    /// converted to RGBA. Only 2D textures are supported: not cube
+    /// maps, arrays or volume textures.
+    ///
+    /// Textures without a `DX10` header don't specify their color
+    /// space, so they're assumed to be sRGB, like the other image
+    /// formats.
+    ///
+    /// Requires the `dds` feature.
+    ///
+    /// # Errors
+    ///
+    /// See [`DdsLoadingError`](enum.DdsLoadingError.html).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let background = fae::Image::with_dds(&std::fs::read("background.dds")?)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_dds(bytes: &[u8]) -> Result<Image, DdsLoadingError> {
+        if !bytes.starts_with(b"DDS ") || read_u32(bytes, 4)? != 124 {
+            return Err(DdsLoadingError::InvalidHeader);
+        }
+        let flags = read_u32(bytes, 8)?;
+        let height = read_u32(bytes, 12)? as i32;
+        let width = read_u32(bytes, 16)? as i32;
+        let mip_levels = if flags & DDSD_MIPMAPCOUNT != 0 {
+            read_u32(bytes, 28)?.max(1) as usize
+        } else {
+            1
+        };
+        let pixel_format_flags = read_u32(bytes, 80)?;
+        let caps2 = read_u32(bytes, 112)?;
+        if caps2 & (DDSCAPS2_CUBEMAP | DDSCAPS2_VOLUME) != 0 || width <= 0 || height <= 0 {
+            return Err(DdsLoadingError::UnsupportedTextureType);
+        }
+        // The count comes from the file, so don't trust it to be sane.
+        let mip_levels = mip_levels.min(compressed::mip_level_count(width, height));
+
+        let mut offset = HEADER_SIZE;
+        let pixel_format = if pixel_format_flags & DDPF_FOURCC == 0 {
+            if read_u32(bytes, 88)? != 32 {
+                return Err(DdsLoadingError::UnsupportedPixelFormat);
+            }
+            let mut masks = [0; 4];
+            for (i, mask) in masks.iter_mut().enumerate() {
+                *mask = read_u32(bytes, 92 + i * 4)?;
+            }
+            if pixel_format_flags & DDPF_ALPHAPIXELS == 0 {
+                masks[3] = 0;
+            }
+            PixelFormat::Uncompressed {
+                masks,
+                format: gl::SRGB_ALPHA,
+            }
+        } else {
+            let four_cc = [bytes[84], bytes[85], bytes[86], bytes[87]];
+            match &four_cc {
+                b"DXT1" => PixelFormat::Compressed(COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT),
+                b"DXT3" => PixelFormat::Compressed(COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT),
+                b"DXT5" => PixelFormat::Compressed(COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT),
+                b"ATI1" | b"BC4U" => PixelFormat::Compressed(gl::COMPRESSED_RED_RGTC1),
+                b"ATI2" | b"BC5U" => PixelFormat::Compressed(gl::COMPRESSED_RG_RGTC2),
+                b"DX10" => {
+                    offset += DX10_HEADER_SIZE;
+                    read_dx10_header(bytes)?
+                }
+                _ => return Err(DdsLoadingError::UnsupportedFourCc(four_cc)),
+            }
+        };
+
+        let mut levels = Vec::with_capacity(mip_levels);
+        for level in 0..mip_levels {
+            let (level_width, level_height) = compressed::mip_size(width, height, level);
+            let size = match pixel_format {
+                PixelFormat::Compressed(format) => {
+                    compressed::compressed_size(format, level_width, level_height).unwrap_or(0)
+                }
+                PixelFormat::Uncompressed { .. } => {
+                    level_width as usize * level_height as usize * 4
+                }
+            };
+            let data = bytes
+                .get(offset..offset + size)
+                .ok_or(DdsLoadingError::UnexpectedEof)?;
+            offset += size;
+            levels.push(match pixel_format {
+                PixelFormat::Compressed(_) => data.to_vec(),
+                PixelFormat::Uncompressed { masks, .. } => unmask_pixels(data, masks),
+            });
+        }
+
+        let format = match pixel_format {
+            PixelFormat::Compressed(format) => format,
+            PixelFormat::Uncompressed { format, .. } => format,
+        };
+        let mut levels = levels.into_iter();
+        Ok(Image {
+            pixels: levels.next().unwrap_or_default(),
+            width,
+            height,
+            format,
+            pixel_type: gl::UNSIGNED_BYTE,
+            null_data: false,
+            mipmaps: levels.collect(),
+        })
+    }
+}
+
+fn read_dx10_header(bytes: &[u8]) -> Result<PixelFormat, DdsLoadingError> {
+    let dxgi_format = read_u32(bytes, HEADER_SIZE)?;
+    let resource_dimension = read_u32(bytes, HEADER_SIZE + 4)?;
+    let misc_flags = read_u32(bytes, HEADER_SIZE + 8)?;
+    let array_size = read_u32(bytes, HEADER_SIZE + 12)?;
+    if resource_dimension != D3D10_RESOURCE_DIMENSION_TEXTURE2D
+        || misc_flags & D3D10_RESOURCE_MISC_TEXTURECUBE != 0
+        || array_size > 1
+    {
+        return Err(DdsLoadingError::UnsupportedTextureType);
+    }
+    let uncompressed = |masks, format| PixelFormat::Uncompressed { masks, format };
+    Ok(match dxgi_format {
+        28 => uncompressed(RGBA_MASKS, gl::RGBA),
+        29 => uncompressed(RGBA_MASKS, gl::SRGB_ALPHA),
+        87 => uncompressed(BGRA_MASKS, gl::RGBA),
+        91 => uncompressed(BGRA_MASKS, gl::SRGB_ALPHA),
+        71 => PixelFormat::Compressed(COMPRESSED_RGBA_S3TC_DXT1_EXT),
+        72 => PixelFormat::Compressed(COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT),
+        74 => PixelFormat::Compressed(COMPRESSED_RGBA_S3TC_DXT3_EXT),
+        75 => PixelFormat::Compressed(COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT),
+        77 => PixelFormat::Compressed(COMPRESSED_RGBA_S3TC_DXT5_EXT),
+        78 => PixelFormat::Compressed(COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT),
+        80 => PixelFormat::Compressed(gl::COMPRESSED_RED_RGTC1),
+        81 => PixelFormat::Compressed(gl::COMPRESSED_SIGNED_RED_RGTC1),
+        83 => PixelFormat::Compressed(gl::COMPRESSED_RG_RGTC2),
+        84 => PixelFormat::Compressed(gl::COMPRESSED_SIGNED_RG_RGTC2),
+        95 => PixelFormat::Compressed(gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT),
+        96 => PixelFormat::Compressed(gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT),
+        98 => PixelFormat::Compressed(gl::COMPRESSED_RGBA_BPTC_UNORM),
+        99 => PixelFormat::Compressed(gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM),
+        format => return Err(DdsLoadingError::UnsupportedDxgiFormat(format)),
+    })
+}
+
+/// Converts 32-bit pixels with the given channel masks into RGBA.
+/// Channels without a mask are 0, except alpha, which is 255.
+fn unmask_pixels(data: &[u8], masks: [u32; 4]) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(data.len());
+    for pixel in data.chunks_exact(4) {
+        let pixel = u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+        for (i, &mask) in masks.iter().enumerate() {
+            pixels.push(if mask == 0 {
+                if i == 3 {
+                    0xFF
+                } else {
+                    0
+                }
+            } else {
+                // Channels wider than 8 bits are cut down to their
+                // highest 8 bits.
+                let value = (pixel & mask) >> mask.trailing_zeros();
+                let bits = mask.count_ones();
+                if bits >= 8 {
+                    (value >> (bits - 8)) as u8
+                } else {
+                    (value * 0xFF / ((1 << bits) - 1)) as u8
+                }
+            });
+        }
+    }
+    pixels
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, DdsLoadingError> {
+    match bytes.get(offset..offset + 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        None => Err(DdsLoadingError::UnexpectedEof),
+    }
+}
+
+#[test]
+fn dds_textures_are_parsed() {
+    fn write_dds(flags: u32, width: u32, height: u32, mips: u32, pixel_format: &[u32]) -> Vec<u8> {
+        let mut header = [0; 31];
+        header[0] = 124;
+        header[1] = flags;
+        header[2] = height;
+        header[3] = width;
+        header[6] = mips;
+        header[18] = 32;
+        header[19..19 + pixel_format.len()].copy_from_slice(pixel_format);
+        let mut bytes = b"DDS ".to_vec();
+        for field in &header {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes
+    }
+    let dxt1 = u32::from_le_bytes(*b"DXT1");
+
+    let mut bytes = write_dds(DDSD_MIPMAPCOUNT, 8, 4, 3, &[DDPF_FOURCC, dxt1]);
+    bytes.extend_from_slice(&[1; 16]);
+    bytes.extend_from_slice(&[2; 8]);
+    bytes.extend_from_slice(&[3; 8]);
+    let image = Image::with_dds(&bytes).unwrap();
+    assert!(image.is_compressed());
+    assert_eq!((image.width, image.height), (8, 4));
+    assert_eq!(image.format, COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT);
+    assert_eq!(image.pixels, vec![1; 16]);
+    assert_eq!(image.mipmaps, vec![vec![2; 8], vec![3; 8]]);
+
+    // A 2x1 BGRX image.
+    let masks = [0xFF_0000, 0xFF00, 0xFF];
+    let mut bytes = write_dds(0, 2, 1, 0, &[0x40, 0, 32, masks[0], masks[1], masks[2], 0]);
+    bytes.extend_from_slice(&[0x30, 0x20, 0x10, 0, 0x60, 0x50, 0x40, 0]);
+    let image = Image::with_dds(&bytes).unwrap();
+    assert_eq!(image.format, gl::SRGB_ALPHA);
+    assert_eq!(
+        image.pixels,
+        vec![0x10, 0x20, 0x30, 0xFF, 0x40, 0x50, 0x60, 0xFF]
+    );
+
+    // The mip level count is capped to the levels a 2x1 image has.
+    let pixel_format = [0x40, 0, 32, masks[0], masks[1], masks[2], 0];
+    let mut bytes = write_dds(DDSD_MIPMAPCOUNT, 2, 1, u32::MAX, &pixel_format);
+    bytes.extend_from_slice(&[0; 12]);
+    assert_eq!(
+        Image::with_dds(&bytes).unwrap().mipmaps,
+        vec![vec![0, 0, 0, 0xFF]]
+    );
+
+    let mut bytes = write_dds(0, 4, 4, 0, &[DDPF_FOURCC, u32::from_le_bytes(*b"ETC1")]);
+    bytes.extend_from_slice(&[0; 8]);
+    assert!(Image::with_dds(&bytes).is_err());
+}
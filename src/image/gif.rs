@@ -0,0 +1,111 @@
+use crate::error::GifLoadingError;
+use crate::gl;
+use crate::image::Image;
+
+use gif::{ColorOutput, DecodeOptions, MemoryLimit};
+
+/// The most memory the decoded image may take, in bytes. The same as
+/// the `gif` crate's default limit for the frame buffer.
+const MEMORY_LIMIT: usize = 50_000_000;
+
+impl Image {
+    /// Parses a GIF image and makes an `Image` out of it. Only the
+    /// first frame of animated GIFs is read.
+    ///
+    /// The resulting image is the size of the GIF's logical screen,
+    /// with the frame drawn at its offset, and transparent pixels
+    /// elsewhere. The format is `SRGB_ALPHA`.
+    ///
+    /// Requires the `gif` feature.
+    ///
+    /// # Errors
+    ///
+    /// A [`GifError`](enum.GifLoadingError.html#variant.GifError)
+    /// will be returned if the data couldn't be read by the `gif`
+    /// crate, and [`NoFrames`](enum.GifLoadingError.html#variant.NoFrames)
+    /// if the GIF is empty. Images with a logical screen over 50 MB
+    /// in RGBA, or a first frame with no pixels, are rejected as
+    /// well.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let icon = fae::Image::with_gif(&std::fs::read("icon.gif")?)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_gif(bytes: &[u8]) -> Result<Image, GifLoadingError> {
+        let mut options = DecodeOptions::new();
+        options.set_color_output(ColorOutput::RGBA);
+        options.set_memory_limit(MemoryLimit(MEMORY_LIMIT as u32));
+        let mut decoder = options.read_info(bytes)?;
+        let (width, height) = (decoder.width() as usize, decoder.height() as usize);
+        if width * height * 4 > MEMORY_LIMIT {
+            return Err(GifLoadingError::ScreenTooLarge(width, height));
+        }
+        let frame = match decoder.read_next_frame()? {
+            Some(frame) => frame,
+            None => return Err(GifLoadingError::NoFrames),
+        };
+        if frame.width == 0 || frame.height == 0 {
+            return Err(GifLoadingError::EmptyFrame);
+        }
+
+        let mut pixels = vec![0; width * height * 4];
+        let (left, top) = (frame.left as usize, frame.top as usize);
+        let frame_width = frame.width as usize;
+        for (y, row) in frame.buffer.chunks_exact(frame_width * 4).enumerate() {
+            let y = top + y;
+            if y >= height || left >= width {
+                break;
+            }
+            let copied_width = frame_width.min(width - left);
+            let start = (y * width + left) * 4;
+            pixels[start..start + copied_width * 4].copy_from_slice(&row[..copied_width * 4]);
+        }
+
+        Ok(Image {
+            pixels,
+            width: width as i32,
+            height: height as i32,
+            format: gl::SRGB_ALPHA,
+            pixel_type: gl::UNSIGNED_BYTE,
+            null_data: false,
+            mipmaps: Vec::new(),
+        })
+    }
+}
+
+#[test]
+fn malformed_gif_headers_are_rejected() {
+    // The logical screen descriptor, with a two color global palette.
+    let gif = |width: u16, height: u16| {
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        bytes.extend_from_slice(&[0x80, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF]);
+        bytes
+    };
+    let frame = |width: u16, height: u16| {
+        let mut bytes = vec![0x2C, 0, 0, 0, 0];
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        // No local palette, then LZW data with just a clear and an
+        // end code, and the trailer.
+        bytes.extend_from_slice(&[0, 2, 1, 0x2C, 0, 0x3B]);
+        bytes
+    };
+
+    let mut bytes = gif(65535, 65535);
+    bytes.extend_from_slice(&frame(1, 1));
+    match Image::with_gif(&bytes) {
+        Err(GifLoadingError::ScreenTooLarge(65535, 65535)) => {}
+        result => panic!("expected ScreenTooLarge, got {:?}", result.map(|_| ())),
+    }
+
+    let mut bytes = gif(4, 4);
+    bytes.extend_from_slice(&frame(0, 4));
+    match Image::with_gif(&bytes) {
+        Err(GifLoadingError::EmptyFrame) => {}
+        result => panic!("expected EmptyFrame, got {:?}", result.map(|_| ())),
+    }
+}
@@ -0,0 +1,50 @@
+use crate::error::JpegLoadingError;
+use crate::gl;
+use crate::image::Image;
+
+use jpeg_decoder::{Decoder, PixelFormat};
+
+impl Image {
+    /// Parses a JPEG image and makes an `Image` out of it.
+    ///
+    /// Like [`with_png`](#method.with_png), this function assumes that
+    /// the image is in SRGB space, so color images will have the
+    /// `SRGB` format. Grayscale images will use `GL_RED`.
+    ///
+    /// Requires the `jpeg` feature.
+    ///
+    /// # Errors
+    ///
+    /// A [`JpegError`](enum.JpegLoadingError.html#variant.JpegError)
+    /// will be returned if the data couldn't be read by the
+    /// `jpeg-decoder` crate. An
+    /// [`UnsupportedPixelFormat`](enum.JpegLoadingError.html#variant.UnsupportedPixelFormat)
+    /// error will be returned for CMYK images.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let background = fae::Image::with_jpeg(&std::fs::read("background.jpg")?)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_jpeg(bytes: &[u8]) -> Result<Image, JpegLoadingError> {
+        let mut decoder = Decoder::new(bytes);
+        let pixels = decoder.decode()?;
+        // The info is always available after a successful decode.
+        let info = decoder.info().unwrap();
+        let format = match info.pixel_format {
+            PixelFormat::L8 => gl::RED,
+            PixelFormat::RGB24 => gl::SRGB,
+            pixel_format => return Err(JpegLoadingError::UnsupportedPixelFormat(pixel_format)),
+        };
+        Ok(Image {
+            pixels,
+            width: info.width as i32,
+            height: info.height as i32,
+            format,
+            pixel_type: gl::UNSIGNED_BYTE,
+            null_data: false,
+            mipmaps: Vec::new(),
+        })
+    }
+}
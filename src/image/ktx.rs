@@ -0,0 +1,238 @@
+use crate::error::KtxLoadingError;
+use crate::gl;
+use crate::gl::types::*;
+use crate::image::compressed;
+use crate::image::Image;
+
+const IDENTIFIER: &[u8] = b"\xABKTX 11\xBB\r\n\x1A\n";
+const HEADER_SIZE: usize = 64;
+const ENDIANNESS_REFERENCE: u32 = 0x0403_0201;
+
+impl Image {
+    /// Parses a KTX (version 1.1) texture and makes an `Image` out of
+    /// it, including its mip levels.
+    ///
+    /// Block-compressed textures (BC1-BC7 and ETC1/ETC2, see
+    /// [`is_compressed`](#method.is_compressed)) are kept compressed,
+    /// and uploaded to the GPU as is. Uncompressed textures are
+    /// supported if their format is `RED`, `RG`, `RGB` or `RGBA`.
+    /// Only 2D textures are supported: not cube maps, arrays or 3D
+    /// textures. The rows are expected to be stored top-down.
+    ///
+    /// Requires the `ktx` feature.
+    ///
+    /// # Errors
+    ///
+    /// See [`KtxLoadingError`](enum.KtxLoadingError.html).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let background = fae::Image::with_ktx(&std::fs::read("background.ktx")?)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_ktx(bytes: &[u8]) -> Result<Image, KtxLoadingError> {
+        if !bytes.starts_with(IDENTIFIER) {
+            return Err(KtxLoadingError::InvalidIdentifier);
+        }
+        let big_endian = read_u32(bytes, 12, false)? != ENDIANNESS_REFERENCE;
+        let field = |index: usize| read_u32(bytes, 16 + index * 4, big_endian);
+        let gl_type = field(0)?;
+        let gl_type_size = field(1)?;
+        let gl_format = field(2)?;
+        let gl_internal_format = field(3)?;
+        let width = field(5)? as i32;
+        let height = field(6)? as i32;
+        let (depth, array_elements, faces) = (field(7)?, field(8)?, field(9)?);
+        let mip_levels = field(10)?.max(1) as usize;
+        let key_value_data_size = field(11)? as usize;
+        if depth > 0 || array_elements > 0 || faces != 1 || width <= 0 || height <= 0 {
+            return Err(KtxLoadingError::UnsupportedTextureType);
+        }
+        // The count comes from the file, so don't trust it to be sane.
+        let mip_levels = mip_levels.min(compressed::mip_level_count(width, height));
+
+        let compressed = gl_type == 0;
+        let (format, pixel_type) = if compressed {
+            if compressed::block_size(gl_internal_format).is_none() {
+                return Err(KtxLoadingError::UnsupportedFormat(gl_internal_format));
+            }
+            (gl_internal_format, gl::UNSIGNED_BYTE)
+        } else {
+            get_uncompressed_format(gl_format, gl_internal_format, gl_type, gl_type_size)?
+        };
+
+        let mut offset = HEADER_SIZE + key_value_data_size;
+        let mut levels = Vec::with_capacity(mip_levels);
+        for level in 0..mip_levels {
+            let (level_width, level_height) = compressed::mip_size(width, height, level);
+            let image_size = read_u32(bytes, offset, big_endian)? as usize;
+            offset += 4;
+            let data = bytes
+                .get(offset..offset + image_size)
+                .ok_or(KtxLoadingError::UnexpectedEof)?;
+            // Each mip level is padded to 4 bytes.
+            offset += image_size.div_ceil(4) * 4;
+
+            let pixels = if compressed {
+                let expected_size = compressed::compressed_size(format, level_width, level_height);
+                if expected_size != Some(image_size) {
+                    return Err(KtxLoadingError::InvalidImageSize);
+                }
+                data.to_vec()
+            } else {
+                let pixel_size = channel_count(gl_format) * gl_type_size as usize;
+                let row_size = level_width as usize * pixel_size;
+                read_rows(
+                    data,
+                    row_size,
+                    level_height as usize,
+                    gl_type_size,
+                    big_endian,
+                )?
+            };
+            levels.push(pixels);
+        }
+
+        let mut levels = levels.into_iter();
+        Ok(Image {
+            pixels: levels.next().unwrap_or_default(),
+            width,
+            height,
+            format,
+            pixel_type,
+            null_data: false,
+            mipmaps: levels.collect(),
+        })
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize, big_endian: bool) -> Result<u32, KtxLoadingError> {
+    match bytes.get(offset..offset + 4) {
+        Some(b) if big_endian => Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]])),
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        None => Err(KtxLoadingError::UnexpectedEof),
+    }
+}
+
+/// Returns the `Image` format and pixel type for an uncompressed
+/// texture, keeping the sRGB-ness of the internal format.
+fn get_uncompressed_format(
+    gl_format: u32,
+    gl_internal_format: u32,
+    gl_type: u32,
+    gl_type_size: u32,
+) -> Result<(GLuint, GLuint), KtxLoadingError> {
+    let type_valid = match gl_type {
+        gl::UNSIGNED_BYTE => gl_type_size == 1,
+        gl::UNSIGNED_SHORT => gl_type_size == 2,
+        _ => false,
+    };
+    if !type_valid || channel_count(gl_format) == 0 {
+        return Err(KtxLoadingError::UnsupportedFormat(gl_internal_format));
+    }
+    let format = match gl_internal_format {
+        gl::SRGB8 | gl::SRGB => gl::SRGB,
+        gl::SRGB8_ALPHA8 | gl::SRGB_ALPHA => gl::SRGB_ALPHA,
+        _ => gl_format,
+    };
+    Ok((format, gl_type))
+}
+
+fn channel_count(gl_format: u32) -> usize {
+    match gl_format {
+        gl::RED => 1,
+        gl::RG => 2,
+        gl::RGB => 3,
+        gl::RGBA => 4,
+        _ => 0,
+    }
+}
+
+/// Copies the rows out of `data`, where they're padded to 4 bytes,
+/// swapping the bytes of 16-bit samples if the file's endianness
+/// differs from the native one.
+fn read_rows(
+    data: &[u8],
+    row_size: usize,
+    height: usize,
+    type_size: u32,
+    big_endian: bool,
+) -> Result<Vec<u8>, KtxLoadingError> {
+    let padded_row_size = row_size.div_ceil(4) * 4;
+    if data.len() < padded_row_size * (height - 1) + row_size {
+        return Err(KtxLoadingError::InvalidImageSize);
+    }
+    let mut pixels = Vec::with_capacity(row_size * height);
+    for y in 0..height {
+        let start = y * padded_row_size;
+        pixels.extend_from_slice(&data[start..start + row_size]);
+    }
+    if type_size == 2 && big_endian != cfg!(target_endian = "big") {
+        for sample in pixels.chunks_exact_mut(2) {
+            sample.swap(0, 1);
+        }
+    }
+    Ok(pixels)
+}
+
+#[test]
+fn ktx_textures_are_parsed() {
+    fn write_ktx(fields: [u32; 12], levels: &[&[u8]]) -> Vec<u8> {
+        let mut bytes = IDENTIFIER.to_vec();
+        bytes.extend_from_slice(&ENDIANNESS_REFERENCE.to_le_bytes());
+        for field in &fields {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        for level in levels {
+            bytes.extend_from_slice(&(level.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(level);
+            bytes.resize(bytes.len().div_ceil(4) * 4, 0);
+        }
+        bytes
+    }
+
+    let etc2 = gl::COMPRESSED_RGB8_ETC2;
+    let fields = [0, 1, 0, etc2, gl::RGB, 8, 4, 0, 0, 1, 3, 0];
+    let bytes = write_ktx(fields, &[&[1; 16], &[2; 8], &[3; 8]]);
+    let image = Image::with_ktx(&bytes).unwrap();
+    assert!(image.is_compressed());
+    assert_eq!((image.width, image.height, image.format), (8, 4, etc2));
+    assert_eq!(image.pixels, vec![1; 16]);
+    assert_eq!(image.mipmaps, vec![vec![2; 8], vec![3; 8]]);
+
+    // The mip level count is capped to the levels an 8x4 image has.
+    let mut fields = fields;
+    fields[10] = u32::MAX;
+    let bytes = write_ktx(fields, &[&[1; 16], &[2; 8], &[3; 8], &[4; 8]]);
+    assert_eq!(Image::with_ktx(&bytes).unwrap().mipmaps.len(), 3);
+
+    // The rows of a 3x2 RGB image are padded from 9 to 12 bytes.
+    let fields = [
+        gl::UNSIGNED_BYTE,
+        1,
+        gl::RGB,
+        gl::SRGB8,
+        gl::RGB,
+        3,
+        2,
+        0,
+        0,
+        1,
+        1,
+        0,
+    ];
+    let bytes = write_ktx(
+        fields,
+        &[&[&[1; 9][..], &[0; 3], &[2; 9], &[0; 3]].concat()],
+    );
+    let image = Image::with_ktx(&bytes).unwrap();
+    assert_eq!(
+        (image.format, image.pixel_type),
+        (gl::SRGB, gl::UNSIGNED_BYTE)
+    );
+    assert_eq!(image.pixels, [[1; 9], [2; 9]].concat());
+
+    let cube_map = [0, 1, 0, etc2, gl::RGB, 4, 4, 0, 0, 6, 1, 0];
+    assert!(Image::with_ktx(&write_ktx(cube_map, &[])).is_err());
+}
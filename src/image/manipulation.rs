@@ -0,0 +1,574 @@
+//! CPU-side operations on the pixels of an `Image`.
+use crate::error::ImageManipulationError;
+use crate::gl;
+use crate::gl::types::*;
+use crate::image::Image;
+use crate::types::Rect;
+
+/// The filter used when resizing an image with
+/// [`Image::resize`](struct.Image.html#method.resize).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResizeFilter {
+    /// Each pixel is copied from the closest pixel of the original.
+    /// Keeps pixel art crisp.
+    Nearest,
+    /// Each pixel is interpolated from the four closest pixels of the
+    /// original.
+    Bilinear,
+}
+
+/// How the pixels of an image are laid out in memory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct Layout {
+    pub channels: usize,
+    pub sample_size: usize,
+}
+
+impl Layout {
+    fn new(format: GLuint, pixel_type: GLuint) -> Result<Layout, ImageManipulationError> {
+        let channels = match format {
+            gl::RED => 1,
+            gl::RG => 2,
+            gl::RGB | gl::SRGB => 3,
+            gl::RGBA | gl::SRGB_ALPHA => 4,
+            _ => {
+                return Err(ImageManipulationError::UnsupportedFormat(
+                    format, pixel_type,
+                ))
+            }
+        };
+        let sample_size = match pixel_type {
+            gl::UNSIGNED_BYTE => 1,
+            gl::UNSIGNED_SHORT => 2,
+            _ => {
+                return Err(ImageManipulationError::UnsupportedFormat(
+                    format, pixel_type,
+                ))
+            }
+        };
+        Ok(Layout {
+            channels,
+            sample_size,
+        })
+    }
+
+    fn pixel_size(self) -> usize {
+        self.channels * self.sample_size
+    }
+
+    fn max_value(self) -> u32 {
+        if self.sample_size == 2 {
+            0xFFFF
+        } else {
+            0xFF
+        }
+    }
+
+    /// Returns the index of the alpha channel, if there is one.
+    fn alpha_channel(self) -> Option<usize> {
+        match self.channels {
+            2 => Some(1),
+            4 => Some(3),
+            _ => None,
+        }
+    }
+
+    fn read(self, pixel: &[u8], channel: usize) -> u32 {
+        let i = channel * self.sample_size;
+        if self.sample_size == 2 {
+            u32::from(u16::from_ne_bytes([pixel[i], pixel[i + 1]]))
+        } else {
+            u32::from(pixel[i])
+        }
+    }
+
+    fn write(self, pixel: &mut [u8], channel: usize, value: u32) {
+        let i = channel * self.sample_size;
+        if self.sample_size == 2 {
+            pixel[i..i + 2].copy_from_slice(&(value as u16).to_ne_bytes());
+        } else {
+            pixel[i] = value as u8;
+        }
+    }
+
+    /// Reads the pixel as normalized RGBA. Single-channel images are
+    /// read as gray, and two-channel images as gray and alpha.
+    fn read_rgba(self, pixel: &[u8]) -> [f32; 4] {
+        let max = self.max_value() as f32;
+        let value = |channel| self.read(pixel, channel) as f32 / max;
+        match self.channels {
+            1 => [value(0), value(0), value(0), 1.0],
+            2 => [value(0), value(0), value(0), value(1)],
+            3 => [value(0), value(1), value(2), 1.0],
+            _ => [value(0), value(1), value(2), value(3)],
+        }
+    }
+
+    /// Writes normalized RGBA into the pixel. Single-channel and
+    /// two-channel images get the luminance of the color, and the
+    /// latter the alpha as well.
+    fn write_rgba(self, pixel: &mut [u8], rgba: [f32; 4]) {
+        let max = self.max_value() as f32;
+        let quantize = |value: f32| (value.clamp(0.0, 1.0) * max).round() as u32;
+        let luminance = 0.2126 * rgba[0] + 0.7152 * rgba[1] + 0.0722 * rgba[2];
+        let values = match self.channels {
+            1 => [luminance, 0.0, 0.0, 0.0],
+            2 => [luminance, rgba[3], 0.0, 0.0],
+            _ => rgba,
+        };
+        for (channel, &value) in values.iter().enumerate().take(self.channels) {
+            self.write(pixel, channel, quantize(value));
+        }
+    }
+}
+
+/// Returns the format with an alpha channel added, keeping it SRGB if
+/// it was.
+fn with_alpha_channel(format: GLuint) -> GLuint {
+    match format {
+        gl::RED => gl::RG,
+        gl::RGB => gl::RGBA,
+        gl::SRGB => gl::SRGB_ALPHA,
+        format => format,
+    }
+}
+
+impl Image {
+    /// Returns the layout of the pixels, if they can be manipulated.
+    pub(super) fn layout(&self) -> Result<Layout, ImageManipulationError> {
+        if self.null_data {
+            return Err(ImageManipulationError::NullData);
+        }
+        let layout = Layout::new(self.format, self.pixel_type)?;
+        let pixel_count = self.width.max(0) as usize * self.height.max(0) as usize;
+        if self.pixels.len() != pixel_count * layout.pixel_size() {
+            return Err(ImageManipulationError::InvalidPixelCount);
+        }
+        Ok(layout)
+    }
+
+    /// Returns the layout like [`layout`](#method.layout), and
+    /// clears the mip levels, which would be out of date after
+    /// modifying the pixels.
+    fn layout_mut(&mut self) -> Result<Layout, ImageManipulationError> {
+        let layout = self.layout()?;
+        self.mipmaps.clear();
+        Ok(layout)
+    }
+
+    fn pixel_index(&self, x: i32, y: i32, layout: Layout) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            None
+        } else {
+            Some((y as usize * self.width as usize + x as usize) * layout.pixel_size())
+        }
+    }
+
+    /// Replaces the pixels with `pixel_count` pixels, each copied from
+    /// the pixel of the current image returned by `source` for the
+    /// new pixel's index, if any. Pixels without a source are left
+    /// zeroed.
+    fn remap<F: Fn(usize) -> Option<usize>>(
+        &mut self,
+        layout: Layout,
+        pixel_count: usize,
+        source: F,
+    ) {
+        let pixel_size = layout.pixel_size();
+        let mut pixels = vec![0; pixel_count * pixel_size];
+        for (i, pixel) in pixels.chunks_exact_mut(pixel_size).enumerate() {
+            if let Some(j) = source(i) {
+                pixel.copy_from_slice(&self.pixels[j * pixel_size..(j + 1) * pixel_size]);
+            }
+        }
+        self.pixels = pixels;
+    }
+
+    /// Returns the color of the pixel at (x, y) as normalized RGBA,
+    /// or None if the coordinates are out of bounds, or the image's
+    /// format isn't supported (see
+    /// [`ImageManipulationError::UnsupportedFormat`](errors/enum.ImageManipulationError.html#variant.UnsupportedFormat)).
+    ///
+    /// `RED` images are read as grayscale, and `RG` images as
+    /// grayscale with alpha. SRGB images aren't converted into linear
+    /// space, the values are returned as they are stored.
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<(f32, f32, f32, f32)> {
+        let layout = self.layout().ok()?;
+        let i = self.pixel_index(x, y, layout)?;
+        let [r, g, b, a] = layout.read_rgba(&self.pixels[i..i + layout.pixel_size()]);
+        Some((r, g, b, a))
+    }
+
+    /// Sets the color of the pixel at (x, y), given as normalized
+    /// RGBA. Does nothing if the coordinates are out of bounds.
+    ///
+    /// `RED` and `RG` images get the luminance of the color, and the
+    /// latter the alpha as well. Images without an alpha channel
+    /// ignore the alpha.
+    pub fn set_pixel(
+        &mut self,
+        x: i32,
+        y: i32,
+        (red, green, blue, alpha): (f32, f32, f32, f32),
+    ) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        if let Some(i) = self.pixel_index(x, y, layout) {
+            let pixel = &mut self.pixels[i..i + layout.pixel_size()];
+            layout.write_rgba(pixel, [red, green, blue, alpha]);
+        }
+        Ok(self)
+    }
+
+    /// Crops the image to the area, which is clamped to the image's
+    /// bounds. The area's coordinates are rounded down to whole
+    /// pixels.
+    pub fn crop<R: Into<Rect>>(&mut self, area: R) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        let area = area.into();
+        let x0 = (area.x as i32).max(0).min(self.width);
+        let y0 = (area.y as i32).max(0).min(self.height);
+        let x1 = ((area.x + area.width) as i32).max(x0).min(self.width);
+        let y1 = ((area.y + area.height) as i32).max(y0).min(self.height);
+        let (old_width, new_width) = (self.width as usize, (x1 - x0) as usize);
+        let new_height = (y1 - y0) as usize;
+        let (x0, y0) = (x0 as usize, y0 as usize);
+        self.remap(layout, new_width * new_height, |i| {
+            Some((y0 + i / new_width) * old_width + x0 + i % new_width)
+        });
+        self.width = new_width as i32;
+        self.height = new_height as i32;
+        Ok(self)
+    }
+
+    /// Mirrors the image horizontally.
+    pub fn flip_horizontal(&mut self) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        let (width, height) = (self.width as usize, self.height as usize);
+        self.remap(layout, width * height, |i| {
+            Some(i / width * width + width - 1 - i % width)
+        });
+        Ok(self)
+    }
+
+    /// Mirrors the image vertically.
+    pub fn flip_vertical(&mut self) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        let (width, height) = (self.width as usize, self.height as usize);
+        self.remap(layout, width * height, |i| {
+            Some((height - 1 - i / width) * width + i % width)
+        });
+        Ok(self)
+    }
+
+    /// Rotates the image 90 degrees clockwise. The width and height
+    /// are swapped.
+    pub fn rotate_clockwise(&mut self) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        let (width, height) = (self.width as usize, self.height as usize);
+        // The new image is `height` pixels wide.
+        self.remap(layout, width * height, |i| {
+            let (x, y) = (i % height, i / height);
+            Some((height - 1 - x) * width + y)
+        });
+        self.width = height as i32;
+        self.height = width as i32;
+        Ok(self)
+    }
+
+    /// Rotates the image 90 degrees counter-clockwise. The width and
+    /// height are swapped.
+    pub fn rotate_counter_clockwise(&mut self) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        let (width, height) = (self.width as usize, self.height as usize);
+        self.remap(layout, width * height, |i| {
+            let (x, y) = (i % height, i / height);
+            Some(x * width + width - 1 - y)
+        });
+        self.width = height as i32;
+        self.height = width as i32;
+        Ok(self)
+    }
+
+    /// Multiplies the color channels of each pixel by its alpha. Does
+    /// nothing if the image doesn't have an alpha channel.
+    ///
+    /// Useful for avoiding dark fringes around transparent areas when
+    /// the image is filtered, if the shaders and blending are set up
+    /// for premultiplied alpha.
+    pub fn premultiply_alpha(&mut self) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        if let Some(alpha_channel) = layout.alpha_channel() {
+            let max = layout.max_value();
+            for pixel in self.pixels.chunks_exact_mut(layout.pixel_size()) {
+                let alpha = layout.read(pixel, alpha_channel);
+                for channel in 0..alpha_channel {
+                    let value = layout.read(pixel, channel);
+                    layout.write(pixel, channel, (value * alpha + max / 2) / max);
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Converts the pixels into another format and pixel type, e.g.
+    /// `gl::RGBA` and `gl::UNSIGNED_BYTE`.
+    ///
+    /// When converting to `RED` or `RG`, the color is converted into
+    /// its luminance, and when converting from them, the value is
+    /// used for all the color channels (see
+    /// [`get_pixel`](#method.get_pixel)). Added alpha channels are
+    /// opaque. Converting between SRGB and linear formats (e.g. from
+    /// `SRGB` to `RGB`) only changes the `format`, the values stay
+    /// the same.
+    pub fn convert(
+        &mut self,
+        format: GLuint,
+        pixel_type: GLuint,
+    ) -> Result<&mut Self, ImageManipulationError> {
+        let from = self.layout_mut()?;
+        let to = Layout::new(format, pixel_type)?;
+        if from != to {
+            let pixel_count = self.pixels.len() / from.pixel_size();
+            let mut pixels = vec![0; pixel_count * to.pixel_size()];
+            let from_pixels = self.pixels.chunks_exact(from.pixel_size());
+            let to_pixels = pixels.chunks_exact_mut(to.pixel_size());
+            for (from_pixel, to_pixel) in from_pixels.zip(to_pixels) {
+                if from.channels == to.channels {
+                    // Only the bit depth changes, so the values can be
+                    // scaled without going through floats.
+                    for channel in 0..from.channels {
+                        let value = from.read(from_pixel, channel);
+                        let value = if to.sample_size > from.sample_size {
+                            value * 257
+                        } else {
+                            (value + 128) / 257
+                        };
+                        to.write(to_pixel, channel, value);
+                    }
+                } else {
+                    to.write_rgba(to_pixel, from.read_rgba(from_pixel));
+                }
+            }
+            self.pixels = pixels;
+        }
+        self.format = format;
+        self.pixel_type = pixel_type;
+        Ok(self)
+    }
+
+    /// Resizes the image to `width` x `height` pixels, using the
+    /// filter to sample the original image.
+    pub fn resize(
+        &mut self,
+        width: i32,
+        height: i32,
+        filter: ResizeFilter,
+    ) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        let (old_width, old_height) = (self.width as usize, self.height as usize);
+        let (new_width, new_height) = (width.max(0) as usize, height.max(0) as usize);
+        if old_width == 0 || old_height == 0 {
+            self.pixels = vec![0; new_width * new_height * layout.pixel_size()];
+        } else if filter == ResizeFilter::Nearest {
+            self.remap(layout, new_width * new_height, |i| {
+                let x = (i % new_width) * old_width / new_width;
+                let y = (i / new_width) * old_height / new_height;
+                Some(y * old_width + x)
+            });
+        } else {
+            let pixel_size = layout.pixel_size();
+            let mut pixels = vec![0; new_width * new_height * pixel_size];
+            // Maps the center of a new pixel to the old image's pixel
+            // coordinates, and returns the two pixels to interpolate
+            // between, and the interpolation factor.
+            let sample = |i: usize, new_size: usize, old_size: usize| {
+                let coord = (i as f32 + 0.5) * old_size as f32 / new_size as f32 - 0.5;
+                let coord = coord.max(0.0).min((old_size - 1) as f32);
+                let low = coord.floor() as usize;
+                (low, (low + 1).min(old_size - 1), coord - low as f32)
+            };
+            for (i, pixel) in pixels.chunks_exact_mut(pixel_size).enumerate() {
+                let (x0, x1, tx) = sample(i % new_width, new_width, old_width);
+                let (y0, y1, ty) = sample(i / new_width, new_height, old_height);
+                let old_pixel = |x: usize, y: usize| {
+                    let start = (y * old_width + x) * pixel_size;
+                    &self.pixels[start..start + pixel_size]
+                };
+                for channel in 0..layout.channels {
+                    let value = |x, y| layout.read(old_pixel(x, y), channel) as f32;
+                    let top = value(x0, y0) * (1.0 - tx) + value(x1, y0) * tx;
+                    let bottom = value(x0, y1) * (1.0 - tx) + value(x1, y1) * tx;
+                    let value = top * (1.0 - ty) + bottom * ty;
+                    layout.write(pixel, channel, value.round() as u32);
+                }
+            }
+            self.pixels = pixels;
+        }
+        self.width = new_width as i32;
+        self.height = new_height as i32;
+        Ok(self)
+    }
+
+    /// Copies `source` into this image, with its top-left corner at
+    /// (x, y). The parts that don't fit are left out. The pixels are
+    /// replaced, not blended.
+    ///
+    /// If the source is in another format, it's converted into this
+    /// image's format first, as with [`convert`](#method.convert).
+    pub fn blit(
+        &mut self,
+        source: &Image,
+        x: i32,
+        y: i32,
+    ) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        let converted;
+        let source = if source.format == self.format && source.pixel_type == self.pixel_type {
+            source.layout()?;
+            source
+        } else {
+            let mut copy = source.clone();
+            copy.convert(self.format, self.pixel_type)?;
+            converted = copy;
+            &converted
+        };
+
+        let pixel_size = layout.pixel_size();
+        let (x0, x1) = (x.max(0), x.saturating_add(source.width).min(self.width));
+        let (y0, y1) = (y.max(0), y.saturating_add(source.height).min(self.height));
+        if x1 <= x0 || y1 <= y0 {
+            // The source doesn't overlap this image at all.
+            return Ok(self);
+        }
+        let row_length = (x1 - x0) as usize * pixel_size;
+        for dest_y in y0..y1 {
+            let source_start = ((dest_y - y) * source.width + x0 - x) as usize * pixel_size;
+            let dest_start = (dest_y * self.width + x0) as usize * pixel_size;
+            self.pixels[dest_start..dest_start + row_length]
+                .copy_from_slice(&source.pixels[source_start..source_start + row_length]);
+        }
+        Ok(self)
+    }
+
+    /// Grows the image by `amount` pixels on each side, by repeating
+    /// the pixels at its edges outwards.
+    ///
+    /// Extruding sprites packed into a spritesheet prevents the
+    /// neighboring sprites from bleeding in when the texture is
+    /// filtered.
+    pub fn extrude(&mut self, amount: i32) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        let amount = amount.max(0);
+        let (old_width, old_height) = (self.width, self.height);
+        if old_width == 0 || old_height == 0 {
+            return Ok(self);
+        }
+        let (new_width, new_height) = (old_width + amount * 2, old_height + amount * 2);
+        self.remap(layout, (new_width * new_height) as usize, |i| {
+            let x = (i as i32 % new_width - amount).max(0).min(old_width - 1);
+            let y = (i as i32 / new_width - amount).max(0).min(old_height - 1);
+            Some((y * old_width + x) as usize)
+        });
+        self.width = new_width;
+        self.height = new_height;
+        Ok(self)
+    }
+
+    /// Makes the pixels of the given color (as normalized RGB)
+    /// transparent. If the image doesn't have an alpha channel, one
+    /// is added, so e.g. `SRGB` images become `SRGB_ALPHA`.
+    ///
+    /// The color is compared at the image's precision, so `(1.0,
+    /// 0.0, 1.0)` matches `#FF00FF` in images with 8 bits per
+    /// channel. `RED` and `RG` images only compare the luminance of
+    /// the color (see [`set_pixel`](#method.set_pixel)).
+    pub fn color_key(
+        &mut self,
+        (red, green, blue): (f32, f32, f32),
+    ) -> Result<&mut Self, ImageManipulationError> {
+        let layout = self.layout_mut()?;
+        if layout.alpha_channel().is_none() {
+            self.convert(with_alpha_channel(self.format), self.pixel_type)?;
+        }
+        let layout = self.layout_mut()?;
+        let alpha_channel = layout.alpha_channel().unwrap();
+
+        let mut key = vec![0; layout.pixel_size()];
+        layout.write_rgba(&mut key, [red, green, blue, 1.0]);
+        for pixel in self.pixels.chunks_exact_mut(layout.pixel_size()) {
+            let matches = (0..alpha_channel).all(|c| layout.read(pixel, c) == layout.read(&key, c));
+            if matches {
+                layout.write(pixel, alpha_channel, 0);
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[test]
+fn images_are_manipulated() -> Result<(), ImageManipulationError> {
+    // A 3x2 image: the top row is red, green and blue, the bottom row
+    // black, white and magenta.
+    #[rustfmt::skip]
+    let pixels = vec![
+        255, 0, 0,   0, 255, 0,     0, 0, 255,
+        0, 0, 0,     255, 255, 255, 255, 0, 255,
+    ];
+    let image = Image {
+        pixels,
+        width: 3,
+        height: 2,
+        format: gl::SRGB,
+        pixel_type: gl::UNSIGNED_BYTE,
+        null_data: false,
+        mipmaps: Vec::new(),
+    };
+
+    let mut rotated = image.clone();
+    rotated.rotate_clockwise()?;
+    assert_eq!((rotated.width, rotated.height), (2, 3));
+    assert_eq!(rotated.get_pixel(1, 0), Some((1.0, 0.0, 0.0, 1.0)));
+    assert_eq!(rotated.get_pixel(0, 2), Some((1.0, 0.0, 1.0, 1.0)));
+    rotated.rotate_counter_clockwise()?;
+    assert_eq!(rotated.pixels, image.pixels);
+
+    let mut cropped = image.clone();
+    cropped.crop((1, 1, 10, 10))?.flip_horizontal()?;
+    assert_eq!(cropped.pixels, vec![255, 0, 255, 255, 255, 255]);
+
+    let mut keyed = image.clone();
+    keyed.color_key((1.0, 0.0, 1.0))?;
+    assert_eq!(keyed.format, gl::SRGB_ALPHA);
+    assert_eq!(keyed.get_pixel(2, 1), Some((1.0, 0.0, 1.0, 0.0)));
+    assert_eq!(keyed.get_pixel(1, 1), Some((1.0, 1.0, 1.0, 1.0)));
+
+    let mut gray = image.clone();
+    gray.convert(gl::RED, gl::UNSIGNED_SHORT)?;
+    assert_eq!(gray.get_pixel(1, 1), Some((1.0, 1.0, 1.0, 1.0)));
+    gray.convert(gl::RGB, gl::UNSIGNED_BYTE)?;
+    assert_eq!(&gray.pixels[3..6], &[182, 182, 182]);
+
+    let mut resized = image.clone();
+    resized.resize(6, 4, ResizeFilter::Nearest)?;
+    assert_eq!(resized.get_pixel(5, 3), image.get_pixel(2, 1));
+    resized.resize(3, 2, ResizeFilter::Bilinear)?;
+    assert_eq!(resized.pixels, image.pixels);
+
+    let mut canvas = Image::with_color(4, 4, &[0, 0, 0, 0]).unwrap();
+    canvas.blit(&image, 2, 3)?.extrude(1)?;
+    assert_eq!((canvas.width, canvas.height), (6, 6));
+    assert_eq!(canvas.get_pixel(5, 5), Some((0.0, 1.0, 0.0, 1.0)));
+    canvas.premultiply_alpha()?;
+    assert_eq!(canvas.get_pixel(0, 0), Some((0.0, 0.0, 0.0, 0.0)));
+
+    let mut canvas = Image::with_color(4, 4, &[0, 0, 0, 0]).unwrap();
+    let untouched = canvas.pixels.clone();
+    for &(x, y) in &[(10, 0), (-100, 0), (0, 10), (0, -100), (4, 4), (-3, -2)] {
+        canvas.blit(&image, x, y)?;
+        assert_eq!(canvas.pixels, untouched);
+    }
+    canvas.blit(&image, -2, -1)?;
+    assert_eq!(canvas.get_pixel(0, 0), Some((1.0, 0.0, 1.0, 1.0)));
+    assert_eq!(canvas.get_pixel(1, 0), Some((0.0, 0.0, 0.0, 0.0)));
+    Ok(())
+}
@@ -0,0 +1,273 @@
+use crate::error::{Error, ImageCreationError};
+use crate::gl;
+use crate::gl::types::*;
+
+#[cfg(feature = "bmp")]
+mod bmp;
+pub(crate) mod compressed;
+#[cfg(feature = "dds")]
+mod dds;
+#[cfg(feature = "gif")]
+mod gif;
+#[cfg(feature = "jpeg")]
+mod jpeg;
+#[cfg(feature = "ktx")]
+mod ktx;
+mod manipulation;
+#[cfg(feature = "png")]
+mod png;
+#[cfg(feature = "qoi")]
+mod qoi;
+#[cfg(feature = "tga")]
+mod tga;
+
+pub use manipulation::ResizeFilter;
+
+/// Contains the raw pixel color data of an image.
+#[derive(Clone, Debug)]
+pub struct Image {
+    /// The pixels of the image.
+    pub pixels: Vec<u8>,
+    /// The width of the image.
+    pub width: i32,
+    /// The height of the image.
+    pub height: i32,
+    /// The OpenGL format of the image.
+    pub format: GLuint,
+    /// The OpenGL type of the pixels of the image.
+    pub pixel_type: GLuint,
+    /// Whether the image represents a null pointer for
+    /// glTexImage2D. If true, the memory for the texture of width x
+    /// height will be allocated on the GPU, but will probably be
+    /// garbage.
+    pub null_data: bool,
+    /// The pixels of the smaller mip levels, if the image has them,
+    /// each level half the size of the previous one, starting from
+    /// the level after the one in `pixels`. Loaded from KTX and DDS
+    /// files. The image manipulation methods only modify `pixels`,
+    /// so they clear these. On OpenGL ES 2.0, the mipmaps are only
+    /// used if they go all the way down to 1x1.
+    pub mipmaps: Vec<Vec<u8>>,
+}
+
+impl Image {
+    /// Parses an image, detecting its format from the first bytes of
+    /// the data. Each format requires its cargo feature: `png`,
+    /// `jpeg`, `gif`, `bmp`, `qoi`, `tga`, `ktx` or `dds`.
+    ///
+    /// TGA images don't have a signature at the start, so they're
+    /// only detected if their header looks valid, after all the other
+    /// formats have been ruled out.
+    ///
+    /// # Errors
+    ///
+    /// [`UnrecognizedFormat`](enum.ImageCreationError.html#variant.UnrecognizedFormat)
+    /// is returned if the format couldn't be detected, and
+    /// [`FormatNotEnabled`](enum.ImageCreationError.html#variant.FormatNotEnabled)
+    /// if the format's feature isn't enabled. Otherwise, the errors
+    /// of the format's loader, e.g.
+    /// [`with_png`](#method.with_png), are returned.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let background = fae::Image::from_bytes(&std::fs::read("background.jpg")?)?;
+    /// # Ok(()) }
+    /// ```
+    #[allow(unreachable_patterns)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Image, Error> {
+        match ImageFormat::detect(bytes) {
+            #[cfg(feature = "png")]
+            Some(ImageFormat::Png) => Ok(Image::with_png(bytes)?),
+            #[cfg(feature = "jpeg")]
+            Some(ImageFormat::Jpeg) => Ok(Image::with_jpeg(bytes)?),
+            #[cfg(feature = "gif")]
+            Some(ImageFormat::Gif) => Ok(Image::with_gif(bytes)?),
+            #[cfg(feature = "bmp")]
+            Some(ImageFormat::Bmp) => Ok(Image::with_bmp(bytes)?),
+            #[cfg(feature = "qoi")]
+            Some(ImageFormat::Qoi) => Ok(Image::with_qoi(bytes)?),
+            #[cfg(feature = "tga")]
+            Some(ImageFormat::Tga) => Ok(Image::with_tga(bytes)?),
+            #[cfg(feature = "ktx")]
+            Some(ImageFormat::Ktx) => Ok(Image::with_ktx(bytes)?),
+            #[cfg(feature = "dds")]
+            Some(ImageFormat::Dds) => Ok(Image::with_dds(bytes)?),
+            Some(format) => Err(ImageCreationError::FormatNotEnabled(format.feature()).into()),
+            None => Err(ImageCreationError::UnrecognizedFormat.into()),
+        }
+    }
+
+    /// Creates a solid color image.
+    ///
+    /// The color can be 1-4 items long, and will be interpreted in
+    /// the following order: red, green, blue, alpha.
+    ///
+    /// The color is interpreted as SRGB when 3 or 4 color components
+    /// are provided, to be consistent with loading images from the
+    /// disk, which are assumed to be in SRGB space by default.
+    ///
+    /// Based on the length of the `color` slice, the format of the
+    /// resulting image will be `gl::RED`, `gl::RG`, `gl::SRGB`, or
+    /// `gl::SRGB_ALPHA`. This can be changed with
+    /// [`format()`](struct.Image.html#method.format).
+    ///
+    /// # Example
+    /// ```
+    /// use fae::Image;
+    /// let image = Image::with_color(128, 128, &[0xB4, 0x6E, 0xC8, 0xFF]);
+    /// // image now represents a 128px by 128px image that consists of fully opaque violet pixels.
+    /// ```
+    pub fn with_color(width: i32, height: i32, color: &[u8]) -> Result<Image, ImageCreationError> {
+        let format = match color.len() {
+            4 => gl::SRGB_ALPHA,
+            3 => gl::SRGB,
+            2 => gl::RG,
+            1 => gl::RED,
+            n => return Err(ImageCreationError::InvalidColorComponentCount(n)),
+        };
+        let mut pixels = vec![0; (width * height) as usize * color.len()];
+        for i in 0..pixels.len() {
+            pixels[i] = color[i % color.len()];
+        }
+        Ok(Image {
+            pixels,
+            width,
+            height,
+            format,
+            pixel_type: gl::UNSIGNED_BYTE,
+            null_data: false,
+            mipmaps: Vec::new(),
+        })
+    }
+
+    /// Creates an image with a specified width, height and a format,
+    /// and signals to OpenGL that the texture will be filled in
+    /// later. The memory for the texture will be allocated on the
+    /// GPU, but no pixel data needs to be sent from the CPU to the
+    /// GPU during initialization.
+    ///
+    /// See also:
+    /// [`Spritesheet::upload_texture_region`](struct.Spritesheet.html#method.upload_texture_region).
+    pub fn with_null_texture(width: i32, height: i32, format: GLuint) -> Image {
+        Image {
+            pixels: Vec::new(),
+            width,
+            height,
+            format,
+            pixel_type: gl::UNSIGNED_BYTE,
+            null_data: true,
+            mipmaps: Vec::new(),
+        }
+    }
+
+    /// Sets the format of the pixels.
+    ///
+    /// Generally: `gl::RED` for grayscale pixels, `gl::RGB` for
+    /// linear non-transparent pixels, and `gl::RGBA` for linear and
+    /// transparent pixels.
+    ///
+    /// # Example
+    /// ```
+    /// use fae::{gl, Image};
+    /// # fn main() -> Result<(), fae::Error> {
+    /// let image = Image::with_color(128, 128, &[0x88])?.format(gl::RED);
+    /// // image now represents a 128px by 128px image that consists of half-red pixels taking up only one byte per pixel.
+    /// # Ok(()) }
+    /// ```
+    pub fn format(&mut self, format: GLuint) -> &mut Self {
+        self.format = format;
+        self
+    }
+}
+
+/// The image formats [`Image::from_bytes`](struct.Image.html#method.from_bytes) can detect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Bmp,
+    Qoi,
+    Tga,
+    Ktx,
+    Dds,
+}
+
+impl ImageFormat {
+    fn detect(bytes: &[u8]) -> Option<ImageFormat> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1A\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(ImageFormat::Gif)
+        } else if bytes.starts_with(b"BM") {
+            Some(ImageFormat::Bmp)
+        } else if bytes.starts_with(b"qoif") {
+            Some(ImageFormat::Qoi)
+        } else if bytes.starts_with(b"\xABKTX 11\xBB\r\n\x1A\n") {
+            Some(ImageFormat::Ktx)
+        } else if bytes.starts_with(b"DDS ") {
+            Some(ImageFormat::Dds)
+        } else if looks_like_tga(bytes) {
+            Some(ImageFormat::Tga)
+        } else {
+            None
+        }
+    }
+
+    fn feature(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Qoi => "qoi",
+            ImageFormat::Tga => "tga",
+            ImageFormat::Ktx => "ktx",
+            ImageFormat::Dds => "dds",
+        }
+    }
+}
+
+/// Returns true if the data ends in a TGA 2.0 footer, or starts with a
+/// plausible TGA header.
+fn looks_like_tga(bytes: &[u8]) -> bool {
+    if bytes.ends_with(b"TRUEVISION-XFILE.\0") {
+        return true;
+    }
+    match bytes.get(0..18) {
+        Some(header) => {
+            let color_map_type_valid = header[1] <= 1;
+            let image_type_valid = [1, 2, 3, 9, 10, 11].contains(&header[2]);
+            let pixel_bits_valid = [8, 15, 16, 24, 32].contains(&header[16]);
+            let size_valid = header[12..16].iter().any(|&b| b != 0);
+            color_map_type_valid && image_type_valid && pixel_bits_valid && size_valid
+        }
+        None => false,
+    }
+}
+
+#[test]
+fn image_formats_are_detected() {
+    assert_eq!(
+        ImageFormat::detect(b"\x89PNG\r\n\x1A\n"),
+        Some(ImageFormat::Png)
+    );
+    assert_eq!(
+        ImageFormat::detect(&[0xFF, 0xD8, 0xFF, 0xE0]),
+        Some(ImageFormat::Jpeg)
+    );
+    assert_eq!(ImageFormat::detect(b"GIF89a"), Some(ImageFormat::Gif));
+    assert_eq!(ImageFormat::detect(b"BM"), Some(ImageFormat::Bmp));
+    assert_eq!(ImageFormat::detect(b"qoif"), Some(ImageFormat::Qoi));
+    assert_eq!(
+        ImageFormat::detect(b"\xABKTX 11\xBB\r\n\x1A\n"),
+        Some(ImageFormat::Ktx)
+    );
+    assert_eq!(ImageFormat::detect(b"DDS "), Some(ImageFormat::Dds));
+    let tga_header = [0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 24, 0];
+    assert_eq!(ImageFormat::detect(&tga_header), Some(ImageFormat::Tga));
+    assert_eq!(ImageFormat::detect(b"hello, world!"), None);
+}
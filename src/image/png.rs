@@ -0,0 +1,323 @@
+use crate::error::{PngEncodingError, PngLoadingError};
+use crate::gl;
+use crate::image::Image;
+
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+use std::fs;
+use std::path::Path;
+
+impl Image {
+    /// Parses a PNG image and makes an `Image` out of it.
+    ///
+    /// This function assumes that the image is in SRGB space, so the
+    /// image `format` is `SRGB` or `SRGB_ALPHA`, depending on
+    /// whether the image has transparency.
+    ///
+    /// # Color type notes
+    ///
+    /// All PNG color types are converted into RGB or RGBA, so that
+    /// they display as expected with the default shaders:
+    ///
+    /// - Indexed images are expanded into RGB using their palette, or
+    ///   RGBA, if they have a `tRNS` chunk.
+    /// - Grayscale and grayscale-alpha images are expanded into RGB
+    ///   and RGBA respectively.
+    /// - Images with a `tRNS` chunk get an alpha channel.
+    /// - Images with less than 8 bits per channel are scaled up to 8
+    ///   bits per channel.
+    ///
+    /// Images with 16 bits per channel keep their precision, and use
+    /// `UNSIGNED_SHORT` as their `pixel_type`. The samples are
+    /// converted into the native byte order, as OpenGL expects. To
+    /// convert them to 8 bits per channel instead, use
+    /// [`with_png_8bpc`](#method.with_png_8bpc).
+    ///
+    /// # Errors
+    ///
+    /// A [`PngError`](enum.PngLoadingError.html#variant.PngError)
+    /// will be returned if the data couldn't be read for some reason
+    /// by the `png` crate (most probably, `bytes` doesn't describe a
+    /// valid PNG image).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let sprite = fae::Image::with_png(&std::fs::read("sprite.png")?)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_png(bytes: &[u8]) -> Result<Image, PngLoadingError> {
+        decode_png(bytes, false)
+    }
+
+    /// Parses a PNG image and makes an `Image` out of it, like
+    /// [`with_png`](#method.with_png), but converts images with 16
+    /// bits per channel to 8 bits per channel. The `pixel_type` of
+    /// the image will always be `UNSIGNED_BYTE`.
+    pub fn with_png_8bpc(bytes: &[u8]) -> Result<Image, PngLoadingError> {
+        decode_png(bytes, true)
+    }
+
+    /// Encodes the image as a PNG.
+    ///
+    /// `RED`, `RG`, `RGB`/`SRGB` and `RGBA`/`SRGB_ALPHA` images are
+    /// encoded as grayscale, grayscale-alpha, RGB and RGBA PNGs
+    /// respectively, with 8 or 16 bits per channel depending on the
+    /// `pixel_type` (`UNSIGNED_BYTE` or `UNSIGNED_SHORT`). Images with
+    /// the `SRGB` and `SRGB_ALPHA` formats are marked as sRGB with an
+    /// `sRGB` chunk, and `RGB` and `RGBA` as linear with a `gAMA`
+    /// chunk.
+    ///
+    /// Note that [`with_png`](#method.with_png) treats all PNGs as
+    /// sRGB, so decoding the PNG results in the same pixels, but
+    /// possibly a different `format`.
+    ///
+    /// # Errors
+    ///
+    /// An [`InvalidImage`](enum.PngEncodingError.html#variant.InvalidImage)
+    /// error is returned if the image's format isn't supported, and a
+    /// [`PngError`](enum.PngEncodingError.html#variant.PngError) if
+    /// the `png` crate fails to encode it.
+    pub fn encode_png(&self) -> Result<Vec<u8>, PngEncodingError> {
+        let layout = self.layout()?;
+        let color_type = match layout.channels {
+            1 => ColorType::Grayscale,
+            2 => ColorType::GrayscaleAlpha,
+            3 => ColorType::RGB,
+            _ => ColorType::RGBA,
+        };
+
+        let mut bytes = Vec::new();
+        let mut encoder = Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(color_type);
+        if layout.sample_size == 2 {
+            encoder.set_depth(BitDepth::Sixteen);
+        } else {
+            encoder.set_depth(BitDepth::Eight);
+        }
+        let mut writer = encoder.write_header()?;
+        match self.format {
+            // Rendering intent: perceptual.
+            gl::SRGB | gl::SRGB_ALPHA => writer.write_chunk(*b"sRGB", &[0])?,
+            // Gamma of 1.0, i.e. linear, multiplied by 100000.
+            gl::RGB | gl::RGBA => writer.write_chunk(*b"gAMA", &100_000u32.to_be_bytes())?,
+            _ => {}
+        }
+        if layout.sample_size == 2 && cfg!(target_endian = "little") {
+            let mut pixels = self.pixels.clone();
+            for sample in pixels.chunks_exact_mut(2) {
+                sample.swap(0, 1);
+            }
+            writer.write_image_data(&pixels)?;
+        } else {
+            writer.write_image_data(&self.pixels)?;
+        }
+        drop(writer);
+        Ok(bytes)
+    }
+
+    /// Encodes the image as a PNG with
+    /// [`encode_png`](#method.encode_png), and writes it to the file
+    /// at `path`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), fae::Error> {
+    /// use fae::Image;
+    /// let mut image = Image::with_color(64, 64, &[0x33, 0x33, 0x33, 0xFF])?;
+    /// image.set_pixel(32, 32, (1.0, 1.0, 1.0, 1.0))?;
+    /// image.save_png("baked.png")?;
+    /// # Ok(()) }
+    /// ```
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), PngEncodingError> {
+        fs::write(path, self.encode_png()?)?;
+        Ok(())
+    }
+}
+
+fn decode_png(bytes: &[u8], strip_16_bits: bool) -> Result<Image, PngLoadingError> {
+    let mut decoder = Decoder::new(bytes);
+    decoder.set_transformations(if strip_16_bits {
+        Transformations::EXPAND | Transformations::STRIP_16
+    } else {
+        Transformations::EXPAND
+    });
+    let (info, mut reader) = decoder.read_info()?;
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels)?;
+
+    // After expansion, the bit depth is either 8 or 16, and the color
+    // type anything but indexed.
+    let sample_size = match info.bit_depth {
+        BitDepth::Sixteen => 2,
+        _ => 1,
+    };
+    let (mut pixels, format) = match info.color_type {
+        ColorType::RGB => (pixels, gl::SRGB),
+        ColorType::RGBA => (pixels, gl::SRGB_ALPHA),
+        ColorType::Grayscale => (expand_gray(&pixels, sample_size, false), gl::SRGB),
+        ColorType::GrayscaleAlpha => (expand_gray(&pixels, sample_size, true), gl::SRGB_ALPHA),
+        // Indexed images are expanded by the decoder.
+        ColorType::Indexed => unreachable!(),
+    };
+    let pixel_type = if sample_size == 2 {
+        // PNG samples are big-endian, OpenGL reads them in the
+        // native byte order.
+        if cfg!(target_endian = "little") {
+            for sample in pixels.chunks_exact_mut(2) {
+                sample.swap(0, 1);
+            }
+        }
+        gl::UNSIGNED_SHORT
+    } else {
+        gl::UNSIGNED_BYTE
+    };
+
+    Ok(Image {
+        pixels,
+        width: info.width as i32,
+        height: info.height as i32,
+        format,
+        pixel_type,
+        null_data: false,
+        mipmaps: Vec::new(),
+    })
+}
+
+/// Expands gray samples (followed by alpha samples, if `alpha`) of
+/// `sample_size` bytes into RGB(A).
+fn expand_gray(pixels: &[u8], sample_size: usize, alpha: bool) -> Vec<u8> {
+    let input_pixel_size = if alpha { 2 } else { 1 } * sample_size;
+    let output_pixel_size = if alpha { 4 } else { 3 } * sample_size;
+    let mut expanded = Vec::with_capacity(pixels.len() / input_pixel_size * output_pixel_size);
+    for pixel in pixels.chunks_exact(input_pixel_size) {
+        let (gray, alpha) = pixel.split_at(sample_size);
+        for _ in 0..3 {
+            expanded.extend_from_slice(gray);
+        }
+        expanded.extend_from_slice(alpha);
+    }
+    expanded
+}
+
+#[test]
+fn png_color_types_and_bit_depths_are_converted() {
+    let encode =
+        |color_type, bit_depth, palette: Option<&[u8]>, trns: Option<&[u8]>, data: &[u8]| {
+            let mut bytes = Vec::new();
+            {
+                let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+                encoder.set_color(color_type);
+                encoder.set_depth(bit_depth);
+                if let Some(palette) = palette {
+                    encoder.set_palette(palette.to_vec());
+                }
+                if let Some(trns) = trns {
+                    encoder.set_trns(trns.to_vec());
+                }
+                let mut writer = encoder.write_header().unwrap();
+                writer.write_image_data(data).unwrap();
+            }
+            bytes
+        };
+
+    // Indexed, 2 bits per pixel, with the second color transparent.
+    let palette = [255, 0, 0, 0, 0, 255];
+    let png = encode(
+        ColorType::Indexed,
+        BitDepth::Two,
+        Some(&palette),
+        Some(&[255, 0]),
+        &[0b0001_0000],
+    );
+    let image = Image::with_png(&png).unwrap();
+    assert_eq!(image.format, gl::SRGB_ALPHA);
+    assert_eq!(image.pixels, vec![255, 0, 0, 255, 0, 0, 255, 0]);
+
+    // Grayscale, 1 bit per pixel.
+    let png = encode(
+        ColorType::Grayscale,
+        BitDepth::One,
+        None,
+        None,
+        &[0b0100_0000],
+    );
+    let image = Image::with_png(&png).unwrap();
+    assert_eq!(image.format, gl::SRGB);
+    assert_eq!(image.pixels, vec![0, 0, 0, 255, 255, 255]);
+
+    // Grayscale-alpha, 16 bits per channel.
+    let data = [0x12, 0x34, 0xFF, 0xFF, 0xAB, 0xCD, 0x00, 0x00];
+    let png = encode(
+        ColorType::GrayscaleAlpha,
+        BitDepth::Sixteen,
+        None,
+        None,
+        &data,
+    );
+    let image = Image::with_png(&png).unwrap();
+    assert_eq!(image.format, gl::SRGB_ALPHA);
+    assert_eq!(image.pixel_type, gl::UNSIGNED_SHORT);
+    let samples: Vec<u16> = image
+        .pixels
+        .chunks_exact(2)
+        .map(|sample| u16::from_ne_bytes([sample[0], sample[1]]))
+        .collect();
+    assert_eq!(
+        samples,
+        vec![0x1234, 0x1234, 0x1234, 0xFFFF, 0xABCD, 0xABCD, 0xABCD, 0]
+    );
+
+    let image = Image::with_png_8bpc(&png).unwrap();
+    assert_eq!(image.pixel_type, gl::UNSIGNED_BYTE);
+    assert_eq!(
+        image.pixels,
+        vec![0x12, 0x12, 0x12, 0xFF, 0xAB, 0xAB, 0xAB, 0]
+    );
+}
+
+#[test]
+fn png_encoding_round_trips() {
+    let image = |format, pixel_type, pixels: Vec<u8>| Image {
+        pixels,
+        width: 2,
+        height: 1,
+        format,
+        pixel_type,
+        null_data: false,
+        mipmaps: Vec::new(),
+    };
+    let images = [
+        image(
+            gl::SRGB_ALPHA,
+            gl::UNSIGNED_BYTE,
+            vec![1, 2, 3, 4, 5, 6, 7, 8],
+        ),
+        image(gl::SRGB, gl::UNSIGNED_SHORT, (0..12).collect()),
+        image(gl::RGB, gl::UNSIGNED_BYTE, vec![10, 20, 30, 40, 50, 60]),
+    ];
+    for original in &images {
+        let decoded = Image::with_png(&original.encode_png().unwrap()).unwrap();
+        assert_eq!(decoded.pixels, original.pixels);
+        assert_eq!(decoded.pixel_type, original.pixel_type);
+    }
+    assert!(images[0]
+        .encode_png()
+        .unwrap()
+        .windows(4)
+        .any(|w| w == b"sRGB"));
+    assert!(images[2]
+        .encode_png()
+        .unwrap()
+        .windows(4)
+        .any(|w| w == b"gAMA"));
+
+    // Grayscale is expanded into RGB when decoded.
+    let gray = image(
+        gl::RG,
+        gl::UNSIGNED_SHORT,
+        vec![0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0],
+    );
+    let mut decoded = Image::with_png(&gray.encode_png().unwrap()).unwrap();
+    decoded.convert(gl::RG, gl::UNSIGNED_SHORT).unwrap();
+    assert_eq!(decoded.pixels, gray.pixels);
+}
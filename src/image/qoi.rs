@@ -0,0 +1,46 @@
+use crate::error::QoiLoadingError;
+use crate::gl;
+use crate::image::Image;
+
+use qoi::{Channels, ColorSpace};
+
+impl Image {
+    /// Parses a QOI image and makes an `Image` out of it.
+    ///
+    /// The format will be `SRGB` or `SRGB_ALPHA` if the image's header
+    /// declares it to be in SRGB space, and `RGB` or `RGBA` if it's
+    /// linear.
+    ///
+    /// Requires the `qoi` feature.
+    ///
+    /// # Errors
+    ///
+    /// A [`QoiError`](enum.QoiLoadingError.html#variant.QoiError)
+    /// will be returned if the data couldn't be read by the `qoi`
+    /// crate.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let sprite = fae::Image::with_qoi(&std::fs::read("sprite.qoi")?)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_qoi(bytes: &[u8]) -> Result<Image, QoiLoadingError> {
+        let (header, pixels) = qoi::decode_to_vec(bytes)?;
+        let format = match (header.channels, header.colorspace) {
+            (Channels::Rgb, ColorSpace::Srgb) => gl::SRGB,
+            (Channels::Rgba, ColorSpace::Srgb) => gl::SRGB_ALPHA,
+            (Channels::Rgb, ColorSpace::Linear) => gl::RGB,
+            (Channels::Rgba, ColorSpace::Linear) => gl::RGBA,
+        };
+        Ok(Image {
+            pixels,
+            width: header.width as i32,
+            height: header.height as i32,
+            format,
+            pixel_type: gl::UNSIGNED_BYTE,
+            null_data: false,
+            mipmaps: Vec::new(),
+        })
+    }
+}
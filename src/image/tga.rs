@@ -0,0 +1,238 @@
+use crate::error::TgaLoadingError;
+use crate::gl;
+use crate::image::Image;
+
+impl Image {
+    /// Parses a TGA image and makes an `Image` out of it.
+    ///
+    /// Color-mapped, true-color and grayscale images are supported,
+    /// both uncompressed and run-length encoded. Images with an alpha
+    /// channel will have the `SRGB_ALPHA` format, and other color
+    /// images `SRGB`. Grayscale images will use `GL_RED`, or `GL_RG`
+    /// if they have an alpha channel.
+    ///
+    /// Requires the `tga` feature.
+    ///
+    /// # Errors
+    ///
+    /// See [`TgaLoadingError`](enum.TgaLoadingError.html).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let texture = fae::Image::with_tga(&std::fs::read("texture.tga")?)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_tga(bytes: &[u8]) -> Result<Image, TgaLoadingError> {
+        let header = bytes.get(0..18).ok_or(TgaLoadingError::UnexpectedEof)?;
+        let id_length = header[0] as usize;
+        let image_type = header[2];
+        let color_map_length = u16::from_le_bytes([header[5], header[6]]) as usize;
+        let color_map_entry_bits = header[7];
+        let width = u16::from_le_bytes([header[12], header[13]]) as usize;
+        let height = u16::from_le_bytes([header[14], header[15]]) as usize;
+        let pixel_bits = header[16];
+        let descriptor = header[17];
+        let alpha_bits = descriptor & 0b1111;
+        let right_to_left = descriptor & 0b1_0000 != 0;
+        let top_to_bottom = descriptor & 0b10_0000 != 0;
+
+        let color_mapped = image_type & 0b111 == 1;
+        let grayscale = image_type & 0b111 == 3;
+        let run_length_encoded = image_type & 0b1000 != 0;
+        match image_type {
+            1 | 2 | 3 | 9 | 10 | 11 => {}
+            _ => return Err(TgaLoadingError::UnsupportedImageType(image_type)),
+        }
+        let color_bits = if color_mapped {
+            match pixel_bits {
+                8 | 16 => {}
+                _ => return Err(TgaLoadingError::UnsupportedBitDepth(pixel_bits)),
+            }
+            color_map_entry_bits
+        } else {
+            pixel_bits
+        };
+        let channels = match (grayscale, color_bits) {
+            (true, 8) => 1,
+            (true, 16) => 2,
+            (false, 15) | (false, 16) if alpha_bits == 0 => 3,
+            (false, 15) | (false, 16) => 4,
+            (false, 24) => 3,
+            (false, 32) => 4,
+            _ => return Err(TgaLoadingError::UnsupportedBitDepth(color_bits)),
+        };
+
+        let color_map_start = 18 + id_length;
+        let color_map_entry_size = (color_map_entry_bits as usize).div_ceil(8);
+        let color_map_size = if header[1] == 1 {
+            color_map_length * color_map_entry_size
+        } else {
+            0
+        };
+        let color_map = bytes
+            .get(color_map_start..color_map_start + color_map_size)
+            .ok_or(TgaLoadingError::UnexpectedEof)?;
+        let color_map_first_index = u16::from_le_bytes([header[3], header[4]]) as usize;
+
+        let pixel_size = (pixel_bits as usize).div_ceil(8);
+        let data = &bytes[color_map_start + color_map_size..];
+        let pixel_count = width
+            .checked_mul(height)
+            .ok_or(TgaLoadingError::UnexpectedEof)?;
+        let raw_pixels = if run_length_encoded {
+            decode_run_lengths(data, pixel_count, pixel_size)?
+        } else {
+            let raw = pixel_count
+                .checked_mul(pixel_size)
+                .and_then(|size| data.get(..size));
+            raw.ok_or(TgaLoadingError::UnexpectedEof)?.to_vec()
+        };
+
+        let mut pixels = vec![0; width * height * channels];
+        for (i, raw) in raw_pixels.chunks_exact(pixel_size).enumerate() {
+            let (x, y) = (i % width, i / width);
+            let x = if right_to_left { width - 1 - x } else { x };
+            let y = if top_to_bottom { y } else { height - 1 - y };
+            let color = if color_mapped {
+                let index = if pixel_size == 1 {
+                    raw[0] as usize
+                } else {
+                    u16::from_le_bytes([raw[0], raw[1]]) as usize
+                };
+                let start = index.saturating_sub(color_map_first_index) * color_map_entry_size;
+                match color_map.get(start..start + color_map_entry_size) {
+                    Some(entry) => entry,
+                    None => &[0; 4][..color_map_entry_size],
+                }
+            } else {
+                raw
+            };
+            let start = (y * width + x) * channels;
+            write_color(color, color_bits, &mut pixels[start..start + channels]);
+        }
+
+        Ok(Image {
+            pixels,
+            width: width as i32,
+            height: height as i32,
+            format: match channels {
+                1 => gl::RED,
+                2 => gl::RG,
+                3 => gl::SRGB,
+                _ => gl::SRGB_ALPHA,
+            },
+            pixel_type: gl::UNSIGNED_BYTE,
+            null_data: false,
+            mipmaps: Vec::new(),
+        })
+    }
+}
+
+/// Expands the run-length encoded packets of `data` into
+/// `pixel_count` pixels.
+fn decode_run_lengths(
+    data: &[u8],
+    pixel_count: usize,
+    pixel_size: usize,
+) -> Result<Vec<u8>, TgaLoadingError> {
+    // A packet expands into at most 128 pixels, so a header claiming
+    // more than that per byte of data can't be satisfied.
+    if pixel_count > data.len().saturating_mul(128) {
+        return Err(TgaLoadingError::UnexpectedEof);
+    }
+    let mut pixels = Vec::with_capacity(pixel_count * pixel_size);
+    let mut cursor = 0;
+    while pixels.len() < pixel_count * pixel_size {
+        let packet_header = *data.get(cursor).ok_or(TgaLoadingError::UnexpectedEof)?;
+        let count = (packet_header & 0x7F) as usize + 1;
+        cursor += 1;
+        if packet_header & 0x80 != 0 {
+            let pixel = data
+                .get(cursor..cursor + pixel_size)
+                .ok_or(TgaLoadingError::UnexpectedEof)?;
+            for _ in 0..count {
+                pixels.extend_from_slice(pixel);
+            }
+            cursor += pixel_size;
+        } else {
+            let run = data
+                .get(cursor..cursor + count * pixel_size)
+                .ok_or(TgaLoadingError::UnexpectedEof)?;
+            pixels.extend_from_slice(run);
+            cursor += count * pixel_size;
+        }
+    }
+    pixels.truncate(pixel_count * pixel_size);
+    Ok(pixels)
+}
+
+/// Converts a color stored in the TGA's (BGR) order into RGB(A), or
+/// grayscale.
+fn write_color(color: &[u8], bits: u8, output: &mut [u8]) {
+    match bits {
+        15 | 16 if output.len() >= 3 => {
+            let value = u16::from_le_bytes([color[0], color[1]]);
+            let expand = |bits: u16| ((bits & 0x1F) * 255 / 31) as u8;
+            output[0] = expand(value >> 10);
+            output[1] = expand(value >> 5);
+            output[2] = expand(value);
+            if output.len() == 4 {
+                output[3] = if value & 0x8000 != 0 { 0xFF } else { 0 };
+            }
+        }
+        24 | 32 => {
+            output[0] = color[2];
+            output[1] = color[1];
+            output[2] = color[0];
+            if output.len() == 4 {
+                output[3] = *color.get(3).unwrap_or(&0xFF);
+            }
+        }
+        _ => output.copy_from_slice(&color[..output.len()]),
+    }
+}
+
+#[test]
+fn tga_images_are_decoded() {
+    let tga = |image_type: u8, pixel_bits: u8, descriptor: u8, data: &[u8]| {
+        let mut bytes = vec![0, 0, image_type, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0];
+        bytes.extend_from_slice(&[pixel_bits, descriptor]);
+        bytes.extend_from_slice(data);
+        bytes
+    };
+
+    // 2x2, true-color, bottom-up.
+    let data = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+    let image = Image::with_tga(&tga(2, 24, 0, &data)).unwrap();
+    assert_eq!(image.format, gl::SRGB);
+    assert_eq!(
+        image.pixels,
+        vec![255, 0, 0, 255, 255, 255, 0, 0, 255, 0, 255, 0]
+    );
+
+    // 2x2, run-length encoded grayscale with alpha, top-down.
+    let data = [0x81, 10, 20, 0x01, 30, 40, 50, 60];
+    let image = Image::with_tga(&tga(11, 16, 0b10_1000, &data)).unwrap();
+    assert_eq!(image.format, gl::RG);
+    assert_eq!(image.pixels, vec![10, 20, 10, 20, 30, 40, 50, 60]);
+
+    assert!(Image::with_tga(&tga(2, 24, 0, &data[..4])).is_err());
+    assert!(Image::with_tga(&tga(3, 24, 0, &[0; 12])).is_err());
+}
+
+#[test]
+fn malformed_tga_headers_are_rejected() {
+    // 65535x65535, run-length encoded true-color, without any data.
+    let mut bytes = vec![0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    bytes.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 32, 0]);
+    assert_eq!(bytes.len(), 18);
+    match Image::with_tga(&bytes) {
+        Err(TgaLoadingError::UnexpectedEof) => {}
+        result => panic!("expected UnexpectedEof, got {:?}", result.map(|_| ())),
+    }
+
+    // The same, but uncompressed.
+    bytes[2] = 2;
+    assert!(Image::with_tga(&bytes).is_err());
+}
@@ -6,16 +6,32 @@
 
 pub use gl;
 
+mod anchor;
+mod animation;
 mod api;
+mod asset_loader;
+#[cfg(feature = "sheet-import")]
+mod atlas;
+mod canvas;
+mod capabilities;
 mod error;
+mod gl_debug;
 mod gl_version;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
 mod image;
+mod particles;
+mod post_process;
+mod render_target;
 mod renderer;
 mod shaders;
 mod sprite;
 #[cfg(feature = "text")]
 mod text;
+mod tilemap;
 mod types;
+mod video;
+mod virtual_resolution;
 
 pub mod profiler;
 pub use api::*;
@@ -0,0 +1,447 @@
+//! A CPU-simulated particle system, drawn straight into a
+//! spritesheet's buffers.
+use crate::api::{GraphicsContext, Spritesheet};
+use crate::renderer::DrawCallHandle;
+use crate::types::Rect;
+
+use std::f32::consts::PI;
+
+/// The area where new particles are spawned, centered on the
+/// emitter's position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmissionShape {
+    /// All particles spawn at the emitter's position.
+    Point,
+    /// Particles spawn anywhere inside a circle.
+    Circle {
+        /// The radius of the circle, in logical pixels.
+        radius: f32,
+    },
+    /// Particles spawn on the edge of a circle.
+    Ring {
+        /// The radius of the ring, in logical pixels.
+        radius: f32,
+    },
+    /// Particles spawn anywhere inside a rectangle.
+    Rectangle {
+        /// The width of the rectangle, in logical pixels.
+        width: f32,
+        /// The height of the rectangle, in logical pixels.
+        height: f32,
+    },
+}
+
+/// Describes how a [`ParticleEmitter`](struct.ParticleEmitter.html)
+/// spawns and moves its particles.
+///
+/// Ranges are (min, max) pairs, and each particle picks a random
+/// value in between when spawned. Curves are (time, value) keyframes
+/// sorted by time, where time goes from 0.0 (the particle spawned) to
+/// 1.0 (the particle's lifetime is over), and values are linearly
+/// interpolated between the keyframes.
+#[derive(Clone, Debug)]
+pub struct ParticleSettings {
+    /// Where the particles spawn.
+    pub shape: EmissionShape,
+    /// How many particles are spawned per second.
+    pub rate: f32,
+    /// The maximum amount of particles alive at once. No particles
+    /// are spawned while at the limit.
+    pub max_particles: usize,
+    /// The range of the particles' lifetimes, in seconds.
+    pub lifetime: (f32, f32),
+    /// The range of the particles' initial speeds, in logical pixels
+    /// per second.
+    pub speed: (f32, f32),
+    /// The direction the particles are launched towards, in radians,
+    /// 0.0 being right and PI / 2 being down.
+    pub direction: f32,
+    /// The width of the cone around `direction` that the particles
+    /// are launched in, in radians. PI * 2 launches particles in
+    /// every direction.
+    pub spread: f32,
+    /// The acceleration applied to every particle, in logical pixels
+    /// per second squared.
+    pub gravity: (f32, f32),
+    /// The range of the particles' rotation speeds, in radians per
+    /// second.
+    pub angular_velocity: (f32, f32),
+    /// The color (red, green, blue, alpha) curve.
+    pub colors: Vec<(f32, (f32, f32, f32, f32))>,
+    /// The size curve. The particles are squares of this width and
+    /// height, in logical pixels.
+    pub sizes: Vec<(f32, f32)>,
+    /// The area of the texture (in actual pixels, in the texture's
+    /// coordinate space) drawn on the particles. If None, the
+    /// particles are drawn with their flat color.
+    pub texture_coordinates: Option<Rect>,
+    /// The Z-coordinate of the particles.
+    pub z: f32,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> ParticleSettings {
+        ParticleSettings {
+            shape: EmissionShape::Point,
+            rate: 10.0,
+            max_particles: 1000,
+            lifetime: (1.0, 1.0),
+            speed: (50.0, 100.0),
+            direction: -PI / 2.0,
+            spread: PI / 4.0,
+            gravity: (0.0, 0.0),
+            angular_velocity: (0.0, 0.0),
+            colors: vec![(0.0, (1.0, 1.0, 1.0, 1.0)), (1.0, (1.0, 1.0, 1.0, 0.0))],
+            sizes: vec![(0.0, 8.0)],
+            texture_coordinates: None,
+            z: 0.0,
+        }
+    }
+}
+
+/// Spawns, simulates and draws particles with a spritesheet.
+///
+/// The particles are simulated on the CPU, and drawn without going
+/// through the [`Sprite`](struct.Sprite.html) builder. The blending
+/// of the particles is decided by the spritesheet, see
+/// [`SpritesheetBuilder::blend_mode`](struct.SpritesheetBuilder.html#method.blend_mode).
+///
+/// The simulation only depends on the settings, the seed, and the
+/// timesteps passed to [`update`](#method.update), so it can be
+/// reproduced exactly.
+///
+/// # Example
+/// ```no_run
+/// # let mut ctx = fae::Context::new();
+/// use fae::{BlendMode, EmissionShape, ParticleEmitter, ParticleSettings, SpritesheetBuilder};
+/// let sparks = SpritesheetBuilder::default()
+///     .blend_mode(BlendMode::Additive)
+///     .build(&mut ctx);
+/// let settings = ParticleSettings {
+///     shape: EmissionShape::Circle { radius: 8.0 },
+///     rate: 200.0,
+///     gravity: (0.0, 300.0),
+///     colors: vec![(0.0, (1.0, 0.8, 0.2, 1.0)), (1.0, (1.0, 0.2, 0.0, 0.0))],
+///     ..Default::default()
+/// };
+/// let mut emitter = ParticleEmitter::new(&sparks, settings, 0xC0FFEE);
+///
+/// // Every frame:
+/// # let (width, height, dpi_factor, delta_seconds) = (0.0, 0.0, 0.0, 0.0);
+/// emitter.set_position(320.0, 240.0);
+/// emitter.update(delta_seconds);
+/// let mut ctx = ctx.start_frame(width, height, dpi_factor);
+/// emitter.draw(&mut ctx);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ParticleEmitter {
+    call: DrawCallHandle,
+    simulation: Simulation,
+}
+
+impl ParticleEmitter {
+    /// Creates an emitter that draws its particles with
+    /// `spritesheet`. The `seed` decides the random values of the
+    /// particles.
+    pub fn new(
+        spritesheet: &Spritesheet,
+        settings: ParticleSettings,
+        seed: u64,
+    ) -> ParticleEmitter {
+        ParticleEmitter {
+            call: spritesheet.handle.clone(),
+            simulation: Simulation::new(settings, seed),
+        }
+    }
+
+    /// Returns the emitter's settings.
+    pub fn settings(&self) -> &ParticleSettings {
+        &self.simulation.settings
+    }
+
+    /// Returns the emitter's settings for modification. The changes
+    /// apply to the particles spawned after the change, except for
+    /// `gravity` and the curves, which apply to every particle.
+    pub fn settings_mut(&mut self) -> &mut ParticleSettings {
+        &mut self.simulation.settings
+    }
+
+    /// Moves the emitter. Particles that have already been spawned
+    /// are not moved.
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.simulation.position = (x, y);
+    }
+
+    /// Sets whether the emitter spawns particles at its `rate`.
+    /// Particles can still be spawned with
+    /// [`burst`](#method.burst) when not emitting.
+    pub fn set_emitting(&mut self, emitting: bool) {
+        self.simulation.emitting = emitting;
+    }
+
+    /// Spawns `count` particles at once, up to the `max_particles`
+    /// limit.
+    pub fn burst(&mut self, count: usize) {
+        self.simulation.burst(count);
+    }
+
+    /// Advances the simulation by `seconds`: moves the particles,
+    /// removes the ones whose lifetime is over, and spawns new ones.
+    pub fn update(&mut self, seconds: f32) {
+        self.simulation.update(seconds);
+    }
+
+    /// Returns the amount of particles currently alive.
+    pub fn particle_count(&self) -> usize {
+        self.simulation.particles.len()
+    }
+
+    /// Removes all the particles.
+    pub fn clear(&mut self) {
+        self.simulation.particles.clear();
+    }
+
+    /// Draws the particles.
+    pub fn draw(&self, ctx: &mut GraphicsContext) {
+        let simulation = &self.simulation;
+        let settings = &simulation.settings;
+        let renderer = &mut *ctx.renderer;
+        let texcoords = match settings.texture_coordinates {
+            Some(rect) => {
+                let (width, height) = renderer.get_texture_size(&self.call);
+                let (width, height) = (width as f32, height as f32);
+                let (x0, y0, x1, y1) = rect.into_corners();
+                (x0 / width, y0 / height, x1 / width, y1 / height)
+            }
+            None => (-1.0, -1.0, -1.0, -1.0),
+        };
+
+        renderer.reserve_quads(&self.call, simulation.particles.len());
+        for particle in &simulation.particles {
+            let t = particle.age / particle.lifetime;
+            let color = sample(&settings.colors, t, lerp_color).unwrap_or((1.0, 1.0, 1.0, 1.0));
+            let size = sample(&settings.sizes, t, lerp).unwrap_or(0.0);
+            let (x, y) = particle.position;
+            let half = size / 2.0;
+            renderer.draw_quad(
+                (x - half, y - half, x + half, y + half),
+                texcoords,
+                color,
+                (particle.rotation, half, half),
+                settings.z,
+                &[],
+                &self.call,
+            );
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Particle {
+    position: (f32, f32),
+    velocity: (f32, f32),
+    rotation: f32,
+    angular_velocity: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+/// The state of an emitter, apart from the spritesheet.
+#[derive(Clone, Debug)]
+struct Simulation {
+    settings: ParticleSettings,
+    position: (f32, f32),
+    emitting: bool,
+    particles: Vec<Particle>,
+    rng: Rng,
+    /// Fractions of particles left over from previous updates.
+    emission_accumulator: f32,
+}
+
+impl Simulation {
+    fn new(settings: ParticleSettings, seed: u64) -> Simulation {
+        Simulation {
+            settings,
+            position: (0.0, 0.0),
+            emitting: true,
+            particles: Vec::new(),
+            rng: Rng::new(seed),
+            emission_accumulator: 0.0,
+        }
+    }
+
+    fn update(&mut self, seconds: f32) {
+        let (gravity_x, gravity_y) = self.settings.gravity;
+        for particle in &mut self.particles {
+            particle.age += seconds;
+            particle.velocity.0 += gravity_x * seconds;
+            particle.velocity.1 += gravity_y * seconds;
+            particle.position.0 += particle.velocity.0 * seconds;
+            particle.position.1 += particle.velocity.1 * seconds;
+            particle.rotation += particle.angular_velocity * seconds;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+
+        if self.emitting {
+            self.emission_accumulator += self.settings.rate * seconds;
+            let count = self.emission_accumulator.floor().max(0.0);
+            self.emission_accumulator -= count;
+            self.burst(count as usize);
+        }
+    }
+
+    fn burst(&mut self, count: usize) {
+        let count = count.min(
+            self.settings
+                .max_particles
+                .saturating_sub(self.particles.len()),
+        );
+        for _ in 0..count {
+            let particle = self.spawn();
+            self.particles.push(particle);
+        }
+    }
+
+    fn spawn(&mut self) -> Particle {
+        let settings = &self.settings;
+        let rng = &mut self.rng;
+        let (offset_x, offset_y) = match settings.shape {
+            EmissionShape::Point => (0.0, 0.0),
+            EmissionShape::Circle { radius } => {
+                let angle = rng.range(0.0, PI * 2.0);
+                // The square root spreads the points evenly over the area.
+                let distance = radius * rng.next_f32().sqrt();
+                (angle.cos() * distance, angle.sin() * distance)
+            }
+            EmissionShape::Ring { radius } => {
+                let angle = rng.range(0.0, PI * 2.0);
+                (angle.cos() * radius, angle.sin() * radius)
+            }
+            EmissionShape::Rectangle { width, height } => (
+                rng.range(-width / 2.0, width / 2.0),
+                rng.range(-height / 2.0, height / 2.0),
+            ),
+        };
+        let direction = settings.direction + rng.range(-settings.spread, settings.spread) / 2.0;
+        let speed = rng.range(settings.speed.0, settings.speed.1);
+        let angular_velocity = rng.range(settings.angular_velocity.0, settings.angular_velocity.1);
+        let lifetime = rng.range(settings.lifetime.0, settings.lifetime.1);
+        Particle {
+            position: (self.position.0 + offset_x, self.position.1 + offset_y),
+            velocity: (direction.cos() * speed, direction.sin() * speed),
+            rotation: 0.0,
+            angular_velocity,
+            age: 0.0,
+            lifetime,
+        }
+    }
+}
+
+/// A small seedable random number generator (SplitMix64), so the
+/// simulation doesn't depend on anything but the seed.
+#[derive(Clone, Debug)]
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number between 0.0 (inclusive) and 1.0 (exclusive).
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+/// Returns the value of the curve at `t`, or None if the curve has no
+/// keyframes.
+fn sample<T: Copy>(curve: &[(f32, T)], t: f32, lerp: fn(T, T, f32) -> T) -> Option<T> {
+    let (first_t, first) = *curve.first()?;
+    if t <= first_t {
+        return Some(first);
+    }
+    for pair in curve.windows(2) {
+        let ((t0, a), (t1, b)) = (pair[0], pair[1]);
+        if t < t1 {
+            return Some(lerp(a, b, (t - t0) / (t1 - t0)));
+        }
+    }
+    curve.last().map(|(_, value)| *value)
+}
+
+fn lerp(a: f32, b: f32, x: f32) -> f32 {
+    a + (b - a) * x
+}
+
+fn lerp_color(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32), x: f32) -> (f32, f32, f32, f32) {
+    (
+        lerp(a.0, b.0, x),
+        lerp(a.1, b.1, x),
+        lerp(a.2, b.2, x),
+        lerp(a.3, b.3, x),
+    )
+}
+
+#[test]
+fn simulation_is_deterministic() {
+    let settings = ParticleSettings {
+        shape: EmissionShape::Circle { radius: 10.0 },
+        rate: 100.0,
+        lifetime: (0.5, 1.0),
+        gravity: (0.0, 98.0),
+        angular_velocity: (-1.0, 1.0),
+        ..Default::default()
+    };
+    let mut a = Simulation::new(settings.clone(), 1234);
+    let mut b = Simulation::new(settings.clone(), 1234);
+    let mut c = Simulation::new(settings, 4321);
+    for _ in 0..60 {
+        a.update(1.0 / 60.0);
+        b.update(1.0 / 60.0);
+        c.update(1.0 / 60.0);
+    }
+    assert!(!a.particles.is_empty());
+    assert_eq!(a.particles, b.particles);
+    assert_ne!(a.particles, c.particles);
+}
+
+#[test]
+fn particles_move_and_expire() {
+    let settings = ParticleSettings {
+        rate: 0.0,
+        lifetime: (1.0, 1.0),
+        speed: (10.0, 10.0),
+        direction: 0.0,
+        spread: 0.0,
+        gravity: (0.0, 20.0),
+        sizes: vec![(0.0, 2.0), (1.0, 6.0)],
+        ..Default::default()
+    };
+    let mut simulation = Simulation::new(settings, 0);
+    simulation.burst(3);
+    simulation.update(0.5);
+    assert_eq!(simulation.particles.len(), 3);
+    let particle = &simulation.particles[0];
+    assert_eq!(particle.velocity, (10.0, 10.0));
+    assert_eq!(particle.position, (5.0, 5.0));
+    let t = particle.age / particle.lifetime;
+    assert_eq!(sample(&simulation.settings.sizes, t, lerp), Some(4.0));
+
+    simulation.update(0.6);
+    assert!(simulation.particles.is_empty());
+}
@@ -0,0 +1,528 @@
+//! Full-screen effects applied to the frame after all the sprites
+//! have been drawn.
+use crate::api::Spritesheet;
+use crate::gl;
+use crate::gl::types::*;
+use crate::render_target::{FullscreenQuad, RenderTarget};
+use crate::renderer::{self, print_gl_errors, Renderer, ShaderProgram};
+use crate::shaders::{ShaderPair, Shaders};
+
+use std::ffi::CString;
+
+const EFFECT_VERTEX_SHADER: &str = include_str!("shaders/effects/effect.vert");
+const COPY_FRAGMENT_SHADER: &str = include_str!("shaders/effects/copy.frag");
+const VIGNETTE_FRAGMENT_SHADER: &str = include_str!("shaders/effects/vignette.frag");
+const SCANLINES_FRAGMENT_SHADER: &str = include_str!("shaders/effects/scanlines.frag");
+const BLUR_FRAGMENT_SHADER: &str = include_str!("shaders/effects/blur.frag");
+const COLOR_GRADING_FRAGMENT_SHADER: &str = include_str!("shaders/effects/color_grading.frag");
+const BLOOM_THRESHOLD_FRAGMENT_SHADER: &str = include_str!("shaders/effects/bloom_threshold.frag");
+const BLOOM_COMBINE_FRAGMENT_SHADER: &str = include_str!("shaders/effects/bloom_combine.frag");
+
+/// An ordered list of [`Effect`](struct.Effect.html)s which are
+/// applied to the frame before it is displayed.
+///
+/// Set with
+/// [`Context::set_post_process`](struct.Context.html#method.set_post_process).
+/// When post-processing is enabled, the frame is rendered into an
+/// offscreen texture, which is then passed through the effects in
+/// order, and the last effect draws the result on the screen.
+///
+/// # Example
+/// ```no_run
+/// # let mut ctx = fae::Context::new();
+/// use fae::{Effect, PostProcess};
+///
+/// let mut post_process = PostProcess::default();
+/// post_process
+///     .bloom(0.8, 0.5)
+///     .effect(Effect::vignette(0.6))
+///     .effect(Effect::scanlines(0.2));
+/// ctx.set_post_process(Some(post_process));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PostProcess {
+    /// The effects, in the order they are applied.
+    pub effects: Vec<Effect>,
+    /// Whether the effects sample the previous pass with linear
+    /// (true) or nearest neighbor (false) filtering.
+    pub smoothing: bool,
+}
+
+impl Default for PostProcess {
+    fn default() -> PostProcess {
+        PostProcess {
+            effects: Vec::new(),
+            smoothing: true,
+        }
+    }
+}
+
+impl PostProcess {
+    /// Adds an effect to the end of the chain.
+    pub fn effect(&mut self, effect: Effect) -> &mut PostProcess {
+        self.effects.push(effect);
+        self
+    }
+
+    /// Adds a bloom effect to the end of the chain: the colors with
+    /// a component brighter than `threshold` (0.0 - 1.0) are blurred
+    /// and added on top of the frame, multiplied by `intensity`. The
+    /// effects before the bloom are kept, as the bloom is added to
+    /// their output.
+    ///
+    /// This is implemented with four effects: a threshold pass,
+    /// horizontal and vertical blurs, and a combining pass.
+    pub fn bloom(&mut self, threshold: f32, intensity: f32) -> &mut PostProcess {
+        let mut threshold_pass = Effect::new(BLOOM_THRESHOLD_FRAGMENT_SHADER);
+        threshold_pass.uniform("threshold", &[threshold]);
+        // The combining pass adds the bloom to the input of this
+        // pass, so that effects before the bloom are kept.
+        threshold_pass.replaces_scene = true;
+        let mut combine_pass = Effect::new(BLOOM_COMBINE_FRAGMENT_SHADER);
+        combine_pass.uniform("intensity", &[intensity]);
+        self.effect(threshold_pass)
+            .effect(Effect::horizontal_blur(2.0))
+            .effect(Effect::vertical_blur(2.0))
+            .effect(combine_pass)
+    }
+}
+
+/// A fragment shader that is applied to the whole frame.
+///
+/// The fragment shader source is used for every GLSL version, so it
+/// should be written with the macros of the built-in
+/// `fae/compat.glsl` snippet (see [`Shaders`](struct.Shaders.html)
+/// for the details). The following inputs are available:
+///
+/// - `FAE_VARYING vec2 frag_texcoord`: the texture coordinates of
+///   the fragment, from 0.0 to 1.0.
+/// - `uniform sampler2D tex`: the output of the previous effect (or
+///   the frame, for the first effect).
+/// - `uniform sampler2D scene`: the frame, before any effects, or
+///   the input of the latest effect with
+///   [`replaces_scene`](#structfield.replaces_scene) set.
+/// - `uniform vec2 resolution`: the size of the frame in physical
+///   pixels.
+/// - The [`uniforms`](#structfield.uniforms) and
+///   [`textures`](#structfield.textures) of the effect.
+///
+/// The colors sampled from the textures are linear, and the sRGB
+/// conversion happens when the frame is finally drawn to the
+/// screen. In legacy mode, however, the textures contain
+/// gamma-corrected colors, as sRGB textures are not generally
+/// supported.
+///
+/// # Example
+/// ```no_run
+/// # let mut ctx = fae::Context::new();
+/// let mut invert = fae::Effect::new(r#"
+/// #include "fae/compat.glsl"
+/// FAE_VARYING vec2 frag_texcoord;
+/// uniform sampler2D tex;
+/// uniform float amount;
+///
+/// void main(void) {
+///     vec4 color = FAE_TEXTURE(tex, frag_texcoord);
+///     FAE_FRAG_COLOR = vec4(mix(color.rgb, 1.0 - color.rgb, amount), color.a);
+/// }
+/// "#);
+/// invert.uniform("amount", &[1.0]);
+///
+/// let mut post_process = fae::PostProcess::default();
+/// post_process.effect(invert);
+/// ctx.set_post_process(Some(post_process));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Effect {
+    /// The fragment shader code.
+    pub fragment_shader: String,
+    /// Float uniforms (1-4 components, ie. `float` to `vec4`) as
+    /// (name, values) pairs.
+    pub uniforms: Vec<(String, Vec<f32>)>,
+    /// Additional textures as (sampler name, spritesheet) pairs.
+    pub textures: Vec<(String, Spritesheet)>,
+    /// Whether the input of this effect is bound as `scene` for this
+    /// and the following effects, instead of the untouched
+    /// frame. Used by multi-pass effects like
+    /// [`PostProcess::bloom`](struct.PostProcess.html#method.bloom),
+    /// which need to combine their result with their original input.
+    pub replaces_scene: bool,
+}
+
+impl Effect {
+    /// Creates an effect from the fragment shader code.
+    pub fn new(fragment_shader: &str) -> Effect {
+        Effect {
+            fragment_shader: fragment_shader.to_string(),
+            uniforms: Vec::new(),
+            textures: Vec::new(),
+            replaces_scene: false,
+        }
+    }
+
+    /// Sets the value of a float uniform, replacing the previous
+    /// value if it was already set.
+    pub fn uniform(&mut self, name: &str, values: &[f32]) -> &mut Effect {
+        if let Some((_, old_values)) = self.uniforms.iter_mut().find(|(n, _)| n == name) {
+            *old_values = values.to_vec();
+        } else {
+            self.uniforms.push((name.to_string(), values.to_vec()));
+        }
+        self
+    }
+
+    /// Binds the texture of the spritesheet to the sampler uniform
+    /// `name`.
+    pub fn texture(&mut self, name: &str, spritesheet: &Spritesheet) -> &mut Effect {
+        self.textures.push((name.to_string(), spritesheet.clone()));
+        self
+    }
+
+    /// Darkens the edges of the frame. A `strength` of 1.0 makes the
+    /// corners black.
+    pub fn vignette(strength: f32) -> Effect {
+        let mut effect = Effect::new(VIGNETTE_FRAGMENT_SHADER);
+        effect.uniform("strength", &[strength]);
+        effect
+    }
+
+    /// Darkens every other row of physical pixels, by `intensity`
+    /// (0.0 - 1.0).
+    pub fn scanlines(intensity: f32) -> Effect {
+        let mut effect = Effect::new(SCANLINES_FRAGMENT_SHADER);
+        effect.uniform("intensity", &[intensity]);
+        effect
+    }
+
+    /// Blurs the frame horizontally. The `radius` is a multiplier
+    /// for the distance between the samples, in physical pixels, 1.0
+    /// being the smoothest.
+    pub fn horizontal_blur(radius: f32) -> Effect {
+        let mut effect = Effect::new(BLUR_FRAGMENT_SHADER);
+        effect.uniform("direction", &[radius, 0.0]);
+        effect
+    }
+
+    /// Blurs the frame vertically. See
+    /// [`Effect::horizontal_blur`](#method.horizontal_blur).
+    pub fn vertical_blur(radius: f32) -> Effect {
+        let mut effect = Effect::new(BLUR_FRAGMENT_SHADER);
+        effect.uniform("direction", &[0.0, radius]);
+        effect
+    }
+
+    /// Maps the colors of the frame through a color lookup table.
+    ///
+    /// The `lut` is a strip of `lut_size` squares of `lut_size` by
+    /// `lut_size` pixels, laid out horizontally (eg. 256x16 pixels
+    /// for a 16-wide LUT). Red increases to the right within each
+    /// square, green downwards, and blue from square to square. The
+    /// spritesheet should use linear filtering.
+    pub fn color_grading(lut: &Spritesheet, lut_size: i32) -> Effect {
+        let mut effect = Effect::new(COLOR_GRADING_FRAGMENT_SHADER);
+        effect.uniform("lut_size", &[lut_size as f32]);
+        effect.texture("lut", lut);
+        effect
+    }
+}
+
+/// The OpenGL side of a PostProcess.
+pub(crate) struct PostProcessor {
+    effects: Vec<Effect>,
+    programs: Vec<ShaderProgram>,
+    copy_program: ShaderProgram,
+    quad: FullscreenQuad,
+    scene: RenderTarget,
+    /// The intermediate targets between effects. Three are needed
+    /// so that a target can hold the `scene` of a
+    /// [`replaces_scene`](struct.Effect.html#structfield.replaces_scene)
+    /// effect while the other two are used for the following passes.
+    targets: [RenderTarget; 3],
+    legacy: bool,
+}
+
+impl PostProcessor {
+    /// Compiles the effects and creates the render targets. Returns
+    /// None if offscreen rendering is not supported.
+    pub fn new(renderer: &Renderer, post_process: PostProcess) -> Option<PostProcessor> {
+        let legacy = renderer.legacy;
+        let smoothing = post_process.smoothing;
+        let scene = RenderTarget::new(legacy, 1, 1, smoothing)?;
+        let targets = [
+            RenderTarget::new(legacy, 1, 1, smoothing)?,
+            RenderTarget::new(legacy, 1, 1, smoothing)?,
+            RenderTarget::new(legacy, 1, 1, smoothing)?,
+        ];
+        let programs = post_process
+            .effects
+            .iter()
+            .map(|effect| create_effect_program(renderer, &effect.fragment_shader))
+            .collect();
+        Some(PostProcessor {
+            effects: post_process.effects,
+            programs,
+            copy_program: create_effect_program(renderer, COPY_FRAGMENT_SHADER),
+            quad: FullscreenQuad::new(legacy),
+            scene,
+            targets,
+            legacy,
+        })
+    }
+
+    pub fn set_uniform(&mut self, effect_index: usize, name: &str, values: &[f32]) {
+        if let Some(effect) = self.effects.get_mut(effect_index) {
+            effect.uniform(name, values);
+        }
+    }
+
+    /// Renders the frame into the scene render target, applies the
+    /// effects, and draws the result into the currently set viewport
+    /// of the default framebuffer.
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        width: f32,
+        height: f32,
+        clear_color: Option<(f32, f32, f32, f32)>,
+    ) {
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+
+        let dpi_factor = renderer.dpi_factor;
+        let physical_width = (width * dpi_factor).round() as i32;
+        let physical_height = (height * dpi_factor).round() as i32;
+        self.scene.resize(physical_width, physical_height);
+        self.scene.bind();
+        renderer.render_into(self.scene.framebuffer(), width, height, clear_color);
+
+        for target in &mut self.targets {
+            target.resize(physical_width, physical_height);
+        }
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::CULL_FACE);
+            if !self.legacy {
+                // The render targets are sRGB textures like the
+                // screen, so this works the same way for both.
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            }
+        }
+
+        let effect_count = self.effects.len();
+        let replaces_scene: Vec<bool> = self.effects.iter().map(|e| e.replaces_scene).collect();
+        let passes = plan_passes(&replaces_scene);
+        for (i, pass) in passes.iter().enumerate() {
+            match pass.output {
+                Some(target) => self.targets[target].bind(),
+                None => unsafe {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                    gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+                },
+            }
+
+            let (program, effect) = if effect_count == 0 {
+                (&self.copy_program, None)
+            } else {
+                (&self.programs[i], Some(&self.effects[i]))
+            };
+            let input = self.get_texture(pass.input);
+            let scene = self.get_texture(pass.scene);
+            let size = (physical_width as f32, physical_height as f32);
+            self.draw_effect(renderer, program, effect, (input, scene), size);
+        }
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Enable(gl::CULL_FACE);
+        }
+        print_gl_errors("after post-processing");
+    }
+
+    fn get_texture(&self, source: PassSource) -> GLuint {
+        match source {
+            PassSource::Scene => self.scene.texture(),
+            PassSource::Target(index) => self.targets[index].texture(),
+        }
+    }
+
+    /// Draws the effect with `textures` (input, scene) bound to the
+    /// `tex` and `scene` samplers.
+    fn draw_effect(
+        &self,
+        renderer: &Renderer,
+        program: &ShaderProgram,
+        effect: Option<&Effect>,
+        textures: (GLuint, GLuint),
+        resolution: (f32, f32),
+    ) {
+        let (input, scene) = textures;
+        let get_uniform_location = |name: &str| {
+            let name = CString::new(name).ok()?;
+            match unsafe { gl::GetUniformLocation(program.program, name.as_ptr()) } {
+                -1 => None,
+                location => Some(location),
+            }
+        };
+
+        unsafe {
+            gl::UseProgram(program.program);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, input);
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, scene);
+        }
+        if let Some(location) = get_uniform_location("tex") {
+            unsafe { gl::Uniform1i(location, 0) };
+        }
+        if let Some(location) = get_uniform_location("scene") {
+            unsafe { gl::Uniform1i(location, 1) };
+        }
+        if let Some(location) = get_uniform_location("resolution") {
+            unsafe { gl::Uniform2f(location, resolution.0, resolution.1) };
+        }
+
+        if let Some(effect) = effect {
+            for (name, values) in &effect.uniforms {
+                if let Some(location) = get_uniform_location(name) {
+                    set_float_uniform(location, values);
+                }
+            }
+            for (i, (name, spritesheet)) in effect.textures.iter().enumerate() {
+                let unit = 2 + i as GLuint;
+                unsafe {
+                    gl::ActiveTexture(gl::TEXTURE0 + unit);
+                    gl::BindTexture(gl::TEXTURE_2D, renderer.get_texture(&spritesheet.handle));
+                }
+                if let Some(location) = get_uniform_location(name) {
+                    unsafe { gl::Uniform1i(location, unit as GLint) };
+                }
+            }
+        }
+        print_gl_errors("after setting up an effect");
+
+        self.quad.draw(program);
+    }
+}
+
+impl Drop for PostProcessor {
+    fn drop(&mut self) {
+        if !gl::Viewport::is_loaded() {
+            return;
+        }
+        for program in &self.programs {
+            renderer::delete_program(program);
+        }
+        renderer::delete_program(&self.copy_program);
+    }
+}
+
+/// Where a pass reads a texture from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PassSource {
+    /// The frame, as rendered before any effects.
+    Scene,
+    /// One of the intermediate targets.
+    Target(usize),
+}
+
+/// The textures used by one effect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Pass {
+    input: PassSource,
+    scene: PassSource,
+    /// The target drawn into, None for the screen.
+    output: Option<usize>,
+}
+
+/// Decides which targets the effects read from and draw into, based
+/// on which of them replace the scene. There is always at least one
+/// pass, which just copies the frame if there are no effects.
+fn plan_passes(replaces_scene: &[bool]) -> Vec<Pass> {
+    let pass_count = replaces_scene.len().max(1);
+    let mut passes = Vec::with_capacity(pass_count);
+    let (mut input, mut scene) = (PassSource::Scene, PassSource::Scene);
+    for i in 0..pass_count {
+        if replaces_scene.get(i) == Some(&true) {
+            scene = input;
+        }
+        let output = if i + 1 >= pass_count {
+            None
+        } else {
+            // Of the three targets, at most two are in use as the
+            // input and the scene, so one is always free.
+            let in_use =
+                |target| input == PassSource::Target(target) || scene == PassSource::Target(target);
+            (0..3).find(|&target| !in_use(target))
+        };
+        passes.push(Pass {
+            input,
+            scene,
+            output,
+        });
+        if let Some(target) = output {
+            input = PassSource::Target(target);
+        }
+    }
+    passes
+}
+
+#[test]
+fn effects_before_bloom_are_kept() {
+    // Vignette, then bloom (threshold, blurs, combine), then scanlines.
+    let replaces_scene = [false, true, false, false, false, false];
+    let passes = plan_passes(&replaces_scene);
+    assert_eq!(passes.len(), 6);
+    for pass in &passes {
+        if let Some(output) = pass.output {
+            assert_ne!(pass.input, PassSource::Target(output));
+            assert_ne!(pass.scene, PassSource::Target(output));
+        }
+    }
+    let vignetted = PassSource::Target(passes[0].output.unwrap());
+    assert_eq!(passes[0].scene, PassSource::Scene);
+    assert_eq!(passes[1].input, vignetted);
+    // The combining pass adds the bloom to the vignetted frame.
+    assert_eq!(passes[4].scene, vignetted);
+    assert_eq!(passes[5].output, None);
+
+    let copy = plan_passes(&[]);
+    assert_eq!(copy.len(), 1);
+    assert_eq!(copy[0].input, PassSource::Scene);
+    assert_eq!(copy[0].output, None);
+}
+
+fn create_effect_program(renderer: &Renderer, fragment_shader: &str) -> ShaderProgram {
+    let pair = ShaderPair {
+        vertex_shader: EFFECT_VERTEX_SHADER.to_string(),
+        fragment_shader: fragment_shader.to_string(),
+    };
+    let shaders = Shaders {
+        shader_330: pair.clone(),
+        shader_110: pair.clone(),
+        shader_300_es: pair.clone(),
+        shader_100_es: pair,
+        defines: Vec::new(),
+        snippets: Vec::new(),
+    };
+    let (api, legacy, instanced) = (renderer.api(), renderer.legacy, renderer.instancing);
+    let vert = shaders.create_vert_string(api, legacy, instanced);
+    let frag = shaders.create_frag_string(api, legacy, instanced);
+    renderer::create_program(&vert, &frag, &[])
+}
+
+fn set_float_uniform(location: GLint, values: &[f32]) {
+    unsafe {
+        match values {
+            [x] => gl::Uniform1f(location, *x),
+            [x, y] => gl::Uniform2f(location, *x, *y),
+            [x, y, z] => gl::Uniform3f(location, *x, *y, *z),
+            [x, y, z, w] => gl::Uniform4f(location, *x, *y, *z, *w),
+            _ => log::warn!("Effect uniforms should have 1-4 components."),
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Offscreen rendering, used for post-processing.
+use crate::gl;
+use crate::gl::types::*;
+use crate::renderer::{print_gl_errors, ShaderProgram};
+
+use std::mem;
+use std::ptr;
+
+/// A framebuffer with a color texture and a depth buffer, which the
+/// frame can be rendered into instead of the screen.
+#[derive(Debug)]
+pub(crate) struct RenderTarget {
+    framebuffer: GLuint,
+    texture: GLuint,
+    depth_buffer: GLuint,
+    legacy: bool,
+    pub size: (i32, i32),
+}
+
+impl RenderTarget {
+    /// Creates a new render target, or None if framebuffers aren't
+    /// supported by the context.
+    ///
+    /// In legacy mode, the color texture holds gamma-encoded colors,
+    /// as sRGB textures aren't generally available. Otherwise, the
+    /// texture is an sRGB texture, so the colors are linear when
+    /// sampled, and it works exactly like the default framebuffer
+    /// with `GL_FRAMEBUFFER_SRGB`.
+    pub fn new(legacy: bool, width: i32, height: i32, smoothed: bool) -> Option<RenderTarget> {
+        if !gl::GenFramebuffers::is_loaded() || !gl::GenRenderbuffers::is_loaded() {
+            log::warn!("Framebuffers are not supported, can't render offscreen.");
+            return None;
+        }
+
+        let (mut framebuffer, mut texture, mut depth_buffer) = (0, 0, 0);
+        let filter = if smoothed { gl::LINEAR } else { gl::NEAREST } as GLint;
+        unsafe {
+            gl::GenFramebuffers(1, &mut framebuffer);
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter);
+            let clamp = gl::CLAMP_TO_EDGE as GLint;
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, clamp);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, clamp);
+            gl::GenRenderbuffers(1, &mut depth_buffer);
+        }
+        let mut target = RenderTarget {
+            framebuffer,
+            texture,
+            depth_buffer,
+            legacy,
+            size: (0, 0),
+        };
+        target.resize(width, height);
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture,
+                0,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::RENDERBUFFER,
+                depth_buffer,
+            );
+        }
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        print_gl_errors("after creating a render target");
+
+        if status == gl::FRAMEBUFFER_COMPLETE {
+            Some(target)
+        } else {
+            log::warn!("Render target framebuffer is incomplete: {:#06x}", status);
+            None
+        }
+    }
+
+    /// Reallocates the texture and depth buffer if the size changed.
+    /// The contents are garbage after a resize.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if self.size == (width, height) {
+            return;
+        }
+        self.size = (width, height);
+        let format = if self.legacy {
+            gl::RGBA
+        } else {
+            gl::SRGB8_ALPHA8
+        };
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                format as GLint,
+                width,
+                height,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                ptr::null(),
+            );
+            gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth_buffer);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT16, width, height);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+        print_gl_errors("after resizing a render target");
+    }
+
+    /// Binds the framebuffer and sets the viewport to cover it.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::Viewport(0, 0, self.size.0, self.size.1);
+        }
+    }
+
+    pub fn framebuffer(&self) -> GLuint {
+        self.framebuffer
+    }
+
+    pub fn texture(&self) -> GLuint {
+        self.texture
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        if !gl::Viewport::is_loaded() {
+            return;
+        }
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteRenderbuffers(1, &self.depth_buffer);
+        }
+    }
+}
+
+/// A quad that covers the whole viewport, for drawing render targets
+/// onto other render targets or the screen. The shaders should have
+/// a `vec2 position` attribute, which will range from -1 to 1.
+#[derive(Debug)]
+pub(crate) struct FullscreenQuad {
+    vbo: GLuint,
+    vao: GLuint,
+    legacy: bool,
+}
+
+impl FullscreenQuad {
+    pub fn new(legacy: bool) -> FullscreenQuad {
+        let (mut vbo, mut vao) = (0, 0);
+        // Two triangles as a triangle strip, clockwise, as culling is
+        // set up for clockwise quads.
+        let vertices: [f32; 8] = [-1.0, -1.0, -1.0, 1.0, 1.0, -1.0, 1.0, 1.0];
+        let len = (mem::size_of::<f32>() * vertices.len()) as isize;
+        unsafe {
+            if !legacy {
+                gl::GenVertexArrays(1, &mut vao);
+                gl::BindVertexArray(vao);
+            }
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                len,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+        }
+        print_gl_errors("after creating a fullscreen quad");
+        FullscreenQuad { vbo, vao, legacy }
+    }
+
+    /// Draws the quad with the program, which should already be in
+    /// use.
+    pub fn draw(&self, program: &ShaderProgram) {
+        let location = match program.position_attrib_location {
+            Some(location) => location,
+            None => return,
+        };
+        unsafe {
+            if !self.legacy {
+                gl::BindVertexArray(self.vao);
+            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::VertexAttribPointer(location, 2, gl::FLOAT, gl::FALSE, 0, ptr::null());
+            gl::EnableVertexAttribArray(location);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            gl::DisableVertexAttribArray(location);
+        }
+        print_gl_errors("after drawing a fullscreen quad");
+    }
+}
+
+impl Drop for FullscreenQuad {
+    fn drop(&mut self) {
+        if !gl::Viewport::is_loaded() {
+            return;
+        }
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            if !self.legacy {
+                gl::DeleteVertexArrays(1, &self.vao);
+            }
+        }
+    }
+}
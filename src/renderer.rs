@@ -1,16 +1,33 @@
-use crate::api::AlphaBlending;
+use crate::api::{AlphaBlending, BlendMode};
+use crate::capabilities::Capabilities;
 use crate::gl;
 use crate::gl::types::*;
-use crate::gl_version::{self, OpenGlApi, OpenGlVersion};
+use crate::gl_debug;
+use crate::gl_version::{OpenGlApi, OpenGlVersion};
+#[cfg(feature = "hot-reload")]
+use crate::hot_reload::{self, HotReload};
+use crate::image::compressed;
 use crate::image::Image;
 use crate::sprite::Sprite;
 use crate::types::RectPx;
 
+use std::ffi::CString;
 use std::mem;
 use std::ptr;
+use std::sync::{Arc, Weak};
 
 pub use crate::shaders::Shaders;
 
+// From OpenGL 2.1 and ES 2.0, not in the core profile bindings. Used
+// instead of RED and RG in legacy contexts.
+pub(crate) const LUMINANCE: GLuint = 0x1909;
+pub(crate) const LUMINANCE_ALPHA: GLuint = 0x190A;
+
+// The quad index buffer has 16-bit indices, as 32-bit ones aren't
+// available in OpenGL ES 2.0 without extensions, so this many quads
+// (4 vertices each) can be drawn with one glDrawElements call.
+const MAX_INDEXED_QUADS: usize = 0x10000 / 4;
+
 #[derive(Clone, Debug)]
 #[repr(transparent)]
 struct TextureHandle(GLuint);
@@ -26,21 +43,33 @@ pub(crate) struct DrawCallHandle {
     index: usize,
 }
 
+/// Refers to a static buffer of a draw call, created with
+/// [`Renderer::create_static_buffer`](struct.Renderer.html#method.create_static_buffer).
+/// The buffer is freed after all the clones of its handle have been
+/// dropped.
+#[derive(Clone, Debug)]
+pub(crate) struct StaticBufferHandle {
+    call: usize,
+    index: usize,
+    _alive: Arc<()>,
+}
+
 #[derive(Clone, Debug)]
-struct ShaderProgram {
-    program: GLuint,
+pub(crate) struct ShaderProgram {
+    pub(crate) program: GLuint,
     vertex_shader: GLuint,
     fragment_shader: GLuint,
 
     projection_matrix_location: Option<GLint>,
     gamma_correction_location: Option<GLint>,
-    position_attrib_location: Option<GLuint>,
+    pub(crate) position_attrib_location: Option<GLuint>,
     texcoord_attrib_location: Option<GLuint>,
     color_attrib_location: Option<GLuint>,
     rotation_attrib_location: Option<GLuint>,
     depth_attrib_location: Option<GLuint>,
     shared_position_attrib_location: Option<GLuint>,
     shared_texcoord_attrib_location: Option<GLuint>,
+    custom_attrib_locations: Vec<AttribArray>,
 }
 
 #[derive(Clone, Debug)]
@@ -51,6 +80,22 @@ struct Attributes {
     vao: VaoHandle,
     vbo_data: Vec<f32>,
     allocated_vbo_data_size: isize,
+    /// The amount of floats each sprite has in addition to the
+    /// built-in attributes.
+    custom_components: usize,
+}
+
+/// Describes how the quads of a draw call are laid out in its vertex
+/// buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum QuadLayout {
+    /// One set of attributes per quad, drawn with instanced draw
+    /// calls over a shared quad. Always used outside of legacy mode.
+    Instanced,
+    /// Four vertices per quad, drawn as indexed triangles. Used in
+    /// legacy mode when instancing isn't supported, or when the
+    /// shaders don't use the instanced attributes.
+    Indexed,
 }
 
 #[derive(Clone, Debug)]
@@ -60,17 +105,78 @@ struct TextureParams {
     format: GLuint,
     pixel_format: GLuint,
     pixel_type: GLuint,
+    /// False for textures created outside of fae, which are not
+    /// deleted or reallocated by the renderer.
+    owned: bool,
 }
 
 #[derive(Clone, Debug)]
 struct DrawCall {
+    /// The name of the draw call's spritesheet or font, used to label
+    /// its objects and debug group.
+    name: Option<String>,
     texture: TextureParams,
     program: ShaderProgram,
     attributes: Attributes,
+    layout: QuadLayout,
     blend: bool,
+    blend_mode: BlendMode,
     sort: bool,
     srgb: bool,
     highest_depth: f32,
+    static_buffers: Vec<StaticBuffer>,
+    /// The static buffers drawn this frame, in order, and the
+    /// translations they are drawn with.
+    queued_static_buffers: Vec<(usize, (f32, f32))>,
+    /// Used for texture uploads, if the draw call was created with
+    /// pixel buffer uploads enabled, and they're supported.
+    pixel_buffers: Option<PixelBufferRing>,
+    /// Textures bound to texture units 1 and up while drawing, with
+    /// the names of the sampler uniforms they're bound to.
+    extra_textures: Vec<(CString, TextureParams)>,
+    /// A copy of the texture's pixels, kept in legacy mode for
+    /// textures that opted in with `keep_texture_shadow`, so that
+    /// they can be resized without copying through a framebuffer.
+    texture_shadow: Option<Vec<u8>>,
+    #[cfg(feature = "hot-reload")]
+    hot_reload: Option<HotReload>,
+}
+
+/// A buffer of quads that are uploaded once and then drawn as many
+/// times as needed, as opposed to the per-frame `vbo_data` of draw
+/// calls. Shares the static quad and element buffers of its draw
+/// call.
+#[derive(Clone, Debug)]
+struct StaticBuffer {
+    attributes: Attributes,
+    float_count: usize,
+    highest_depth: f32,
+    /// Dead when the buffer's handles have all been dropped, after
+    /// which the buffer is freed and its slot can be reused.
+    owner: Weak<()>,
+}
+
+impl StaticBuffer {
+    fn is_dead(&self) -> bool {
+        self.owner.upgrade().is_none()
+    }
+
+    /// Deletes the buffer's objects if all of its handles have been
+    /// dropped. Freed buffers have no objects, and are skipped.
+    fn free_if_dead(&mut self) {
+        if self.attributes.vbo.0 == 0 || !self.is_dead() {
+            return;
+        }
+        unsafe {
+            gl::DeleteBuffers(1, [self.attributes.vbo.0].as_ptr());
+            if self.attributes.vao.0 != 0 {
+                gl::DeleteVertexArrays(1, [self.attributes.vao.0].as_ptr());
+            }
+        }
+        self.attributes.vbo = VboHandle(0);
+        self.attributes.vao = VaoHandle(0);
+        self.float_count = 0;
+    }
 }
 
 /// Describes how textures are wrapped.
@@ -90,15 +196,33 @@ pub enum TextureWrapping {
 pub(crate) struct Renderer {
     calls: Vec<DrawCall>,
     pub(crate) legacy: bool,
-    pub(crate) version: OpenGlVersion,
+    /// Whether sprites can be drawn with instanced draw calls. Always
+    /// true outside of legacy mode.
+    pub(crate) instancing: bool,
+    /// Whether vertex array objects can be used. Always true outside
+    /// of legacy mode.
+    vertex_arrays: bool,
+    /// The element buffer shared by draw calls with the
+    /// `QuadLayout::Indexed` layout. Created when the first one is.
+    quad_indices: VboHandle,
+    /// Whether objects can be labeled and draw calls grouped with
+    /// `KHR_debug`.
+    debug_labels: bool,
+    pub(crate) capabilities: Capabilities,
     pub(crate) dpi_factor: f32,
+    /// The size of the frame being drawn, in logical pixels. Used to
+    /// resolve anchored sprites.
+    pub(crate) frame_size: (f32, f32),
+    /// Holds the custom attribute values of the Sprite currently
+    /// being built, to avoid allocating a Vec for each sprite.
+    pub(crate) custom_data: Vec<f32>,
 }
 
 impl Renderer {
     // TODO(0.6.0): Add a new renderer constructor that fails on legacy contexts.
     pub(crate) fn new() -> Renderer {
-        let version = gl_version::get_version();
-        let legacy = match &version {
+        let capabilities = Capabilities::query();
+        let legacy = match &capabilities.version {
             OpenGlVersion::Available { api, major, minor } => {
                 let legacy = match api {
                     OpenGlApi::Desktop => *major < 3 || (*major == 3 && *minor < 3),
@@ -122,38 +246,75 @@ impl Renderer {
             }
         };
 
+        let debug_labels = gl_debug::install(&capabilities);
+        let instancing = !legacy
+            || (capabilities.instanced_arrays
+                && gl::VertexAttribDivisor::is_loaded()
+                && gl::DrawElementsInstanced::is_loaded());
+        let vertex_arrays = !legacy
+            || (capabilities.vertex_array_objects
+                && gl::GenVertexArrays::is_loaded()
+                && gl::BindVertexArray::is_loaded()
+                && gl::DeleteVertexArrays::is_loaded());
+        if legacy {
+            log::info!(
+                "Legacy extensions: instancing {}, vertex array objects {}",
+                if instancing { "on" } else { "off" },
+                if vertex_arrays { "on" } else { "off" },
+            );
+        }
+
         Renderer {
             calls: Vec::new(),
             legacy,
-            version,
+            instancing,
+            vertex_arrays,
+            quad_indices: VboHandle(0),
+            debug_labels,
+            capabilities,
             dpi_factor: 1.0,
+            frame_size: (0.0, 0.0),
+            custom_data: Vec::new(),
         }
     }
 
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn create_draw_call(
         &mut self,
+        name: Option<&str>,
         image: Option<&Image>,
         shaders: &Shaders,
         alpha_blending: AlphaBlending,
+        blend_mode: BlendMode,
         minification_smoothing: bool,
         magnification_smoothing: bool,
         wrap: (TextureWrapping, TextureWrapping),
         srgb: bool,
+        custom_attributes: &[(String, i32)],
+        pixel_buffer_uploads: bool,
     ) -> DrawCallHandle {
-        let (api, legacy) = (
-            match self.version {
-                OpenGlVersion::Available { api, .. } => api,
-                _ => OpenGlApi::Desktop,
-            },
-            self.legacy,
-        );
-        let vert = shaders.create_vert_string(api, legacy);
-        let frag = shaders.create_frag_string(api, legacy);
+        let (api, legacy, instancing) = (self.api(), self.legacy, self.instancing);
+        let vert = shaders.create_vert_string(api, legacy, instancing);
+        let frag = shaders.create_frag_string(api, legacy, instancing);
         let index = self.calls.len();
 
-        let program = create_program(&vert, &frag);
-        let attributes = create_attributes(legacy, &program);
+        let program = create_program(&vert, &frag, custom_attributes);
+        // Legacy shaders written for per-vertex attributes don't have
+        // the shared quad attributes, so they're drawn without
+        // instancing even if it's supported.
+        let layout = if !legacy || (instancing && program.shared_position_attrib_location.is_some())
+        {
+            QuadLayout::Instanced
+        } else {
+            QuadLayout::Indexed
+        };
+        if layout == QuadLayout::Indexed && self.quad_indices.0 == 0 {
+            self.quad_indices = create_quad_indices();
+            if self.debug_labels {
+                gl_debug::label(gl::BUFFER, self.quad_indices.0, "fae quad indices");
+            }
+        }
+        let attributes = create_attributes(layout, self.vertex_arrays, &program);
         let filter = |smoothed| if smoothed { gl::LINEAR } else { gl::NEAREST } as i32;
         let get_wrap = |wrap_type| match wrap_type {
             TextureWrapping::Clamp => gl::CLAMP_TO_EDGE,
@@ -172,12 +333,9 @@ impl Renderer {
                 handle,
                 size: (image.width, image.height),
                 format: image.format,
-                pixel_format: match image.format {
-                    gl::SRGB => gl::RGB,
-                    gl::SRGB_ALPHA => gl::RGBA,
-                    format => format,
-                },
+                pixel_format: get_pixel_format(image.format),
                 pixel_type: image.pixel_type,
+                owned: true,
             };
             let pixels: Option<&[u8]> = if image.null_data {
                 None
@@ -185,6 +343,10 @@ impl Renderer {
                 Some(&image.pixels)
             };
             insert_texture(&texture, image.width, image.height, pixels);
+            // OpenGL ES 2.0 doesn't have GL_TEXTURE_MAX_LEVEL.
+            let max_level = !(legacy && api == OpenGlApi::ES);
+            let mipmaps = &image.mipmaps;
+            insert_mipmaps(&texture, mipmaps, minification_smoothing, max_level);
             texture
         } else {
             TextureParams {
@@ -193,26 +355,251 @@ impl Renderer {
                 format: gl::RED,
                 pixel_format: gl::RED,
                 pixel_type: gl::UNSIGNED_BYTE,
+                owned: true,
             }
         };
 
-        self.calls.push(DrawCall {
+        let call = DrawCall {
+            name: name.map(str::to_string),
             texture,
             program,
             attributes,
+            layout,
             blend: alpha_blending.blend,
+            blend_mode,
             sort: alpha_blending.sort,
             srgb,
             highest_depth: -1.0,
-        });
+            static_buffers: Vec::new(),
+            queued_static_buffers: Vec::new(),
+            pixel_buffers: if pixel_buffer_uploads && self.capabilities.pixel_buffer_objects {
+                PixelBufferRing::new()
+            } else {
+                None
+            },
+            extra_textures: Vec::new(),
+            texture_shadow: None,
+            #[cfg(feature = "hot-reload")]
+            hot_reload: None,
+        };
+        if self.debug_labels {
+            label_draw_call(&call);
+        }
+        self.calls.push(call);
 
         DrawCallHandle { index }
     }
 
+    /// Returns true if textures can be created with the
+    /// block-compressed `format`.
+    pub(crate) fn supports_compressed_format(&self, format: GLuint) -> bool {
+        self.capabilities.supports_compressed_format(format)
+    }
+
+    pub(crate) fn api(&self) -> OpenGlApi {
+        match self.capabilities.version {
+            OpenGlVersion::Available { api, .. } => api,
+            _ => OpenGlApi::Desktop,
+        }
+    }
+
+    /// Starts polling the files the draw call was created from for
+    /// changes. See
+    /// [`Renderer::reload_changed_files`](#method.reload_changed_files).
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn watch_files(
+        &mut self,
+        call: &DrawCallHandle,
+        shaders: Shaders,
+        custom_attributes: Vec<(String, i32)>,
+        shader_files: Option<hot_reload::ShaderFiles>,
+        image_file: Option<std::path::PathBuf>,
+    ) {
+        let hot_reload = HotReload::new(
+            self.api(),
+            self.legacy,
+            shaders,
+            custom_attributes,
+            shader_files,
+            image_file,
+        );
+        self.calls[call.index].hot_reload = Some(hot_reload);
+    }
+
+    /// Recompiles the shaders and re-uploads the textures of draw
+    /// calls whose files have changed. If the new shaders fail to
+    /// compile, the errors are logged and the previous program is
+    /// kept in use.
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn reload_changed_files(&mut self) {
+        let (api, legacy, instancing) = (self.api(), self.legacy, self.instancing);
+        let debug_labels = self.debug_labels;
+        for (i, call) in self.calls.iter_mut().enumerate() {
+            let hot_reload = match &mut call.hot_reload {
+                Some(hot_reload) => hot_reload,
+                None => continue,
+            };
+
+            let compile = |vert: &str, frag: &str, custom_attributes: &[(String, i32)]| {
+                let (program, errors) = compile_program(vert, frag, custom_attributes);
+                if errors.is_empty() {
+                    Ok(program)
+                } else {
+                    delete_program(&program);
+                    Err(errors)
+                }
+            };
+            if let Some(program) = hot_reload.reload_shaders(api, legacy, instancing, compile) {
+                log::info!("Reloaded the shaders of draw call #{}.", i);
+                delete_program(&call.program);
+                if call.attributes.vao.0 != 0 {
+                    disable_vertex_array(&call.attributes, &call.program);
+                    setup_vertex_array(&call.attributes, &program);
+                    let buffers = call.static_buffers.iter();
+                    for buffer in buffers.filter(|b| b.attributes.vao.0 != 0) {
+                        disable_vertex_array(&buffer.attributes, &call.program);
+                        setup_vertex_array(&buffer.attributes, &program);
+                    }
+                }
+                call.program = program;
+                if let (true, Some(name)) = (debug_labels, &call.name) {
+                    let label = format!("{} program", name);
+                    gl_debug::label(gl::PROGRAM, call.program.program, &label);
+                }
+            }
+
+            if let Some(path) = hot_reload.changed_image() {
+                if let Some(image) = hot_reload::load_image(path) {
+                    call.texture.size = (image.width, image.height);
+                    call.texture.format = image.format;
+                    call.texture.pixel_format = get_pixel_format(image.format);
+                    call.texture.pixel_type = image.pixel_type;
+                    insert_texture(
+                        &call.texture,
+                        image.width,
+                        image.height,
+                        Some(&image.pixels),
+                    );
+                    if call.texture_shadow.is_some() {
+                        call.texture_shadow = Some(image.pixels);
+                    }
+                    log::info!("Reloaded image '{}'.", path.display());
+                }
+            }
+        }
+    }
+
     pub(crate) fn draw<'a, 'b>(&'a mut self, call: &'b DrawCallHandle) -> Sprite<'a, 'b> {
         Sprite::new(self, call)
     }
 
+    /// Creates an empty static buffer for the draw call. Static
+    /// buffers live until their handles are dropped, after which they
+    /// are freed at the start of the next frame, or when the next
+    /// static buffer is created.
+    pub(crate) fn create_static_buffer(&mut self, call: &DrawCallHandle) -> StaticBufferHandle {
+        let draw_call = &mut self.calls[call.index];
+        for buffer in &mut draw_call.static_buffers {
+            buffer.free_if_dead();
+        }
+        let uses_vao = draw_call.attributes.vao.0 != 0;
+        let mut vao = 0;
+        let mut vbo = 0;
+        unsafe {
+            if uses_vao {
+                gl::GenVertexArrays(1, &mut vao);
+            }
+            gl::GenBuffers(1, &mut vbo);
+        }
+        let attributes = Attributes {
+            vao: VaoHandle(vao),
+            vbo: VboHandle(vbo),
+            vbo_static: draw_call.attributes.vbo_static.clone(),
+            element_buffer: draw_call.attributes.element_buffer.clone(),
+            vbo_data: Vec::new(),
+            allocated_vbo_data_size: 0,
+            custom_components: draw_call.attributes.custom_components,
+        };
+        if uses_vao {
+            setup_vertex_array(&attributes, &draw_call.program);
+        }
+        let index = draw_call
+            .static_buffers
+            .iter()
+            .position(|buffer| buffer.is_dead())
+            .unwrap_or(draw_call.static_buffers.len());
+        if let (true, Some(name)) = (self.debug_labels, &draw_call.name) {
+            let label = format!("{} static buffer #{}", name, index);
+            gl_debug::label(gl::BUFFER, attributes.vbo.0, &label);
+            gl_debug::label(gl::VERTEX_ARRAY, attributes.vao.0, &label);
+        }
+        print_gl_errors("after static buffer creation");
+
+        let alive = Arc::new(());
+        let buffer = StaticBuffer {
+            attributes,
+            float_count: 0,
+            highest_depth: -1.0,
+            owner: Arc::downgrade(&alive),
+        };
+        if index < draw_call.static_buffers.len() {
+            draw_call.static_buffers[index] = buffer;
+        } else {
+            draw_call.static_buffers.push(buffer);
+        }
+        StaticBufferHandle {
+            call: call.index,
+            index,
+            _alive: alive,
+        }
+    }
+
+    /// Replaces the contents of the static buffer with the quads
+    /// drawn (with [`draw_quad`](#method.draw_quad) or
+    /// [`draw`](#method.draw)) into the buffer's draw call in `f`.
+    pub(crate) fn build_static_buffer<F: FnOnce(&mut Renderer)>(
+        &mut self,
+        buffer: &StaticBufferHandle,
+        f: F,
+    ) {
+        let call = &mut self.calls[buffer.call];
+        let frame_data = mem::take(&mut call.attributes.vbo_data);
+        let frame_depth = mem::replace(&mut call.highest_depth, -1.0);
+
+        f(self);
+
+        let call = &mut self.calls[buffer.call];
+        let data = mem::replace(&mut call.attributes.vbo_data, frame_data);
+        let depth = mem::replace(&mut call.highest_depth, frame_depth);
+        let static_buffer = &mut call.static_buffers[buffer.index];
+        static_buffer.float_count = data.len();
+        static_buffer.highest_depth = depth;
+        let len = (mem::size_of::<f32>() * data.len()) as isize;
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, static_buffer.attributes.vbo.0);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                len,
+                data.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+        }
+        print_gl_errors("after uploading a static buffer");
+    }
+
+    /// Queues the static buffer to be drawn this frame, translated by
+    /// `offset` (in logical pixels). The static buffers of a draw call
+    /// are drawn in the order they were queued, after the
+    /// per-frame quads.
+    pub(crate) fn draw_static_buffer(&mut self, buffer: &StaticBufferHandle, offset: (f32, f32)) {
+        let call = &mut self.calls[buffer.call];
+        let static_buffer = &call.static_buffers[buffer.index];
+        if static_buffer.float_count > 0 {
+            call.highest_depth = call.highest_depth.max(static_buffer.highest_depth);
+            call.queued_static_buffers.push((buffer.index, offset));
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn draw_quad_clipped(
         &mut self,
@@ -222,6 +609,7 @@ impl Renderer {
         color: (f32, f32, f32, f32),
         rotation: (f32, f32, f32),
         z: f32,
+        custom: &[f32],
         call: &DrawCallHandle,
     ) {
         let (cx0, cy0, cx1, cy1) = clip_area; // Clip coords
@@ -246,10 +634,31 @@ impl Renderer {
             ty1.min(ty1 + th * (y1 - oy1) / oh),
         );
 
-        self.draw_quad((x0, y0, x1, y1), texcoords, color, rotation, z, call);
+        self.draw_quad(
+            (x0, y0, x1, y1),
+            texcoords,
+            color,
+            rotation,
+            z,
+            custom,
+            call,
+        );
+    }
+
+    /// Reserves space for `count` more quads in the draw call's
+    /// buffer, to avoid reallocating when drawing many quads at once.
+    pub(crate) fn reserve_quads(&mut self, call: &DrawCallHandle, count: usize) {
+        let call = &mut self.calls[call.index];
+        let attributes = &mut call.attributes;
+        let floats_per_quad = match call.layout {
+            QuadLayout::Indexed => (12 + attributes.custom_components) * 4,
+            QuadLayout::Instanced => 16 + attributes.custom_components,
+        };
+        attributes.vbo_data.reserve(floats_per_quad * count);
     }
 
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn draw_quad(
         &mut self,
         coords: (f32, f32, f32, f32),
@@ -257,6 +666,7 @@ impl Renderer {
         color: (f32, f32, f32, f32),
         rotation: (f32, f32, f32),
         depth: f32,
+        custom: &[f32],
         call: &DrawCallHandle,
     ) {
         let (x0, y0, x1, y1) = coords;
@@ -265,7 +675,7 @@ impl Renderer {
         let (rads, pivot_x, pivot_y) = rotation;
 
         self.calls[call.index].highest_depth = self.calls[call.index].highest_depth.max(depth);
-        if self.legacy {
+        if self.calls[call.index].layout == QuadLayout::Indexed {
             let (pivot_x, pivot_y) = (pivot_x + x0, pivot_y + y0);
 
             let quad = [
@@ -275,28 +685,36 @@ impl Renderer {
                 pivot_y, // Top-right vertex
                 x1, y1, depth, tx1, ty1, red, green, blue, alpha, rads, pivot_x,
                 pivot_y, // Bottom-right vertex
-                x0, y0, depth, tx0, ty0, red, green, blue, alpha, rads, pivot_x,
-                pivot_y, // Top-left vertex
-                x1, y1, depth, tx1, ty1, red, green, blue, alpha, rads, pivot_x,
-                pivot_y, // Bottom-right vertex
                 x0, y1, depth, tx0, ty1, red, green, blue, alpha, rads, pivot_x,
                 pivot_y, // Bottom-left vertex
             ];
 
-            self.calls[call.index]
-                .attributes
-                .vbo_data
-                .extend_from_slice(&quad);
+            let attributes = &mut self.calls[call.index].attributes;
+            if attributes.custom_components == 0 {
+                attributes.vbo_data.extend_from_slice(&quad);
+            } else {
+                for vertex in quad.chunks(12) {
+                    attributes.vbo_data.extend_from_slice(vertex);
+                    extend_with_custom_data(
+                        &mut attributes.vbo_data,
+                        custom,
+                        attributes.custom_components,
+                    );
+                }
+            }
         } else {
             let (width, height, tw, th) = (x1 - x0, y1 - y0, tx1 - tx0, ty1 - ty0);
             let quad = [
                 x0, y0, width, height, tx0, ty0, tw, th, red, green, blue, alpha, rads, pivot_x,
                 pivot_y, depth,
             ];
-            self.calls[call.index]
-                .attributes
-                .vbo_data
-                .extend_from_slice(&quad);
+            let attributes = &mut self.calls[call.index].attributes;
+            attributes.vbo_data.extend_from_slice(&quad);
+            extend_with_custom_data(
+                &mut attributes.vbo_data,
+                custom,
+                attributes.custom_components,
+            );
         }
     }
 
@@ -306,6 +724,17 @@ impl Renderer {
         width: f32,
         height: f32,
         clear_color: Option<(f32, f32, f32, f32)>,
+    ) {
+        self.render_into(0, width, height, clear_color);
+    }
+
+    /// Renders the queued draws into the framebuffer.
+    pub(crate) fn render_into(
+        &mut self,
+        framebuffer: GLuint,
+        width: f32,
+        height: f32,
+        clear_color: Option<(f32, f32, f32, f32)>,
     ) {
         let m00 = 2.0 / width;
         let m11 = -2.0 / height;
@@ -314,7 +743,7 @@ impl Renderer {
         ];
 
         unsafe {
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
         }
 
         if let Some((r, g, b, a)) = clear_color {
@@ -325,6 +754,9 @@ impl Renderer {
         }
 
         let legacy = self.legacy;
+        let debug_labels = self.debug_labels;
+        let srgb_framebuffer = self.capabilities.srgb_framebuffer;
+        let quad_indices = &self.quad_indices;
 
         unsafe {
             gl::Enable(gl::DEPTH_TEST);
@@ -348,14 +780,26 @@ impl Renderer {
         for i in call_indices {
             let call = &mut self.calls[i];
 
-            if call.attributes.vbo_data.is_empty() {
+            if call.attributes.vbo_data.is_empty() && call.queued_static_buffers.is_empty() {
                 continue;
             }
 
+            if debug_labels {
+                match &call.name {
+                    Some(name) => gl_debug::push_group(name),
+                    None => gl_debug::push_group(&format!("draw call #{}", i)),
+                }
+            }
+
             unsafe {
                 if call.blend {
                     gl::Enable(gl::BLEND);
                     gl::DepthFunc(gl::LEQUAL);
+                    match call.blend_mode {
+                        BlendMode::Alpha => gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+                        BlendMode::Additive => gl::BlendFunc(gl::SRC_ALPHA, gl::ONE),
+                        BlendMode::Multiply => gl::BlendFunc(gl::DST_COLOR, gl::ZERO),
+                    }
                 } else {
                     gl::Disable(gl::BLEND);
                     gl::DepthFunc(gl::LESS);
@@ -363,10 +807,12 @@ impl Renderer {
                 gl::UseProgram(call.program.program);
 
                 if !legacy {
-                    if call.srgb {
-                        gl::Enable(gl::FRAMEBUFFER_SRGB);
-                    } else {
-                        gl::Disable(gl::FRAMEBUFFER_SRGB);
+                    if srgb_framebuffer {
+                        if call.srgb {
+                            gl::Enable(gl::FRAMEBUFFER_SRGB);
+                        } else {
+                            gl::Disable(gl::FRAMEBUFFER_SRGB);
+                        }
                     }
                 } else if let Some(gamma_correction_location) =
                     call.program.gamma_correction_location
@@ -380,58 +826,72 @@ impl Renderer {
                 if let Some(location) = call.program.projection_matrix_location {
                     gl::UniformMatrix4fv(location, 1, gl::FALSE, matrix.as_ptr());
                 }
-
-                if !legacy {
-                    gl::BindVertexArray(call.attributes.vao.0);
-                    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, call.attributes.element_buffer.0);
-                }
                 gl::BindTexture(gl::TEXTURE_2D, call.texture.handle.0);
-                gl::BindBuffer(gl::ARRAY_BUFFER, call.attributes.vbo.0);
+                if !call.extra_textures.is_empty() {
+                    for (j, (sampler, texture)) in call.extra_textures.iter().enumerate() {
+                        let unit = j as GLuint + 1;
+                        gl::ActiveTexture(gl::TEXTURE0 + unit);
+                        gl::BindTexture(gl::TEXTURE_2D, texture.handle.0);
+                        let location =
+                            gl::GetUniformLocation(call.program.program, sampler.as_ptr());
+                        gl::Uniform1i(location, unit as GLint);
+                    }
+                    gl::ActiveTexture(gl::TEXTURE0);
+                }
             }
             print_gl_errors(&format!("after initializing draw call #{}", i));
 
-            if legacy {
-                // 12 floats (3 for pos + 2 tex + 4 col + 3 rot) per vertex
-                let vertex_count = call.attributes.vbo_data.len() as i32 / 12;
-                enable_vertex_attribs(&[
-                    (call.program.position_attrib_location, 3),
-                    (call.program.texcoord_attrib_location, 2),
-                    (call.program.color_attrib_location, 4),
-                    (call.program.rotation_attrib_location, 3),
-                ]);
-                unsafe {
-                    gl::DrawArrays(gl::TRIANGLES, 0, vertex_count);
-                }
-                disable_vertex_attribs(&[
-                    call.program.position_attrib_location,
-                    call.program.texcoord_attrib_location,
-                    call.program.color_attrib_location,
-                    call.program.rotation_attrib_location,
-                ]);
-                crate::profiler::write(|p| p.quads_drawn += vertex_count as u32 / 6);
-                print_gl_errors(&format!("[legacy] after drawing buffer #{}", i));
-            } else {
-                // 16 floats (4 for x,y,w,h + 4 tex xywh + 4 col + 3 rot + 1 z) per vertex
-                let count = call.attributes.vbo_data.len() as i32 / 16;
-                let mode = gl::TRIANGLES;
-                let val_type = gl::UNSIGNED_BYTE;
-                unsafe {
-                    gl::DrawElementsInstanced(mode, 6, val_type, ptr::null(), count);
+            if !call.attributes.vbo_data.is_empty() {
+                let float_count = call.attributes.vbo_data.len();
+                let (program, attributes) = (&call.program, &call.attributes);
+                draw_buffer(
+                    call.layout,
+                    program,
+                    attributes,
+                    quad_indices,
+                    float_count,
+                    i,
+                );
+            }
+
+            for (index, (x, y)) in call.queued_static_buffers.drain(..) {
+                if let Some(location) = call.program.projection_matrix_location {
+                    let mut matrix = matrix;
+                    matrix[3] += m00 * x;
+                    matrix[7] += m11 * y;
+                    unsafe {
+                        gl::UniformMatrix4fv(location, 1, gl::FALSE, matrix.as_ptr());
+                    }
                 }
-                crate::profiler::write(|p| p.quads_drawn += count as u32);
-                print_gl_errors(&format!("after drawing buffer #{}", i));
+                let buffer = &call.static_buffers[index];
+                draw_buffer(
+                    call.layout,
+                    &call.program,
+                    &buffer.attributes,
+                    quad_indices,
+                    buffer.float_count,
+                    i,
+                );
             }
 
+            if debug_labels {
+                gl_debug::pop_group();
+            }
             print_gl_errors(&*format!("after render #{}", i));
         }
     }
 
     /// Prepares the renderer for drawing.
-    pub(crate) fn prepare_new_frame(&mut self, dpi_factor: f32) {
+    pub(crate) fn prepare_new_frame(&mut self, dpi_factor: f32, width: f32, height: f32) {
         self.dpi_factor = dpi_factor;
+        self.frame_size = (width, height);
         for call in &mut self.calls {
             call.attributes.vbo_data.clear();
             call.highest_depth = -1.0;
+            call.queued_static_buffers.clear();
+            for buffer in &mut call.static_buffers {
+                buffer.free_if_dead();
+            }
         }
     }
 
@@ -514,18 +974,120 @@ impl Renderer {
         self.calls[call.index].texture.handle.0
     }
 
+    /// Replaces the draw call's texture with one created outside of
+    /// fae, which will not be deleted by the renderer. If the
+    /// previous texture was created by the renderer, it is deleted.
+    pub(crate) fn set_external_texture(
+        &mut self,
+        call: &DrawCallHandle,
+        texture: GLuint,
+        size: (i32, i32),
+        format: GLuint,
+    ) {
+        let params = &mut self.calls[call.index].texture;
+        if params.owned && params.handle.0 != 0 && params.handle.0 != texture {
+            unsafe {
+                gl::DeleteTextures(1, [params.handle.0].as_ptr());
+            }
+        }
+        *params = TextureParams {
+            handle: TextureHandle(texture),
+            size,
+            format,
+            pixel_format: get_pixel_format(format),
+            pixel_type: gl::UNSIGNED_BYTE,
+            owned: false,
+        };
+        self.calls[call.index].texture_shadow = None;
+    }
+
+    /// Creates a texture which is bound to the sampler uniform
+    /// `sampler` when drawing with the draw call, in addition to the
+    /// draw call's main texture. Returns the index to pass to
+    /// [`Renderer::upload_texture`](#method.upload_texture).
+    pub(crate) fn add_extra_texture(
+        &mut self,
+        call: &DrawCallHandle,
+        sampler: &str,
+        image: &Image,
+        smoothing: bool,
+    ) -> usize {
+        let filter = if smoothing { gl::LINEAR } else { gl::NEAREST } as GLint;
+        let clamp = gl::CLAMP_TO_EDGE as GLint;
+        let texture = TextureParams {
+            handle: create_texture(filter, filter, clamp, clamp),
+            size: (image.width, image.height),
+            format: image.format,
+            pixel_format: get_pixel_format(image.format),
+            pixel_type: image.pixel_type,
+            owned: true,
+        };
+        let pixels: Option<&[u8]> = if image.null_data {
+            None
+        } else {
+            Some(&image.pixels)
+        };
+        insert_texture(&texture, image.width, image.height, pixels);
+        if let (true, Some(name)) = (self.debug_labels, &self.calls[call.index].name) {
+            let label = format!("{} {} texture", name, sampler);
+            gl_debug::label(gl::TEXTURE, texture.handle.0, &label);
+        }
+        let sampler = CString::new(sampler).unwrap_or_default();
+        let extra_textures = &mut self.calls[call.index].extra_textures;
+        extra_textures.push((sampler, texture));
+        extra_textures.len()
+    }
+
+    /// Replaces all the pixels of a texture of the draw call: the
+    /// main texture if `index` is 0, otherwise the extra texture
+    /// returned by
+    /// [`Renderer::add_extra_texture`](#method.add_extra_texture).
+    /// The length of `pixels` is not checked.
+    pub(crate) fn upload_texture(&mut self, call: &DrawCallHandle, index: usize, pixels: &[u8]) {
+        let call = &mut self.calls[call.index];
+        let texture = match index {
+            0 => &call.texture,
+            i => &call.extra_textures[i - 1].1,
+        };
+        let (width, height) = texture.size;
+        insert_sub_texture(
+            &texture.handle,
+            0,
+            0,
+            width,
+            height,
+            texture.pixel_format,
+            texture.pixel_type,
+            pixels,
+            call.pixel_buffers.as_mut(),
+        );
+        if let (0, Some(shadow)) = (index, &mut call.texture_shadow) {
+            if shadow.len() == pixels.len() {
+                shadow.copy_from_slice(pixels);
+            }
+        }
+    }
+
+    pub(crate) fn get_texture_format(&self, call: &DrawCallHandle) -> GLuint {
+        self.calls[call.index].texture.format
+    }
+
     pub(crate) fn get_texture_size(&self, call: &DrawCallHandle) -> (i32, i32) {
         self.calls[call.index].texture.size
     }
 
     pub(crate) fn upload_texture_region(
-        &self,
+        &mut self,
         call: &DrawCallHandle,
         region: RectPx,
         image: &Image,
     ) -> bool {
-        let (tex_width, tex_height) = self.calls[call.index].texture.size;
-        if region.width == image.width
+        let call = &mut self.calls[call.index];
+        let (tex_width, tex_height) = call.texture.size;
+        let compressed =
+            image.is_compressed() || compressed::block_size(call.texture.format).is_some();
+        if !compressed
+            && region.width == image.width
             && region.height == image.height
             && region.x + region.width <= tex_width
             && region.y + region.height <= tex_height
@@ -533,20 +1095,43 @@ impl Renderer {
             && region.y >= 0
         {
             insert_sub_texture(
-                &self.calls[call.index].texture.handle,
+                &call.texture.handle,
                 region.x,
                 region.y,
                 region.width,
                 region.height,
-                image.format,
+                get_pixel_format(image.format),
+                image.pixel_type,
                 &image.pixels,
+                call.pixel_buffers.as_mut(),
             );
+            if let Some(shadow) = &mut call.texture_shadow {
+                write_shadow_region(shadow, tex_width, region, &image.pixels);
+            }
             true
         } else {
             false
         }
     }
 
+    /// Starts keeping a copy of the draw call's texture on the CPU in
+    /// legacy mode, so that it can be resized without losing its
+    /// contents. Should be called right after creating the draw call
+    /// with a null texture, as the copy starts out empty.
+    #[cfg(feature = "text")]
+    pub(crate) fn keep_texture_shadow(&mut self, call: &DrawCallHandle) {
+        let call = &mut self.calls[call.index];
+        let texture = &call.texture;
+        if !self.legacy || !texture.owned || call.texture_shadow.is_some() {
+            return;
+        }
+        if let Some(pixel_size) = get_pixel_size(texture.pixel_format, texture.pixel_type) {
+            let (width, height) = texture.size;
+            let size = width.max(0) as usize * height.max(0) as usize;
+            call.texture_shadow = Some(vec![0; size * pixel_size]);
+        }
+    }
+
     pub(crate) fn resize_texture(
         &mut self,
         call: &DrawCallHandle,
@@ -554,22 +1139,35 @@ impl Renderer {
         new_height: i32,
         preserve_contents: bool,
     ) -> bool {
-        let (old_width, old_height) = self.calls[call.index].texture.size;
-        if self.legacy {
+        let legacy = self.legacy;
+        let call = &mut self.calls[call.index];
+        let texture = &call.texture;
+        let (old_width, old_height) = texture.size;
+        let unchanged = new_width == old_width && new_height == old_height;
+        if !texture.owned || compressed::block_size(texture.format).is_some() || unchanged {
             false
-        } else if new_width != old_width || new_height != old_height {
+        } else if legacy {
+            // Framebuffer objects aren't a part of OpenGL 2.1, and
+            // ES 2.0 can't render into luminance textures, so the
+            // contents are re-uploaded from the shadow copy instead.
+            if preserve_contents && call.texture_shadow.is_none() {
+                return false;
+            }
+            let shadow = call.texture_shadow.as_mut();
+            resize_texture_legacy(texture, shadow, new_width, new_height);
+            call.texture.size = (new_width, new_height);
+            true
+        } else {
             resize_texture(
-                &self.calls[call.index].texture,
+                texture,
                 old_width,
                 old_height,
                 new_width,
                 new_height,
                 preserve_contents,
             );
-            self.calls[call.index].texture.size = (new_width, new_height);
+            call.texture.size = (new_width, new_height);
             true
-        } else {
-            false
         }
     }
 }
@@ -581,14 +1179,8 @@ impl Drop for Renderer {
             // gl resources (because they can't have been allocated)
             return;
         }
-        let legacy = self.legacy;
         for call in &self.calls {
-            let ShaderProgram {
-                program,
-                vertex_shader,
-                fragment_shader,
-                ..
-            } = call.program;
+            delete_program(&call.program);
             let Attributes {
                 vbo,
                 vbo_static,
@@ -597,23 +1189,112 @@ impl Drop for Renderer {
                 ..
             } = &call.attributes;
             unsafe {
-                gl::DeleteShader(vertex_shader);
-                gl::DeleteShader(fragment_shader);
-                gl::DeleteProgram(program);
-                gl::DeleteTextures(1, [call.texture.handle.0].as_ptr());
+                if call.texture.owned {
+                    gl::DeleteTextures(1, [call.texture.handle.0].as_ptr());
+                }
                 gl::DeleteBuffers(1, [vbo.0].as_ptr());
-                if !legacy {
+                if call.layout == QuadLayout::Instanced {
                     gl::DeleteBuffers(2, [vbo_static.0, element_buffer.0].as_ptr());
+                }
+                if vao.0 != 0 {
                     gl::DeleteVertexArrays(1, [vao.0].as_ptr());
                 }
+                if let Some(pixel_buffers) = &call.pixel_buffers {
+                    pixel_buffers.delete();
+                }
+                for (_, texture) in &call.extra_textures {
+                    gl::DeleteTextures(1, [texture.handle.0].as_ptr());
+                }
+                for buffer in &call.static_buffers {
+                    gl::DeleteBuffers(1, [buffer.attributes.vbo.0].as_ptr());
+                    if buffer.attributes.vao.0 != 0 {
+                        gl::DeleteVertexArrays(1, [buffer.attributes.vao.0].as_ptr());
+                    }
+                }
+            }
+        }
+        if self.quad_indices.0 != 0 {
+            unsafe {
+                gl::DeleteBuffers(1, [self.quad_indices.0].as_ptr());
             }
         }
     }
 }
 
+/// Labels the objects of the draw call after its name, if it has
+/// one. Textures created outside of fae are left as they are.
+fn label_draw_call(call: &DrawCall) {
+    let name = match &call.name {
+        Some(name) => name,
+        None => return,
+    };
+    if call.texture.owned {
+        gl_debug::label(
+            gl::TEXTURE,
+            call.texture.handle.0,
+            &format!("{} texture", name),
+        );
+    }
+    gl_debug::label(
+        gl::PROGRAM,
+        call.program.program,
+        &format!("{} program", name),
+    );
+    let attributes = &call.attributes;
+    let vertices = format!("{} vertex buffer", name);
+    gl_debug::label(gl::BUFFER, attributes.vbo.0, &vertices);
+    let quad = format!("{} quad buffer", name);
+    gl_debug::label(gl::BUFFER, attributes.vbo_static.0, &quad);
+    let elements = format!("{} element buffer", name);
+    gl_debug::label(gl::BUFFER, attributes.element_buffer.0, &elements);
+    let vertex_array = format!("{} vertex array", name);
+    gl_debug::label(gl::VERTEX_ARRAY, attributes.vao.0, &vertex_array);
+}
+
+/// Returns the format of the pixel data that should be uploaded to a
+/// texture with the internal format `format`.
+fn get_pixel_format(format: GLuint) -> GLuint {
+    match format {
+        gl::SRGB => gl::RGB,
+        gl::SRGB_ALPHA => gl::RGBA,
+        format => format,
+    }
+}
+
 #[inline]
-fn create_program(vert_source: &str, frag_source: &str) -> ShaderProgram {
-    let print_shader_error = |shader, shader_type| {
+pub(crate) fn create_program(
+    vert_source: &str,
+    frag_source: &str,
+    custom_attributes: &[(String, i32)],
+) -> ShaderProgram {
+    let (program, errors) = compile_program(vert_source, frag_source, custom_attributes);
+    for error in &errors {
+        log::error!("{}", error);
+    }
+    if cfg!(debug_assertions) && !errors.is_empty() {
+        panic!("shader program creation failed");
+    }
+    program
+}
+
+pub(crate) fn delete_program(program: &ShaderProgram) {
+    unsafe {
+        gl::DeleteShader(program.vertex_shader);
+        gl::DeleteShader(program.fragment_shader);
+        gl::DeleteProgram(program.program);
+    }
+}
+
+/// Compiles and links the shaders into a program. Returns the program
+/// and the compilation and linking errors, if there were any. The
+/// program is probably not usable if there were errors.
+fn compile_program(
+    vert_source: &str,
+    frag_source: &str,
+    custom_attributes: &[(String, i32)],
+) -> (ShaderProgram, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut check_shader_error = |shader, shader_type| {
         let mut compilation_status = 0;
         unsafe {
             gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut compilation_status);
@@ -623,14 +1304,11 @@ fn create_program(vert_source: &str, frag_source: &str) -> ShaderProgram {
             unsafe {
                 gl::GetShaderInfoLog(shader, 1024, ptr::null_mut(), info.as_mut_ptr());
             }
-            log::error!(
+            errors.push(format!(
                 "Shader ({}) compilation failed:\n{}",
                 shader_type,
                 error_buffer_into_string(info).trim()
-            );
-            if cfg!(debug_assertions) {
-                panic!("shader compilation failed");
-            }
+            ));
         }
     };
 
@@ -646,7 +1324,7 @@ fn create_program(vert_source: &str, frag_source: &str) -> ShaderProgram {
         );
         gl::CompileShader(vertex_shader);
     }
-    print_shader_error(vertex_shader, "vertex");
+    check_shader_error(vertex_shader, "vertex");
 
     let fragment_shader = unsafe { gl::CreateShader(gl::FRAGMENT_SHADER) };
     unsafe {
@@ -658,7 +1336,7 @@ fn create_program(vert_source: &str, frag_source: &str) -> ShaderProgram {
         );
         gl::CompileShader(fragment_shader);
     }
-    print_shader_error(fragment_shader, "fragment");
+    check_shader_error(fragment_shader, "fragment");
 
     unsafe {
         gl::AttachShader(program, vertex_shader);
@@ -674,13 +1352,10 @@ fn create_program(vert_source: &str, frag_source: &str) -> ShaderProgram {
         unsafe {
             gl::GetProgramInfoLog(program, 1024, ptr::null_mut(), info.as_mut_ptr());
         }
-        log::error!(
+        errors.push(format!(
             "Program linking failed:\n{}",
             error_buffer_into_string(info).trim()
-        );
-        if cfg!(debug_assertions) {
-            panic!("program linking failed");
-        }
+        ));
     }
     print_gl_errors("after shader program creation");
 
@@ -702,7 +1377,18 @@ fn create_program(vert_source: &str, frag_source: &str) -> ShaderProgram {
         }
     };
 
-    ShaderProgram {
+    let custom_attrib_locations = custom_attributes
+        .iter()
+        .map(|(name, components)| {
+            let location = get_attrib_location(&format!("{}\0", name));
+            if location.is_none() {
+                log::warn!("Custom attribute '{}' is not used by the shaders.", name);
+            }
+            (location, *components)
+        })
+        .collect();
+
+    let program = ShaderProgram {
         program,
         vertex_shader,
         fragment_shader,
@@ -715,30 +1401,29 @@ fn create_program(vert_source: &str, frag_source: &str) -> ShaderProgram {
         depth_attrib_location: get_attrib_location("depth\0"),
         shared_position_attrib_location: get_attrib_location("shared_position\0"),
         shared_texcoord_attrib_location: get_attrib_location("shared_texcoord\0"),
-    }
+        custom_attrib_locations,
+    };
+    (program, errors)
 }
 
 #[inline]
-fn create_attributes(legacy: bool, program: &ShaderProgram) -> Attributes {
+fn create_attributes(
+    layout: QuadLayout,
+    vertex_arrays: bool,
+    program: &ShaderProgram,
+) -> Attributes {
     let mut vao = 0;
-    if !legacy {
-        unsafe {
-            gl::GenVertexArrays(1, &mut vao);
-            gl::BindVertexArray(vao);
-        }
-    }
-
     let mut vbo_static = 0;
     let mut element_buffer = 0;
-    if !legacy {
+    if layout == QuadLayout::Instanced {
         unsafe {
+            if vertex_arrays {
+                gl::GenVertexArrays(1, &mut vao);
+                gl::BindVertexArray(vao);
+            }
             gl::GenBuffers(1, &mut vbo_static);
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo_static);
         }
-        enable_vertex_attribs(&[
-            (program.shared_position_attrib_location, 2),
-            (program.shared_texcoord_attrib_location, 2),
-        ]);
         // The vertices of two triangles that form a quad, interleaved
         // in a (pos x, pos y, tex x, tex y) arrangement:
         let static_quad_vertices: [f32; 16] = [
@@ -755,7 +1440,7 @@ fn create_attributes(legacy: bool, program: &ShaderProgram) -> Attributes {
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, element_buffer);
         }
         let elements: [u8; 6] = [0, 1, 2, 0, 2, 3];
-        let len = (mem::size_of::<f32>() * elements.len()) as isize;
+        let len = (mem::size_of::<u8>() * elements.len()) as isize;
         let ptr = elements.as_ptr() as *const _;
         unsafe {
             gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, len, ptr, gl::STATIC_DRAW);
@@ -765,49 +1450,233 @@ fn create_attributes(legacy: bool, program: &ShaderProgram) -> Attributes {
     let mut vbo = 0;
     unsafe {
         gl::GenBuffers(1, &mut vbo);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-    }
-
-    if !legacy {
-        enable_vertex_attribs(&[
-            (program.position_attrib_location, 4),
-            (program.texcoord_attrib_location, 4),
-            (program.color_attrib_location, 4),
-            (program.rotation_attrib_location, 3),
-            (program.depth_attrib_location, 1),
-        ]);
-
-        let setup_vertex_attrib_divisor = |location: Option<GLuint>| {
-            if let Some(location) = location {
-                unsafe {
-                    gl::VertexAttribDivisor(location, 1);
-                }
-            }
-        };
-        setup_vertex_attrib_divisor(program.position_attrib_location);
-        setup_vertex_attrib_divisor(program.texcoord_attrib_location);
-        setup_vertex_attrib_divisor(program.color_attrib_location);
-        setup_vertex_attrib_divisor(program.rotation_attrib_location);
-        setup_vertex_attrib_divisor(program.depth_attrib_location);
     }
-    print_gl_errors("after attribute creation");
 
-    Attributes {
+    let attributes = Attributes {
         vao: VaoHandle(vao),
         vbo: VboHandle(vbo),
         vbo_static: VboHandle(vbo_static),
         element_buffer: VboHandle(element_buffer),
         vbo_data: Vec::new(),
         allocated_vbo_data_size: 0,
+        custom_components: program
+            .custom_attrib_locations
+            .iter()
+            .map(|(_, components)| *components as usize)
+            .sum(),
+    };
+    if vao != 0 {
+        setup_vertex_array(&attributes, program);
+    }
+    print_gl_errors("after attribute creation");
+    attributes
+}
+
+/// Creates the element buffer for drawing `QuadLayout::Indexed`
+/// quads: two triangles per four vertices, for `MAX_INDEXED_QUADS`
+/// quads.
+fn create_quad_indices() -> VboHandle {
+    let elements: Vec<u16> = (0..MAX_INDEXED_QUADS as u16)
+        .flat_map(|quad| {
+            let i = quad * 4;
+            // Top-left, top-right, bottom-right, bottom-left.
+            vec![i, i + 1, i + 2, i, i + 2, i + 3]
+        })
+        .collect();
+    let mut element_buffer = 0;
+    let len = (mem::size_of::<u16>() * elements.len()) as isize;
+    let ptr = elements.as_ptr() as *const _;
+    unsafe {
+        gl::GenBuffers(1, &mut element_buffer);
+        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, element_buffer);
+        gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, len, ptr, gl::STATIC_DRAW);
     }
+    print_gl_errors("after quad index buffer creation");
+    VboHandle(element_buffer)
+}
+
+/// Sets up the attribute pointers of `attributes` to point to the
+/// instanced attributes of `program`. If `attributes` has a VAO, the
+/// setup is stored in it, and it's unbound afterwards. Otherwise, the
+/// attributes are set up for the next draw, and should be cleaned up
+/// with [`disable_vertex_array`](fn.disable_vertex_array.html).
+fn setup_vertex_array(attributes: &Attributes, program: &ShaderProgram) {
+    let vao = attributes.vao.0;
+    unsafe {
+        if vao != 0 {
+            gl::BindVertexArray(vao);
+        }
+        gl::BindBuffer(gl::ARRAY_BUFFER, attributes.vbo_static.0);
+    }
+    enable_vertex_attribs(
+        &[
+            (program.shared_position_attrib_location, 2),
+            (program.shared_texcoord_attrib_location, 2),
+        ],
+        0,
+    );
+
+    unsafe {
+        gl::BindBuffer(gl::ARRAY_BUFFER, attributes.vbo.0);
+    }
+    let mut attribs = vec![
+        (program.position_attrib_location, 4),
+        (program.texcoord_attrib_location, 4),
+        (program.color_attrib_location, 4),
+        (program.rotation_attrib_location, 3),
+        (program.depth_attrib_location, 1),
+    ];
+    attribs.extend_from_slice(&program.custom_attrib_locations);
+    enable_vertex_attribs(&attribs, 0);
+
+    for (location, _) in &attribs {
+        if let Some(location) = location {
+            unsafe {
+                gl::VertexAttribDivisor(*location, 1);
+            }
+        }
+    }
+
+    if vao != 0 {
+        // Legacy draws don't use VAOs, so they'd modify this one if
+        // it was left bound.
+        unsafe {
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+/// Disables the attributes of `program` in the VAO of `attributes`,
+/// or the current attribute state if it has none, and resets their
+/// divisors. Used to clean up before setting the VAO up with a new
+/// program, and after instanced draws without a VAO.
+fn disable_vertex_array(attributes: &Attributes, program: &ShaderProgram) {
+    let vao = attributes.vao.0;
+    if vao != 0 {
+        unsafe {
+            gl::BindVertexArray(vao);
+        }
+    }
+    let mut locations = vec![
+        program.shared_position_attrib_location,
+        program.shared_texcoord_attrib_location,
+        program.position_attrib_location,
+        program.texcoord_attrib_location,
+        program.color_attrib_location,
+        program.rotation_attrib_location,
+        program.depth_attrib_location,
+    ];
+    locations.extend(program.custom_attrib_locations.iter().map(|a| a.0));
+    for location in locations.iter().flatten() {
+        unsafe {
+            gl::VertexAttribDivisor(*location, 0);
+        }
+    }
+    disable_vertex_attribs(&locations);
+    if vao != 0 {
+        unsafe {
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+/// Draws the first `float_count` floats of quads in the buffers of
+/// `attributes` with `program`, which should already be in use.
+fn draw_buffer(
+    layout: QuadLayout,
+    program: &ShaderProgram,
+    attributes: &Attributes,
+    quad_indices: &VboHandle,
+    float_count: usize,
+    call_index: usize,
+) {
+    match layout {
+        QuadLayout::Indexed => {
+            unsafe {
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, quad_indices.0);
+                gl::BindBuffer(gl::ARRAY_BUFFER, attributes.vbo.0);
+            }
+            // 12 floats (3 for pos + 2 tex + 4 col + 3 rot) per
+            // vertex, and then the custom attributes, for 4 vertices
+            let floats_per_quad = (12 + attributes.custom_components) * 4;
+            let quad_count = float_count / floats_per_quad;
+            let mut attribs = vec![
+                (program.position_attrib_location, 3),
+                (program.texcoord_attrib_location, 2),
+                (program.color_attrib_location, 4),
+                (program.rotation_attrib_location, 3),
+            ];
+            attribs.extend_from_slice(&program.custom_attrib_locations);
+            // The index buffer only covers MAX_INDEXED_QUADS quads, so
+            // bigger buffers are drawn in batches, with the attribute
+            // pointers offset to the start of each batch.
+            let mut drawn = 0;
+            while drawn < quad_count {
+                let batch = (quad_count - drawn).min(MAX_INDEXED_QUADS);
+                let offset = drawn * floats_per_quad * mem::size_of::<f32>();
+                enable_vertex_attribs(&attribs, offset);
+                unsafe {
+                    let count = (batch * 6) as i32;
+                    gl::DrawElements(gl::TRIANGLES, count, gl::UNSIGNED_SHORT, ptr::null());
+                }
+                drawn += batch;
+            }
+            let locations: Vec<Option<GLuint>> = attribs.iter().map(|a| a.0).collect();
+            disable_vertex_attribs(&locations);
+            crate::profiler::write(|p| p.quads_drawn += quad_count as u32);
+            print_gl_errors(&format!("[legacy] after drawing buffer #{}", call_index));
+        }
+        QuadLayout::Instanced => {
+            let vao = attributes.vao.0;
+            unsafe {
+                if vao != 0 {
+                    gl::BindVertexArray(vao);
+                    gl::BindBuffer(gl::ARRAY_BUFFER, attributes.vbo.0);
+                }
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, attributes.element_buffer.0);
+            }
+            if vao == 0 {
+                setup_vertex_array(attributes, program);
+            }
+            // 16 floats (4 for x,y,w,h + 4 tex xywh + 4 col + 3 rot
+            // + 1 z) per instance, and then the custom attributes
+            let floats_per_instance = 16 + attributes.custom_components;
+            let count = (float_count / floats_per_instance) as i32;
+            let mode = gl::TRIANGLES;
+            let val_type = gl::UNSIGNED_BYTE;
+            unsafe {
+                gl::DrawElementsInstanced(mode, 6, val_type, ptr::null(), count);
+            }
+            if vao == 0 {
+                disable_vertex_array(attributes, program);
+            } else {
+                unsafe {
+                    gl::BindVertexArray(0);
+                }
+            }
+            crate::profiler::write(|p| p.quads_drawn += count as u32);
+            print_gl_errors(&format!("after drawing buffer #{}", call_index));
+        }
+    }
+}
+
+/// Pushes `count` floats from `custom` into `data`, padding with
+/// zeroes if `custom` is too short.
+#[inline]
+fn extend_with_custom_data(data: &mut Vec<f32>, custom: &[f32], count: usize) {
+    let provided = custom.len().min(count);
+    data.extend_from_slice(&custom[..provided]);
+    data.extend((provided..count).map(|_| 0.0));
 }
 
 // (location, component_count)
 type AttribArray = (Option<GLuint>, GLint);
-fn enable_vertex_attribs(attribs: &[AttribArray]) {
+/// Points the attributes at the currently bound array buffer, with
+/// the first attribute starting `base_offset` bytes into it.
+fn enable_vertex_attribs(attribs: &[AttribArray], base_offset: usize) {
     let total_components = attribs.iter().map(|attrib| attrib.1 * 4).sum();
 
-    let mut offset = 0;
+    let mut offset = base_offset;
     for attrib in attribs {
         // Only enable the attributes that exist
         if let Some(location) = attrib.0 {
@@ -823,7 +1692,7 @@ fn enable_vertex_attribs(attribs: &[AttribArray]) {
                 gl::EnableVertexAttribArray(location);
             }
         }
-        let component_size = attrib.1 * 4;
+        let component_size = attrib.1 as usize * 4;
         offset += component_size;
     }
 
@@ -859,12 +1728,76 @@ fn create_texture(min: GLint, mag: GLint, wrap_s: GLint, wrap_t: GLint) -> Textu
 
 #[inline]
 fn insert_texture(texture: &TextureParams, width: GLint, height: GLint, pixels: Option<&[u8]>) {
+    insert_texture_level(texture, 0, width, height, pixels);
+}
+
+/// Uploads the mip levels after the base level, and makes the
+/// minification filter use them. Without `max_level` (i.e. without
+/// `GL_TEXTURE_MAX_LEVEL`), partial mip chains would make the texture
+/// incomplete, so they're left out.
+fn insert_mipmaps(texture: &TextureParams, mipmaps: &[Vec<u8>], smoothed: bool, max_level: bool) {
+    if mipmaps.is_empty() {
+        return;
+    }
+    let (width, height) = texture.size;
+    if !max_level && mipmaps.len() + 1 < compressed::mip_level_count(width, height) {
+        log::warn!("Partial mip chains aren't supported by the context, ignoring the mipmaps.");
+        return;
+    }
+    for (i, pixels) in mipmaps.iter().enumerate() {
+        let (level_width, level_height) = compressed::mip_size(width, height, i + 1);
+        insert_texture_level(texture, i + 1, level_width, level_height, Some(pixels));
+    }
+    let filter = if smoothed {
+        gl::LINEAR_MIPMAP_LINEAR
+    } else {
+        gl::NEAREST_MIPMAP_NEAREST
+    };
+    unsafe {
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as GLint);
+        if max_level {
+            let max_level = mipmaps.len() as GLint;
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, max_level);
+        }
+    }
+    print_gl_errors("after inserting mipmaps");
+}
+
+fn insert_texture_level(
+    texture: &TextureParams,
+    level: usize,
+    width: GLint,
+    height: GLint,
+    pixels: Option<&[u8]>,
+) {
+    if let Some(size) = compressed::compressed_size(texture.format, width, height) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture.handle.0);
+            gl::CompressedTexImage2D(
+                gl::TEXTURE_2D,
+                level as GLint,
+                texture.format,
+                width,
+                height,
+                0,
+                pixels.map(|pixels| pixels.len()).unwrap_or(size) as GLsizei,
+                if let Some(pixels) = pixels {
+                    pixels.as_ptr() as *const _
+                } else {
+                    ptr::null()
+                },
+            );
+        }
+        print_gl_errors("after inserting a compressed texture");
+        return;
+    }
+
     unsafe {
         gl::BindTexture(gl::TEXTURE_2D, texture.handle.0);
         gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
         gl::TexImage2D(
             gl::TEXTURE_2D,
-            0,
+            level as GLint,
             texture.format as GLint,
             width,
             height,
@@ -881,33 +1814,127 @@ fn insert_texture(texture: &TextureParams, width: GLint, height: GLint, pixels:
     print_gl_errors("after inserting a texture");
 }
 
+/// Uploads `data` into the texture. If `pixel_buffers` is provided,
+/// the data is staged through them, falling back to uploading from
+/// `data` directly if that fails.
+#[allow(clippy::too_many_arguments)]
 fn insert_sub_texture(
     texture: &TextureHandle,
     x: i32,
     y: i32,
     width: i32,
     height: i32,
-    format: GLuint,
+    pixel_format: GLuint,
+    pixel_type: GLuint,
     data: &[u8],
+    pixel_buffers: Option<&mut PixelBufferRing>,
 ) {
+    let staged = match pixel_buffers {
+        Some(pixel_buffers) if !data.is_empty() => pixel_buffers.stage(data),
+        _ => false,
+    };
+    // When a pixel unpack buffer is bound, the pointer is an offset
+    // into the buffer.
+    let pixels = if staged {
+        ptr::null()
+    } else {
+        data.as_ptr() as *const _
+    };
     unsafe {
         gl::BindTexture(gl::TEXTURE_2D, texture.0);
         gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
         gl::TexSubImage2D(
-            gl::TEXTURE_2D,            // target
-            0,                         // level
-            x,                         // xoffset
-            y,                         // yoffset
-            width,                     // width
-            height,                    // height
-            format,                    // format
-            gl::UNSIGNED_BYTE,         // type
-            data.as_ptr() as *const _, // pixels
+            gl::TEXTURE_2D, // target
+            0,              // level
+            x,              // xoffset
+            y,              // yoffset
+            width,          // width
+            height,         // height
+            pixel_format,   // format
+            pixel_type,     // type
+            pixels,         // pixels
         );
+        if staged {
+            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+        }
     }
     print_gl_errors("after insert_sub_texture");
 }
 
+/// The amount of buffers in a
+/// [`PixelBufferRing`](struct.PixelBufferRing.html).
+const PIXEL_BUFFER_COUNT: usize = 3;
+
+/// A ring of pixel unpack buffers that texture uploads are staged
+/// through, so that `glTexSubImage2D` can return without waiting for
+/// the pixels to be copied into the texture. Each buffer is only
+/// reused every `PIXEL_BUFFER_COUNT` uploads, by which time the GPU
+/// has probably finished reading it.
+#[derive(Clone, Debug)]
+struct PixelBufferRing {
+    buffers: [GLuint; PIXEL_BUFFER_COUNT],
+    allocated_sizes: [isize; PIXEL_BUFFER_COUNT],
+    next: usize,
+}
+
+impl PixelBufferRing {
+    fn new() -> Option<PixelBufferRing> {
+        let mut buffers = [0; PIXEL_BUFFER_COUNT];
+        unsafe {
+            gl::GenBuffers(PIXEL_BUFFER_COUNT as GLint, buffers.as_mut_ptr());
+        }
+        print_gl_errors("after creating pixel unpack buffers");
+        let ring = PixelBufferRing {
+            buffers,
+            allocated_sizes: [0; PIXEL_BUFFER_COUNT],
+            next: 0,
+        };
+        if buffers.contains(&0) {
+            ring.delete();
+            None
+        } else {
+            Some(ring)
+        }
+    }
+
+    /// Copies `data` into the next buffer in the ring, and leaves it
+    /// bound as the `GL_PIXEL_UNPACK_BUFFER`. Returns false, with no
+    /// buffer bound, if the buffer couldn't be written to.
+    fn stage(&mut self, data: &[u8]) -> bool {
+        let index = self.next;
+        self.next = (self.next + 1) % PIXEL_BUFFER_COUNT;
+        let size = data.len() as isize;
+        unsafe {
+            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, self.buffers[index]);
+            if size > self.allocated_sizes[index] {
+                gl::BufferData(gl::PIXEL_UNPACK_BUFFER, size, ptr::null(), gl::STREAM_DRAW);
+                self.allocated_sizes[index] = size;
+            }
+            // Invalidating lets the driver hand out fresh memory if
+            // the GPU is still reading the buffer's previous contents.
+            let access = gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_BUFFER_BIT;
+            let mapped = gl::MapBufferRange(gl::PIXEL_UNPACK_BUFFER, 0, size, access);
+            let written = if mapped.is_null() {
+                false
+            } else {
+                ptr::copy_nonoverlapping(data.as_ptr(), mapped as *mut u8, data.len());
+                gl::UnmapBuffer(gl::PIXEL_UNPACK_BUFFER) == gl::TRUE
+            };
+            if !written {
+                gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+            }
+            print_gl_errors("after staging pixels in a pixel unpack buffer");
+            written
+        }
+    }
+
+    fn delete(&self) {
+        unsafe {
+            gl::DeleteBuffers(PIXEL_BUFFER_COUNT as GLint, self.buffers.as_ptr());
+        }
+    }
+}
+
 fn resize_texture(
     texture: &TextureParams,
     old_width: i32,
@@ -959,6 +1986,98 @@ fn resize_texture(
     print_gl_errors("after resize_texture");
 }
 
+/// Reallocates the texture, and uploads the shadow copy's pixels into
+/// it, if there is one. The shadow copy is resized to match, keeping
+/// its contents in the origin corner.
+fn resize_texture_legacy(
+    texture: &TextureParams,
+    shadow: Option<&mut Vec<u8>>,
+    new_width: i32,
+    new_height: i32,
+) {
+    let pixels = shadow.and_then(|shadow| {
+        let pixel_size = get_pixel_size(texture.pixel_format, texture.pixel_type)?;
+        let (old_width, _) = texture.size;
+        *shadow = resize_pixels(shadow, pixel_size, old_width, new_width, new_height);
+        Some(&shadow[..])
+    });
+    insert_texture(texture, new_width, new_height, pixels);
+    print_gl_errors("after resize_texture_legacy");
+}
+
+/// Returns the `pixels` of an image that is `old_width` pixels wide,
+/// cropped or padded with zeroes to `new_width` x `new_height`.
+fn resize_pixels(
+    pixels: &[u8],
+    pixel_size: usize,
+    old_width: i32,
+    new_width: i32,
+    new_height: i32,
+) -> Vec<u8> {
+    let old_row_size = old_width.max(0) as usize * pixel_size;
+    let new_row_size = new_width.max(0) as usize * pixel_size;
+    let mut resized = vec![0; new_row_size * new_height.max(0) as usize];
+    if old_row_size > 0 && new_row_size > 0 {
+        let copied = old_row_size.min(new_row_size);
+        let old_rows = pixels.chunks_exact(old_row_size);
+        for (new_row, old_row) in resized.chunks_exact_mut(new_row_size).zip(old_rows) {
+            new_row[..copied].copy_from_slice(&old_row[..copied]);
+        }
+    }
+    resized
+}
+
+/// Copies the tightly packed `pixels` of `region` into the shadow
+/// copy of a texture that is `width` pixels wide.
+fn write_shadow_region(shadow: &mut [u8], width: i32, region: RectPx, pixels: &[u8]) {
+    let pixel_count = region.width as usize * region.height as usize;
+    if pixel_count == 0 || shadow.is_empty() {
+        return;
+    }
+    let pixel_size = pixels.len() / pixel_count;
+    let row_size = region.width as usize * pixel_size;
+    for (y, row) in pixels.chunks_exact(row_size).enumerate() {
+        let start = ((region.y as usize + y) * width as usize + region.x as usize) * pixel_size;
+        if let Some(dest) = shadow.get_mut(start..start + row_size) {
+            dest.copy_from_slice(row);
+        }
+    }
+}
+
+/// Returns the size of a pixel in bytes, for the uncompressed
+/// formats fae creates textures with.
+fn get_pixel_size(pixel_format: GLuint, pixel_type: GLuint) -> Option<usize> {
+    let channels = match pixel_format {
+        gl::RED | LUMINANCE => 1,
+        gl::RG | LUMINANCE_ALPHA => 2,
+        gl::RGB => 3,
+        gl::RGBA => 4,
+        _ => return None,
+    };
+    let sample_size = match pixel_type {
+        gl::UNSIGNED_BYTE => 1,
+        gl::UNSIGNED_SHORT | gl::HALF_FLOAT => 2,
+        gl::FLOAT => 4,
+        _ => return None,
+    };
+    Some(channels * sample_size)
+}
+
+#[test]
+fn shadow_copies_are_updated_and_resized() {
+    let mut shadow = vec![0; 3 * 2];
+    let region = RectPx {
+        x: 1,
+        y: 0,
+        width: 2,
+        height: 2,
+    };
+    write_shadow_region(&mut shadow, 3, region, &[1, 2, 3, 4]);
+    assert_eq!(shadow, vec![0, 1, 2, 0, 3, 4]);
+    assert_eq!(resize_pixels(&shadow, 1, 3, 2, 3), vec![0, 1, 0, 3, 0, 0]);
+    assert_eq!(get_pixel_size(gl::RGBA, gl::UNSIGNED_SHORT), Some(8));
+}
+
 #[cfg(not(debug_assertions))]
 pub(crate) fn print_gl_errors(_context: &str) {}
 
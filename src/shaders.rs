@@ -1,5 +1,8 @@
 use crate::gl_version::OpenGlApi;
 
+mod preprocessor;
+use preprocessor::Target;
+
 /// Contains the shader code for a spritesheet.
 ///
 /// Passed to the renderer with
@@ -34,6 +37,60 @@ use crate::gl_version::OpenGlApi;
 /// void main() {}
 /// ```
 ///
+/// # Legacy vertex attributes
+///
+/// When the legacy context supports instancing through extensions,
+/// legacy vertex shaders that declare the `shared_position`
+/// attribute are drawn like the 330 ones: with per-instance
+/// `position`, `texcoord`, `color`, `rotation` and `depth`
+/// attributes. Other legacy vertex shaders get per-vertex
+/// `position`, `texcoord`, `color` and `rotation` attributes, which
+/// describe each corner of the quad. The default shaders include
+/// `fae/vertex.glsl`, which declares the right ones for the context.
+///
+/// # Includes and defines
+///
+/// Before compilation, `#include "name"` lines are replaced with the
+/// named snippet, either one of your own
+/// [`snippets`](#structfield.snippets), or one of the built-in ones:
+///
+/// - `fae/compat.glsl`: macros for writing a single shader for all
+///   the GLSL versions. `FAE_ATTRIBUTE` is `in` or `attribute`,
+///   `FAE_VARYING` is `in`, `out`, or `varying`, `FAE_TEXTURE` is
+///   `texture` or `texture2D`, and `FAE_FRAG_COLOR` is the
+///   fragment shader's output (declared by the snippet in the modern
+///   versions). `FAE_LEGACY` and `FAE_ES` are defined in legacy and
+///   OpenGL ES contexts, respectively. `FAE_INSTANCED` is defined
+///   when sprites are drawn with instancing, which is always the
+///   case outside of legacy mode.
+/// - `fae/vertex.glsl`: the default vertex shader's inputs and
+///   outputs, and the `fae_vertex_transform()` function which does
+///   what the default vertex shader does.
+/// - `fae/srgb.glsl`: the `fae_srgb_to_linear` and
+///   `fae_linear_to_srgb` functions, for `vec3` and `vec4` colors.
+/// - `fae/texture.glsl`: the `fae_sample(tex, texcoord, color)`
+///   function, which samples the texture like the default fragment
+///   shader does, handling sprites without a texture.
+///
+/// Each snippet is only included once per shader. Additionally, the
+/// [`defines`](#structfield.defines) are inserted right after the
+/// version preprocessor line.
+///
+/// With these, the following fragment shader works with every
+/// version, and could be used for all four `fragment_shader`s:
+/// ```glsl, ignore
+/// #include "fae/texture.glsl"
+/// FAE_VARYING vec2 frag_texcoord;
+/// FAE_VARYING vec4 frag_color;
+/// uniform sampler2D tex;
+///
+/// void main(void) {
+///     FAE_FRAG_COLOR = fae_sample(tex, frag_texcoord, frag_color) * TINT;
+/// }
+/// ```
+/// (Assuming `TINT` was defined with
+/// [`Shaders::define`](#method.define).)
+///
 /// # Example
 /// ```no_run
 #[doc = "# let mut ctx = fae::Context::new();
@@ -66,9 +123,17 @@ pub struct Shaders {
     pub shader_300_es: ShaderPair,
     /// The `#version 100` version of the shader, for OpenGL ES 2.0 and WebGL 1.0.
     pub shader_100_es: ShaderPair,
+    /// Preprocessor definitions as (name, value) pairs, which are
+    /// inserted as `#define name value` lines at the start of every
+    /// shader.
+    pub defines: Vec<(String, String)>,
+    /// Named snippets of shader code as (name, code) pairs, which can
+    /// be included in the shaders with `#include "name"`.
+    pub snippets: Vec<(String, String)>,
 }
 
-enum ShaderType {
+#[derive(Clone, Copy)]
+pub(crate) enum ShaderType {
     Vertex,
     Fragment,
 }
@@ -108,20 +173,52 @@ impl Default for Shaders {
             shader_110: legacy.clone(),
             shader_300_es: modern,
             shader_100_es: legacy,
+            defines: Vec::new(),
+            snippets: Vec::new(),
         }
     }
 }
 
 impl Shaders {
-    pub(crate) fn create_vert_string(&self, api: OpenGlApi, legacy: bool) -> String {
-        self.create_string(api, legacy, ShaderType::Vertex)
+    /// Adds a preprocessor definition to the shaders. See
+    /// [`defines`](#structfield.defines).
+    pub fn define(&mut self, name: &str, value: &str) -> &mut Shaders {
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds a snippet that can be included in the shaders. See
+    /// [`snippets`](#structfield.snippets).
+    pub fn snippet(&mut self, name: &str, code: &str) -> &mut Shaders {
+        self.snippets.push((name.to_string(), code.to_string()));
+        self
+    }
+
+    pub(crate) fn create_vert_string(
+        &self,
+        api: OpenGlApi,
+        legacy: bool,
+        instanced: bool,
+    ) -> String {
+        self.create_string(api, legacy, instanced, ShaderType::Vertex)
     }
 
-    pub(crate) fn create_frag_string(&self, api: OpenGlApi, legacy: bool) -> String {
-        self.create_string(api, legacy, ShaderType::Fragment)
+    pub(crate) fn create_frag_string(
+        &self,
+        api: OpenGlApi,
+        legacy: bool,
+        instanced: bool,
+    ) -> String {
+        self.create_string(api, legacy, instanced, ShaderType::Fragment)
     }
 
-    fn create_string(&self, api: OpenGlApi, legacy: bool, shader_type: ShaderType) -> String {
+    fn create_string(
+        &self,
+        api: OpenGlApi,
+        legacy: bool,
+        instanced: bool,
+        shader_type: ShaderType,
+    ) -> String {
         let (base_string, version_string) = match api {
             OpenGlApi::Desktop => {
                 if legacy {
@@ -142,6 +239,19 @@ impl Shaders {
             }
         };
 
+        let target = Target {
+            api,
+            legacy,
+            instanced,
+            shader_type,
+        };
+        let base_string = preprocessor::preprocess(base_string, target, &self.snippets);
+        let defines: String = self
+            .defines
+            .iter()
+            .map(|(name, value)| format!("#define {} {}\n", name, value))
+            .collect();
+
         if base_string.contains("#version") {
             if cfg!(debug_assertions) && !base_string.contains(version_string) {
                 // There is a #version but it isn't what we'd expect.
@@ -156,13 +266,23 @@ impl Shaders {
                     );
                 }
             }
-            base_string.to_string()
+            // The version must be the first line, so insert the
+            // defines after it.
+            let mut result = String::with_capacity(base_string.len() + defines.len());
+            for line in base_string.lines() {
+                result += line;
+                result += "\n";
+                if line.trim_start().starts_with("#version") {
+                    result += &defines;
+                }
+            }
+            result
         } else {
             let mut header = version_string.to_string() + "\n";
             if api != OpenGlApi::Desktop {
                 header += "precision mediump float;\n";
             }
-            header + base_string
+            header + &defines + &base_string
         }
     }
 }
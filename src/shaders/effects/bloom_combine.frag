@@ -0,0 +1,12 @@
+#include "fae/compat.glsl"
+
+FAE_VARYING vec2 frag_texcoord;
+uniform sampler2D tex;
+uniform sampler2D scene;
+uniform float intensity;
+
+void main(void) {
+    vec4 scene_color = FAE_TEXTURE(scene, frag_texcoord);
+    vec3 bloom = FAE_TEXTURE(tex, frag_texcoord).rgb * intensity;
+    FAE_FRAG_COLOR = vec4(scene_color.rgb + bloom, scene_color.a);
+}
@@ -0,0 +1,11 @@
+#include "fae/compat.glsl"
+
+FAE_VARYING vec2 frag_texcoord;
+uniform sampler2D tex;
+uniform float threshold;
+
+void main(void) {
+    vec4 color = FAE_TEXTURE(tex, frag_texcoord);
+    float brightness = max(color.r, max(color.g, color.b));
+    FAE_FRAG_COLOR = color * step(threshold, brightness);
+}
@@ -0,0 +1,19 @@
+#include "fae/compat.glsl"
+
+FAE_VARYING vec2 frag_texcoord;
+uniform sampler2D tex;
+uniform vec2 resolution;
+uniform vec2 direction;
+
+// A 9-tap gaussian blur, sampled with 5 linearly filtered samples.
+void main(void) {
+    vec2 pixel_step = direction / resolution;
+    vec2 near = pixel_step * 1.3846153846;
+    vec2 far = pixel_step * 3.2307692308;
+    vec4 color = FAE_TEXTURE(tex, frag_texcoord) * 0.2270270270;
+    color += FAE_TEXTURE(tex, frag_texcoord + near) * 0.3162162162;
+    color += FAE_TEXTURE(tex, frag_texcoord - near) * 0.3162162162;
+    color += FAE_TEXTURE(tex, frag_texcoord + far) * 0.0702702703;
+    color += FAE_TEXTURE(tex, frag_texcoord - far) * 0.0702702703;
+    FAE_FRAG_COLOR = color;
+}
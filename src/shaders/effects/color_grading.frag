@@ -0,0 +1,25 @@
+#include "fae/compat.glsl"
+#include "fae/srgb.glsl"
+
+FAE_VARYING vec2 frag_texcoord;
+uniform sampler2D tex;
+uniform sampler2D lut;
+uniform float lut_size;
+
+void main(void) {
+    vec4 color = clamp(FAE_TEXTURE(tex, frag_texcoord), 0.0, 1.0);
+#ifndef FAE_LEGACY
+    // The LUT is indexed with gamma-encoded colors.
+    color.rgb = fae_linear_to_srgb(color.rgb);
+#endif
+    float max_index = lut_size - 1.0;
+    float blue = color.b * max_index;
+    float slice_0 = floor(blue);
+    float slice_1 = min(slice_0 + 1.0, max_index);
+    vec2 uv = vec2((color.r * max_index + 0.5) / (lut_size * lut_size),
+                   (color.g * max_index + 0.5) / lut_size);
+    vec4 graded_0 = FAE_TEXTURE(lut, uv + vec2(slice_0 / lut_size, 0.0));
+    vec4 graded_1 = FAE_TEXTURE(lut, uv + vec2(slice_1 / lut_size, 0.0));
+    vec3 graded = mix(graded_0.rgb, graded_1.rgb, blue - slice_0);
+    FAE_FRAG_COLOR = vec4(graded, color.a);
+}
@@ -0,0 +1,8 @@
+#include "fae/compat.glsl"
+
+FAE_VARYING vec2 frag_texcoord;
+uniform sampler2D tex;
+
+void main(void) {
+    FAE_FRAG_COLOR = FAE_TEXTURE(tex, frag_texcoord);
+}
@@ -0,0 +1,10 @@
+// The vertex shader used by all post-processing effects.
+#include "fae/compat.glsl"
+
+FAE_ATTRIBUTE vec2 position;
+FAE_VARYING vec2 frag_texcoord;
+
+void main(void) {
+    frag_texcoord = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,12 @@
+#include "fae/compat.glsl"
+
+FAE_VARYING vec2 frag_texcoord;
+uniform sampler2D tex;
+uniform vec2 resolution;
+uniform float intensity;
+
+void main(void) {
+    vec4 color = FAE_TEXTURE(tex, frag_texcoord);
+    float odd_line = mod(floor(frag_texcoord.y * resolution.y), 2.0);
+    FAE_FRAG_COLOR = vec4(color.rgb * (1.0 - intensity * odd_line), color.a);
+}
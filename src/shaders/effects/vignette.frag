@@ -0,0 +1,14 @@
+#include "fae/compat.glsl"
+
+FAE_VARYING vec2 frag_texcoord;
+uniform sampler2D tex;
+uniform float strength;
+
+void main(void) {
+    vec4 color = FAE_TEXTURE(tex, frag_texcoord);
+    vec2 from_center = frag_texcoord - 0.5;
+    // The corners are 0.5 away from the center (squared), so they
+    // are darkened by the full strength.
+    float vignette = clamp(1.0 - strength * 2.0 * dot(from_center, from_center), 0.0, 1.0);
+    FAE_FRAG_COLOR = vec4(color.rgb * vignette, color.a);
+}
@@ -0,0 +1,21 @@
+// sRGB conversion functions, used by `#include "fae/srgb.glsl"`.
+
+vec3 fae_srgb_to_linear(vec3 color) {
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(low, high, step(vec3(0.04045), color));
+}
+
+vec4 fae_srgb_to_linear(vec4 color) {
+    return vec4(fae_srgb_to_linear(color.rgb), color.a);
+}
+
+vec3 fae_linear_to_srgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(low, high, step(vec3(0.0031308), color));
+}
+
+vec4 fae_linear_to_srgb(vec4 color) {
+    return vec4(fae_linear_to_srgb(color.rgb), color.a);
+}
@@ -0,0 +1,13 @@
+// Texture sampling that handles untextured sprites, used by
+// `#include "fae/texture.glsl"`.
+#include "fae/compat.glsl"
+
+// Returns `color` tinted by the texture at `texcoord`, or just
+// `color` if the sprite has no texture coordinates (they are -1).
+vec4 fae_sample(sampler2D tex, vec2 texcoord, vec4 color) {
+    if (texcoord.x == -1.0 && texcoord.y == -1.0) {
+        return color;
+    } else {
+        return color * FAE_TEXTURE(tex, texcoord);
+    }
+}
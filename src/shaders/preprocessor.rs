@@ -0,0 +1,193 @@
+//! A small preprocessor that expands `#include` directives in
+//! shaders before they are passed to OpenGL.
+use super::ShaderType;
+use crate::gl_version::OpenGlApi;
+
+// Include expansion will stop at this depth, to avoid infinite
+// recursion with snippets that include each other.
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+const VERTEX_330: &str = include_str!("include/vertex.glsl");
+const VERTEX_110: &str = include_str!("include/legacy/vertex.glsl");
+const VERTEX_110_INSTANCED: &str = include_str!("include/legacy/vertex_instanced.glsl");
+const SRGB: &str = include_str!("include/srgb.glsl");
+const TEXTURE: &str = include_str!("include/texture.glsl");
+
+/// The shader being preprocessed.
+#[derive(Clone, Copy)]
+pub(crate) struct Target {
+    pub api: OpenGlApi,
+    pub legacy: bool,
+    pub instanced: bool,
+    pub shader_type: ShaderType,
+}
+
+/// Expands the `#include "name"` lines in `source` with the
+/// built-in snippets or the ones in `snippets`. Each snippet is only
+/// included once, subsequent includes of the same snippet are
+/// ignored.
+///
+/// Unknown snippets are replaced with an `#error` directive, which
+/// will make the shader compilation fail with a descriptive error.
+pub(crate) fn preprocess(source: &str, target: Target, snippets: &[(String, String)]) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut included = Vec::new();
+    expand(source, target, snippets, &mut included, 0, &mut result);
+    result
+}
+
+fn expand(
+    source: &str,
+    target: Target,
+    snippets: &[(String, String)],
+    included: &mut Vec<String>,
+    depth: usize,
+    result: &mut String,
+) {
+    for line in source.lines() {
+        let name = match parse_include(line) {
+            Some(name) => name,
+            None => {
+                result.push_str(line);
+                result.push('\n');
+                continue;
+            }
+        };
+
+        if included.iter().any(|included| included == name) {
+            continue;
+        }
+        let snippet = if let Some(snippet) = get_builtin_snippet(name, target) {
+            snippet
+        } else if let Some((_, snippet)) = snippets.iter().find(|(n, _)| n == name) {
+            snippet.clone()
+        } else {
+            log::error!("Shader includes an unknown snippet: '{}'", name);
+            result.push_str(&format!("#error unknown include \"{}\"\n", name));
+            continue;
+        };
+
+        if depth >= MAX_INCLUDE_DEPTH {
+            log::error!("Shader includes are nested too deep at: '{}'", name);
+            result.push_str(&format!("#error include \"{}\" is nested too deep\n", name));
+            continue;
+        }
+        included.push(name.to_string());
+        expand(&snippet, target, snippets, included, depth + 1, result);
+    }
+}
+
+/// Returns the name of the included snippet if the line is an
+/// `#include "name"` directive.
+fn parse_include(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if !line.starts_with('#') {
+        return None;
+    }
+    let rest = line[1..].trim_start();
+    if !rest.starts_with("include") {
+        return None;
+    }
+    let rest = rest["include".len()..].trim();
+    if rest.len() >= 2 && rest.starts_with('"') && rest.ends_with('"') {
+        Some(&rest[1..rest.len() - 1])
+    } else {
+        None
+    }
+}
+
+fn get_builtin_snippet(name: &str, target: Target) -> Option<String> {
+    match name {
+        "fae/compat.glsl" => Some(create_compat_snippet(target)),
+        "fae/vertex.glsl" if target.legacy && target.instanced => {
+            Some(VERTEX_110_INSTANCED.to_string())
+        }
+        "fae/vertex.glsl" if target.legacy => Some(VERTEX_110.to_string()),
+        "fae/vertex.glsl" => Some(VERTEX_330.to_string()),
+        "fae/srgb.glsl" => Some(SRGB.to_string()),
+        "fae/texture.glsl" => Some(TEXTURE.to_string()),
+        _ => None,
+    }
+}
+
+/// Creates the macros that hide the differences between the legacy
+/// and modern GLSL versions.
+fn create_compat_snippet(target: Target) -> String {
+    let mut snippet = String::new();
+    if target.legacy {
+        snippet += "#define FAE_LEGACY 1\n";
+    }
+    if target.instanced {
+        snippet += "#define FAE_INSTANCED 1\n";
+    }
+    if target.api == OpenGlApi::ES {
+        snippet += "#define FAE_ES 1\n";
+    }
+    let texture = if target.legacy {
+        "texture2D"
+    } else {
+        "texture"
+    };
+    snippet += &format!("#define FAE_TEXTURE {}\n", texture);
+    match (target.shader_type, target.legacy) {
+        (ShaderType::Vertex, true) => {
+            snippet += "#define FAE_ATTRIBUTE attribute\n";
+            snippet += "#define FAE_VARYING varying\n";
+        }
+        (ShaderType::Vertex, false) => {
+            snippet += "#define FAE_ATTRIBUTE in\n";
+            snippet += "#define FAE_VARYING out\n";
+        }
+        (ShaderType::Fragment, true) => {
+            snippet += "#define FAE_VARYING varying\n";
+            snippet += "#define FAE_FRAG_COLOR gl_FragColor\n";
+        }
+        (ShaderType::Fragment, false) => {
+            snippet += "#define FAE_VARYING in\n";
+            snippet += "out vec4 fae_frag_color;\n";
+            snippet += "#define FAE_FRAG_COLOR fae_frag_color\n";
+        }
+    }
+    snippet
+}
+
+#[test]
+fn includes_are_expanded_once() {
+    let target = Target {
+        api: OpenGlApi::Desktop,
+        legacy: true,
+        instanced: false,
+        shader_type: ShaderType::Fragment,
+    };
+    let snippets = vec![(
+        "blink.glsl".to_string(),
+        "#include \"fae/compat.glsl\"\nuniform float blink;".to_string(),
+    )];
+    let source = "#include \"blink.glsl\"\n  # include \"fae/compat.glsl\"\nvoid main() {}";
+    let result = preprocess(source, target, &snippets);
+    assert_eq!(result.matches("#define FAE_VARYING varying").count(), 1);
+    assert!(result.contains("uniform float blink;"));
+    assert!(result.ends_with("void main() {}\n"));
+
+    let result = preprocess("#include \"missing.glsl\"", target, &snippets);
+    assert!(result.starts_with("#error"));
+}
+
+#[test]
+fn legacy_vertex_snippet_follows_instancing() {
+    let mut target = Target {
+        api: OpenGlApi::ES,
+        legacy: true,
+        instanced: false,
+        shader_type: ShaderType::Vertex,
+    };
+    let source = "#include \"fae/compat.glsl\"\n#include \"fae/vertex.glsl\"";
+    let result = preprocess(source, target, &[]);
+    assert!(!result.contains("FAE_INSTANCED"));
+    assert!(!result.contains("shared_position"));
+
+    target.instanced = true;
+    let result = preprocess(source, target, &[]);
+    assert!(result.contains("#define FAE_INSTANCED 1"));
+    assert!(result.contains("attribute vec2 shared_position;"));
+}
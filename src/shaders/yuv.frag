@@ -0,0 +1,42 @@
+// Converts the Y, U and V planes of a video frame into RGB, used by
+// VideoSpritesheet for every GLSL version. FAE_YUV_MATRIX and
+// FAE_YUV_OFFSET are defined by fae according to the color matrix
+// and range of the video.
+#include "fae/compat.glsl"
+#include "fae/srgb.glsl"
+
+FAE_VARYING vec2 frag_texcoord;
+FAE_VARYING vec4 frag_color;
+uniform sampler2D tex;
+#ifdef FAE_YUV_NV12
+uniform sampler2D tex_uv;
+#else
+uniform sampler2D tex_u;
+uniform sampler2D tex_v;
+#endif
+
+void main(void) {
+    if (frag_texcoord.x == -1.0 && frag_texcoord.y == -1.0) {
+        FAE_FRAG_COLOR = frag_color;
+        return;
+    }
+    // Legacy contexts use luminance textures instead of red and
+    // red-green ones, which sample into .rgb and .rgb + .a.
+    float y = FAE_TEXTURE(tex, frag_texcoord).r;
+#if defined(FAE_YUV_NV12) && defined(FAE_LEGACY)
+    vec2 uv = FAE_TEXTURE(tex_uv, frag_texcoord).ra;
+#elif defined(FAE_YUV_NV12)
+    vec2 uv = FAE_TEXTURE(tex_uv, frag_texcoord).rg;
+#else
+    vec2 uv = vec2(FAE_TEXTURE(tex_u, frag_texcoord).r, FAE_TEXTURE(tex_v, frag_texcoord).r);
+#endif
+    vec3 rgb = clamp(FAE_YUV_MATRIX * (vec3(y, uv) - FAE_YUV_OFFSET), 0.0, 1.0);
+#ifdef FAE_YUV_LINEARIZE
+    // Defined when the framebuffer does the sRGB conversion (the
+    // spritesheet is drawn with sRGB enabled, and the context
+    // supports GL_FRAMEBUFFER_SRGB), so the gamma-encoded video
+    // colors need to be linear here.
+    rgb = fae_srgb_to_linear(rgb);
+#endif
+    FAE_FRAG_COLOR = frag_color * vec4(rgb, 1.0);
+}
@@ -1,14 +1,9 @@
+use crate::anchor::{Anchor, Anchoring};
+use crate::animation::AnimationPlayer;
 use crate::renderer::{DrawCallHandle, Renderer};
 use crate::types::*;
 
-// TODO(0.5.1): Add an anchoring system for sprites for smooth resizes.
-// - The simpler way, `with_anchor(x, y)`, would anchor the whole
-//   sprite to some corner.
-// - The more advanced version would involve specifying the
-//   top-left/bottom-right corners and their individual anchors.
-// - The anchors should be specified in 0..1 floats, describing the %
-//   of the way between the left/right and top/bottom edges of the
-//   window. See also: how Unity does its GUIs.
+use std::f32::consts::FRAC_PI_2;
 
 /// Sprite builder struct. Call
 /// [`finish`](struct.Sprite.html#method.finish) to draw the sprite.
@@ -28,10 +23,14 @@ pub struct Sprite<'a, 'b> {
     color: (f32, f32, f32, f32),
     rotation: (f32, f32, f32),
     clip_area: Option<(f32, f32, f32, f32)>,
+    anchoring: Option<Anchoring>,
+    pixel_alignment: bool,
+    region: Option<Region>,
 }
 
 impl<'a, 'b> Sprite<'a, 'b> {
     pub(crate) fn new(renderer: &'a mut Renderer, call: &'b DrawCallHandle) -> Sprite<'a, 'b> {
+        renderer.custom_data.clear();
         Sprite {
             renderer,
             call,
@@ -41,31 +40,61 @@ impl<'a, 'b> Sprite<'a, 'b> {
             color: (1.0, 1.0, 1.0, 1.0),
             rotation: (0.0, 0.0, 0.0),
             clip_area: None,
+            anchoring: None,
+            pixel_alignment: false,
+            region: None,
         }
     }
 
     /// Renders the quad specified by this struct.
+    ///
+    /// If the sprite is [anchored](#method.anchor), this is where
+    /// the anchors are resolved against the current frame size.
     pub fn finish(&mut self) {
-        if let Some(area) = self.clip_area {
+        let mut coords = self.coords;
+        if let Some(anchoring) = self.anchoring {
+            coords = anchoring.resolve(coords, self.renderer.frame_size);
+        }
+        if self.pixel_alignment {
+            coords = align_to_pixels(coords, self.renderer.dpi_factor);
+        }
+        let mut texcoords = self.texcoords;
+        let (mut rads, mut pivot_x, mut pivot_y) = self.rotation;
+        let mut clip_area = self.clip_area;
+        if let Some(region) = self.region {
+            let trimmed = region.place(coords);
+            pivot_x -= trimmed.0 - coords.0;
+            pivot_y -= trimmed.1 - coords.1;
+            coords = trimmed;
+            if region.rotated {
+                if let Some(area) = clip_area.take() {
+                    let unclipped = coords;
+                    if !clip_rotated(area, &mut coords, &mut texcoords) {
+                        return;
+                    }
+                    pivot_x -= coords.0 - unclipped.0;
+                    pivot_y -= coords.1 - unclipped.1;
+                }
+                let (rotated_coords, (x, y)) = unrotate_quad(coords, (pivot_x, pivot_y));
+                coords = rotated_coords;
+                rads -= FRAC_PI_2;
+                pivot_x = x;
+                pivot_y = y;
+            }
+        }
+        let rotation = (rads, pivot_x, pivot_y);
+
+        let custom = std::mem::take(&mut self.renderer.custom_data);
+        if let Some(area) = clip_area {
             self.renderer.draw_quad_clipped(
-                area,
-                self.coords,
-                self.texcoords,
-                self.color,
-                self.rotation,
-                self.z,
-                self.call,
+                area, coords, texcoords, self.color, rotation, self.z, &custom, self.call,
             );
         } else {
             self.renderer.draw_quad(
-                self.coords,
-                self.texcoords,
-                self.color,
-                self.rotation,
-                self.z,
-                self.call,
+                coords, texcoords, self.color, rotation, self.z, &custom, self.call,
             );
         }
+        self.renderer.custom_data = custom;
     }
 
     /// Specifies the Z-coordinate of the sprite. Sprites with a
@@ -112,6 +141,62 @@ impl<'a, 'b> Sprite<'a, 'b> {
         self
     }
 
+    /// Anchors the sprite to a point in the frame. The
+    /// [`coordinates`](#method.coordinates) are then interpreted as
+    /// an offset from the anchor: the same point of the sprite is
+    /// placed at the anchor, e.g. with `Anchor::BOTTOM_RIGHT`, the
+    /// sprite's bottom-right corner is placed at the frame's
+    /// bottom-right corner, when the coordinates' x and y are 0.
+    ///
+    /// The [`clip_area`](#method.clip_area) is not anchored.
+    ///
+    /// ```no_run
+    /// # let mut ctx = fae::Context::new();
+    /// # let spritesheet = fae::SpritesheetBuilder::default().build(&mut ctx);
+    /// # let mut ctx = ctx.start_frame(640.0, 480.0, 1.0);
+    /// use fae::Anchor;
+    /// // A 64x64 sprite, 10 pixels away from the bottom-right corner.
+    /// spritesheet
+    ///     .draw(&mut ctx)
+    ///     .anchor(Anchor::BOTTOM_RIGHT)
+    ///     .coordinates((-10.0, -10.0, 64.0, 64.0))
+    ///     .finish();
+    /// ```
+    pub fn anchor(&mut self, anchor: Anchor) -> &mut Self {
+        self.anchoring = Some(Anchoring::Whole(anchor));
+        self
+    }
+
+    /// Anchors the top-left and bottom-right corners of the sprite
+    /// to their own points in the frame, offset by the (x, y) pair
+    /// following each anchor (in logical pixels). This replaces the
+    /// [`coordinates`](#method.coordinates), and makes the sprite
+    /// stretch along with the frame.
+    ///
+    /// ```no_run
+    /// # let mut ctx = fae::Context::new();
+    /// # let spritesheet = fae::SpritesheetBuilder::default().build(&mut ctx);
+    /// # let mut ctx = ctx.start_frame(640.0, 480.0, 1.0);
+    /// use fae::Anchor;
+    /// // A 20 pixels tall bar along the top of the frame, with 10
+    /// // pixels of margin on each side.
+    /// spritesheet
+    ///     .draw(&mut ctx)
+    ///     .anchored_corners(Anchor::TOP_LEFT, (10.0, 10.0), Anchor::TOP_RIGHT, (-10.0, 30.0))
+    ///     .finish();
+    /// ```
+    pub fn anchored_corners(
+        &mut self,
+        top_left: Anchor,
+        (x0, y0): (f32, f32),
+        bottom_right: Anchor,
+        (x1, y1): (f32, f32),
+    ) -> &mut Self {
+        self.anchoring = Some(Anchoring::Corners(top_left, bottom_right));
+        self.coords = (x0, y0, x1, y1);
+        self
+    }
+
     /// Specifies the texture coordinates (in actual pixels, in the
     /// texture's coordinate space) from where the quad is sampled.
     pub fn texture_coordinates<R: Into<Rect>>(&mut self, rect: R) -> &mut Self {
@@ -119,24 +204,48 @@ impl<'a, 'b> Sprite<'a, 'b> {
         let (tw, th) = (tw as f32, th as f32);
         let (x0, y0, x1, y1) = rect.into().into_corners();
         self.texcoords = (x0 / tw, y0 / th, x1 / tw, y1 / th);
+        self.region = None;
+        self
+    }
+
+    /// Specifies the region of the texture the quad is sampled from,
+    /// including how it was trimmed and rotated by a texture
+    /// packer. The [`coordinates`](#method.coordinates) are then the
+    /// area of the whole, untrimmed sprite: the trimmed part is drawn
+    /// at its offset inside it, scaled along with it, and rotated back
+    /// upright if needed. See [`Region`](struct.Region.html).
+    ///
+    /// Rects can be passed in as well, as untrimmed and unrotated
+    /// regions, in which case this is the same as
+    /// [`texture_coordinates`](#method.texture_coordinates).
+    pub fn region<R: Into<Region>>(&mut self, region: R) -> &mut Self {
+        let region = region.into();
+        self.texture_coordinates(region.area);
+        self.region = Some(region);
         self
     }
 
-    /// Rounds previously set coordinates
+    /// Specifies the texture region from the current frame of the
+    /// animation player, as with [`region`](#method.region). Does
+    /// nothing if the animation has no frames.
+    pub fn animation(&mut self, player: &AnimationPlayer) -> &mut Self {
+        if let Some(region) = player.current_region() {
+            self.region(region);
+        }
+        self
+    }
+
+    /// Rounds the coordinates
     /// ([`coordinates`](#method.coordinates)) so that they
-    /// align with the physical pixels of the monitor.
+    /// align with the physical pixels of the monitor. The rounding
+    /// is done in [`finish`](#method.finish), after resolving the
+    /// anchors, if any.
     ///
     /// This might help you with weird visual glitches, especially if
     /// you're trying to render quads that have the same physical
     /// pixel size as the texture it's sampling.
     pub fn pixel_alignment(&mut self) -> &mut Self {
-        let (x0, y0, x1, y1) = self.coords;
-        let dpi_factor = self.renderer.dpi_factor;
-        let round_px = |x: f32| (x * dpi_factor).round() / dpi_factor;
-        let (w, h) = (round_px(x1 - x0), round_px(y1 - y0));
-        let (x0, y0) = (round_px(x0), round_px(y0));
-        let (x1, y1) = (x0 + w, y0 + h);
-        self.coords = (x0, y0, x1, y1);
+        self.pixel_alignment = true;
         self
     }
 
@@ -144,6 +253,7 @@ impl<'a, 'b> Sprite<'a, 'b> {
     /// where the quad is sampled.
     pub fn uvs<R: Into<Rect>>(&mut self, rect: R) -> &mut Self {
         self.texcoords = rect.into().into_corners();
+        self.region = None;
         self
     }
 
@@ -167,4 +277,133 @@ impl<'a, 'b> Sprite<'a, 'b> {
         self.rotation = (rotation, pivot_x, pivot_y);
         self
     }
+
+    /// Specifies the values of the spritesheet's custom attributes
+    /// for this quad, in the order they were declared with
+    /// [`SpritesheetBuilder::custom_attribute`](struct.SpritesheetBuilder.html#method.custom_attribute).
+    ///
+    /// Missing values are filled in with zeroes, and values beyond
+    /// the declared attributes' components are ignored.
+    pub fn custom(&mut self, values: &[f32]) -> &mut Self {
+        self.renderer.custom_data.clear();
+        self.renderer.custom_data.extend_from_slice(values);
+        self
+    }
+}
+
+fn align_to_pixels(
+    (x0, y0, x1, y1): (f32, f32, f32, f32),
+    dpi_factor: f32,
+) -> (f32, f32, f32, f32) {
+    let round_px = |x: f32| (x * dpi_factor).round() / dpi_factor;
+    let (w, h) = (round_px(x1 - x0), round_px(y1 - y0));
+    let (x0, y0) = (round_px(x0), round_px(y0));
+    (x0, y0, x0 + w, y0 + h)
+}
+
+/// Clips a quad showing a rotated region, like
+/// `Renderer::draw_quad_clipped` does for unrotated ones. Returns
+/// false if the quad is entirely outside the clip area.
+fn clip_rotated(
+    (cx0, cy0, cx1, cy1): (f32, f32, f32, f32),
+    coords: &mut (f32, f32, f32, f32),
+    texcoords: &mut (f32, f32, f32, f32),
+) -> bool {
+    let (ox0, oy0, ox1, oy1) = *coords;
+    let (tx0, ty0, tx1, ty1) = *texcoords;
+    if ox0 > cx1 || ox1 < cx0 || oy0 > cy1 || oy1 < cy0 {
+        return false;
+    }
+    let (x0, y0, x1, y1) = (
+        ox0.max(cx0).min(cx1),
+        oy0.max(cy0).min(cy1),
+        ox1.max(cx0).min(cx1),
+        oy1.max(cy0).min(cy1),
+    );
+    let (ow, oh) = (ox1 - ox0, oy1 - oy0);
+    let (u0, v0, u1, v1) = (
+        (x0 - ox0) / ow,
+        (y0 - oy0) / oh,
+        (x1 - ox0) / ow,
+        (y1 - oy0) / oh,
+    );
+    // The sprite is rotated clockwise in the texture, so the sprite's
+    // x-axis goes down the texture, and its y-axis goes left.
+    let (tw, th) = (tx1 - tx0, ty1 - ty0);
+    *texcoords = (
+        tx0 + tw * (1.0 - v1),
+        ty0 + th * u0,
+        tx0 + tw * (1.0 - v0),
+        ty0 + th * u1,
+    );
+    *coords = (x0, y0, x1, y1);
+    true
+}
+
+/// Returns the corners and pivot (relative to the top-left corner) of
+/// a quad that covers the given corners when it's rotated a quarter
+/// turn counter-clockwise (on top of the sprite's own rotation). The
+/// pivot is relative to the given corners as well, and stays in
+/// place, so that the sprite's own rotation happens around the same
+/// point.
+///
+/// The returned quad has the width and height swapped, so it shows a
+/// region that's rotated clockwise in the texture the right way up.
+fn unrotate_quad(
+    (x0, y0, x1, y1): (f32, f32, f32, f32),
+    (pivot_x, pivot_y): (f32, f32),
+) -> ((f32, f32, f32, f32), (f32, f32)) {
+    let (w, h) = (x1 - x0, y1 - y0);
+    let (center_x, center_y) = (x0 + w / 2.0, y0 + h / 2.0);
+    let (pivot_x, pivot_y) = (x0 + pivot_x, y0 + pivot_y);
+    // Rotating around the pivot instead of the center moves the quad,
+    // which is countered by offsetting the quad beforehand.
+    let (dx, dy) = (pivot_x - center_x, pivot_y - center_y);
+    let (x, y) = (center_x - h / 2.0 + dx + dy, center_y - w / 2.0 + dy - dx);
+    ((x, y, x + h, y + w), (pivot_x - x, pivot_y - y))
+}
+
+#[test]
+fn rotated_regions_are_turned_upright() {
+    // Applies a rotation like the vertex shader does.
+    let rotate = |(x, y): (f32, f32), (rads, pivot_x, pivot_y): (f32, f32, f32)| {
+        let (x, y) = (x - pivot_x, y - pivot_y);
+        let (x, y) = (
+            rads.cos() * x - rads.sin() * y,
+            rads.sin() * x + rads.cos() * y,
+        );
+        (x + pivot_x, y + pivot_y)
+    };
+    let assert_close = |(ax, ay): (f32, f32), (bx, by): (f32, f32)| {
+        assert!((ax - bx).abs() < 0.001 && (ay - by).abs() < 0.001);
+    };
+
+    let sprite = (10.0, 20.0, 40.0, 30.0);
+    for &(rads, pivot_x, pivot_y) in &[(0.0, 0.0, 0.0), (0.7, 5.0, -3.0), (FRAC_PI_2, 30.0, 10.0)] {
+        let ((x0, y0, x1, y1), (qpx, qpy)) = unrotate_quad(sprite, (pivot_x, pivot_y));
+        let quad_rotation = (rads - FRAC_PI_2, x0 + qpx, y0 + qpy);
+        let sprite_rotation = (rads, sprite.0 + pivot_x, sprite.1 + pivot_y);
+        // The quad's top-right texel is the sprite's top-left one, and
+        // so on, around the corners.
+        assert_close(
+            rotate((x1, y0), quad_rotation),
+            rotate((sprite.0, sprite.1), sprite_rotation),
+        );
+        assert_close(
+            rotate((x1, y1), quad_rotation),
+            rotate((sprite.2, sprite.1), sprite_rotation),
+        );
+        assert_close(
+            rotate((x0, y1), quad_rotation),
+            rotate((sprite.2, sprite.3), sprite_rotation),
+        );
+    }
+
+    let region = Region {
+        area: (0.0, 0.0, 10.0, 20.0).into(),
+        rotated: true,
+        trim_offset: (4.0, 2.0),
+        source_size: (40.0, 20.0),
+    };
+    assert_eq!(region.place((0.0, 0.0, 80.0, 40.0)), (8.0, 4.0, 48.0, 24.0));
 }
@@ -40,6 +40,10 @@ impl<'a> RustTypeProvider<'a> {
         })
     }
 
+    pub fn name(&self) -> String {
+        get_font_name(&self.font)
+    }
+
     fn font_size_to_scale(&self, font_size: i32) -> Scale {
         Scale::uniform(
             font_size as f32 * (self.ascent - self.descent) as f32 / self.units_per_em as f32,
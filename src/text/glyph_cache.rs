@@ -1,6 +1,5 @@
-use crate::api::AlphaBlending;
+use crate::api::{AlphaBlending, BlendMode};
 use crate::gl;
-use crate::gl::types::*;
 use crate::image::Image;
 use crate::renderer::{DrawCallHandle, Renderer, Shaders, TextureWrapping};
 use crate::text::types::*;
@@ -29,14 +28,9 @@ pub(crate) struct GlyphCache {
 }
 
 impl GlyphCache {
-    pub fn new(renderer: &mut Renderer, smoothed: bool) -> GlyphCache {
-        let mut max_size = 0 as GLint;
-        unsafe { gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_size) };
-        let size = if renderer.legacy {
-            max_size
-        } else {
-            64.min(max_size)
-        };
+    pub fn new(renderer: &mut Renderer, name: &str, smoothed: bool) -> GlyphCache {
+        let max_size = renderer.capabilities.max_texture_size;
+        let size = 64.min(max_size);
 
         let cache_image = Image::with_null_texture(size, size, gl::RED);
 
@@ -47,17 +41,24 @@ impl GlyphCache {
         shaders.shader_300_es.fragment_shader = TEXT_FRAGMENT_SHADER_330.to_string();
 
         let call = renderer.create_draw_call(
+            Some(name),
             Some(&cache_image),
             &shaders,
             AlphaBlending {
                 blend: true,
                 sort: true,
             },
+            BlendMode::Alpha,
             true,
             smoothed,
             (TextureWrapping::Clamp, TextureWrapping::Clamp),
             false,
+            &[],
+            false,
         );
+        // Legacy contexts can't copy the glyphs over to a resized
+        // texture on the GPU, so they're copied from the CPU.
+        renderer.keep_texture_shadow(&call);
         let cache = GlyphCache {
             call,
             width: size,
@@ -158,7 +159,7 @@ impl GlyphCache {
 
     pub fn upload_glyph<F: Fn(i32, i32) -> u8>(
         &mut self,
-        renderer: &Renderer,
+        renderer: &mut Renderer,
         spot: RectPx,
         get_color: F,
     ) {
@@ -184,6 +185,7 @@ impl GlyphCache {
             pixel_type: gl::UNSIGNED_BYTE,
             format: gl::RED,
             null_data: false,
+            mipmaps: Vec::new(),
         };
         renderer.upload_texture_region(&self.call, (tex_x, tex_y, width, height).into(), &image);
     }
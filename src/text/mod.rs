@@ -21,6 +21,7 @@ pub use self::text_builder::Text;
 pub use self::types::Alignment;
 
 use self::text_builder::TextData;
+use crate::anchor::Anchoring;
 use crate::renderer::{DrawCallHandle, Renderer};
 use crate::text::glyph_cache::*;
 use crate::text::layout::*;
@@ -44,14 +45,14 @@ pub(crate) struct TextRenderer {
 impl TextRenderer {
     #[cfg(feature = "font8x8")]
     pub(crate) fn with_font8x8(renderer: &mut Renderer, smoothed: bool) -> TextRenderer {
-        let cache = GlyphCache::new(renderer, smoothed);
+        let cache = GlyphCache::new(renderer, "font8x8", smoothed);
         TextRenderer::with_params(cache, Box::new(fonts::Font8x8Provider::new()))
     }
 
     #[cfg(feature = "ttf")]
     pub(crate) fn with_ttf(renderer: &mut Renderer, ttf_data: Vec<u8>) -> Option<TextRenderer> {
-        let cache = GlyphCache::new(renderer, true);
         let font = Box::new(fonts::RustTypeProvider::new(ttf_data)?);
+        let cache = GlyphCache::new(renderer, &font.name(), true);
         Some(TextRenderer::with_params(cache, font))
     }
 
@@ -88,6 +89,8 @@ impl TextRenderer {
             rotation,
             clip_area,
             visible,
+            anchoring,
+            bottom_right,
             ..
         } = data;
         let text = &data.text;
@@ -96,6 +99,28 @@ impl TextRenderer {
             return None;
         }
 
+        let (mut x, mut y, mut max_line_width, mut clip_area) = (x, y, max_line_width, clip_area);
+        match anchoring {
+            Some(Anchoring::Whole(anchor)) => {
+                // The text's own size is subtracted after the layout.
+                x += (anchor.x * self.window_size.0) as i32;
+                y += (anchor.y * self.window_size.1) as i32;
+            }
+            Some(corners @ Anchoring::Corners(_, _)) => {
+                let (x0, y0) = (x as f32, y as f32);
+                let (x1, y1) = (bottom_right.0 as f32, bottom_right.1 as f32);
+                let (x0, y0, x1, y1) = corners.resolve((x0, y0, x1, y1), self.window_size);
+                x = x0 as i32;
+                y = y0 as i32;
+                max_line_width = Some((x1 - x0) as i32);
+                if clip_area.is_none() {
+                    let df = self.dpi_factor;
+                    clip_area = Some((x0 / df, y0 / df, (x1 - x0) / df, (y1 - y0) / df).into());
+                }
+            }
+            None => {}
+        }
+
         let draw_data_index = self.draw_datas.len();
         self.draw_datas.push(TextDrawData {
             position: (x as f32 / self.dpi_factor, y as f32 / self.dpi_factor),
@@ -113,10 +138,6 @@ impl TextRenderer {
             std::f32::NEG_INFINITY,
         );
 
-        let max_line_width = max_line_width;
-        let (x, y) = (x, y);
-        let alignment = alignment;
-
         let mut text_glyphs = Vec::with_capacity(text.len());
         let mut previous_id = None;
         for c in text.chars() {
@@ -193,6 +214,29 @@ impl TextRenderer {
             cursor = cursor + self.font.get_line_advance(font_size);
         }
 
+        if let Some(Anchoring::Whole(anchor)) = anchoring {
+            if min_x <= max_x {
+                let df = self.dpi_factor;
+                let width = max_line_width.unwrap_or((max_x * df) as i32 - x);
+                let height = (max_y * df) as i32 - y;
+                let dx = -(anchor.x * width as f32) as i32;
+                let dy = -(anchor.y * height as f32) as i32;
+                if let Some(ref mut glyphs) = glyphs {
+                    for glyph in glyphs {
+                        glyph.cursor.x += dx;
+                        glyph.cursor.y += dy;
+                    }
+                }
+                let draw_data = &mut self.draw_datas[draw_data_index];
+                draw_data.position.0 += dx as f32 / df;
+                draw_data.position.1 += dy as f32 / df;
+                min_x += dx as f32 / df;
+                max_x += dx as f32 / df;
+                min_y += dy as f32 / df;
+                max_y += dy as f32 / df;
+            }
+        }
+
         if let Some((clip_min_x, clip_min_y, clip_max_x, clip_max_y)) =
             clip_area.map(|a| a.into_corners())
         {
@@ -1,3 +1,4 @@
+use crate::anchor::{Anchor, Anchoring};
 use crate::text::{Alignment, TextRenderer};
 use crate::types::*;
 
@@ -22,6 +23,10 @@ pub(crate) struct TextData {
     pub rotation: (f32, f32, f32),
     pub clip_area: Option<Rect>,
     pub visible: bool,
+    pub anchoring: Option<Anchoring>,
+    /// The offset of the bottom-right corner, with
+    /// `Anchoring::Corners`.
+    pub bottom_right: (i32, i32),
 }
 
 impl<'a> Text<'a> {
@@ -46,12 +51,17 @@ impl<'a> Text<'a> {
                 color: (0.0, 0.0, 0.0, 1.0),
                 rotation: (0.0, 0.0, 0.0),
                 visible: true,
+                anchoring: None,
+                bottom_right: (0, 0),
             },
         }
     }
 
     /// Draws the text, and returns the bounding box of all the glyphs
     /// drawn, if any were.
+    ///
+    /// If the text is [anchored](#method.anchor), this is where the
+    /// anchors are resolved against the current frame size.
     pub fn finish(&mut self) -> Option<Rect> {
         self.renderer.draw_text(&self.data)
     }
@@ -109,4 +119,53 @@ impl<'a> Text<'a> {
         self.data.rotation = (rotation, pivot_x, pivot_y);
         self
     }
+
+    /// Anchors the text to a point in the frame. The position given
+    /// to [`Font::draw`](struct.Font.html#method.draw) is then
+    /// interpreted as an offset from the anchor, and the same point
+    /// of the text's area is placed at the anchor. E.g. with
+    /// `Anchor::BOTTOM_RIGHT`, the text ends at the bottom-right
+    /// corner of the frame, when the offset is 0.
+    ///
+    /// The text's area is from the position to the bottom-right
+    /// corner of the laid out glyphs, or to the
+    /// [`max_width`](#method.max_width) if that is set.
+    ///
+    /// ```no_run
+    /// # let mut ctx = fae::Context::new();
+    /// # let font = fae::Font::with_font8x8(&mut ctx, true);
+    /// # let mut ctx = ctx.start_frame(640.0, 480.0, 1.0);
+    /// use fae::Anchor;
+    /// font.draw(&mut ctx, "Paused", 0.0, 0.0, 24.0)
+    ///     .anchor(Anchor::CENTER)
+    ///     .finish();
+    /// ```
+    pub fn anchor(&mut self, anchor: Anchor) -> &mut Self {
+        self.data.anchoring = Some(Anchoring::Whole(anchor));
+        self
+    }
+
+    /// Lays the text out in an area whose top-left and bottom-right
+    /// corners are anchored to their own points in the frame, offset
+    /// by the (x, y) pair following each anchor (in logical
+    /// pixels). This replaces the position given to
+    /// [`Font::draw`](struct.Font.html#method.draw).
+    ///
+    /// The width of the area is used as the
+    /// [`max_width`](#method.max_width), and the text is clipped to
+    /// the area, unless a [`clip_area`](#method.clip_area) is set.
+    pub fn anchored_corners(
+        &mut self,
+        top_left: Anchor,
+        (x0, y0): (f32, f32),
+        bottom_right: Anchor,
+        (x1, y1): (f32, f32),
+    ) -> &mut Self {
+        let dpi_factor = self.renderer.dpi_factor;
+        self.data.anchoring = Some(Anchoring::Corners(top_left, bottom_right));
+        self.data.x = (x0 * dpi_factor) as i32;
+        self.data.y = (y0 * dpi_factor) as i32;
+        self.data.bottom_right = ((x1 * dpi_factor) as i32, (y1 * dpi_factor) as i32);
+        self
+    }
 }
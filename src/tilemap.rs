@@ -0,0 +1,492 @@
+//! Drawing large grids of tiles without rebuilding them every frame.
+use crate::api::{GraphicsContext, Spritesheet};
+use crate::renderer::{DrawCallHandle, Renderer, StaticBufferHandle};
+use crate::types::Rect;
+
+/// The width and height of the chunks (in tiles) that tilemaps are
+/// split into. Each chunk is uploaded into its own buffer, and only
+/// rebuilt when its tiles change.
+const CHUNK_SIZE: i32 = 32;
+
+/// Describes where the tiles are in a tilemap's spritesheet.
+///
+/// Tile ids (as used in
+/// [`Tilemap::set_tile`](struct.Tilemap.html#method.set_tile)) are
+/// indices into `regions`.
+#[derive(Clone, Debug, Default)]
+pub struct Tileset {
+    /// The areas of the tiles in the spritesheet's texture (in actual
+    /// pixels, in the texture's coordinate space).
+    pub regions: Vec<Rect>,
+}
+
+impl Tileset {
+    /// Creates a tileset of `columns` by `rows` tiles of equal size,
+    /// starting from the top-left corner of the texture. Tile ids
+    /// go from left to right, top to bottom.
+    pub fn grid(tile_width: i32, tile_height: i32, columns: i32, rows: i32) -> Tileset {
+        let mut regions = Vec::with_capacity((columns * rows).max(0) as usize);
+        for y in 0..rows {
+            for x in 0..columns {
+                regions.push((x * tile_width, y * tile_height, tile_width, tile_height).into());
+            }
+        }
+        Tileset { regions }
+    }
+}
+
+/// Describes how the tiles of a [`Tilemap`](struct.Tilemap.html) are
+/// arranged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileLayout {
+    /// Tiles form a regular grid of rectangles.
+    Orthogonal,
+    /// The grid is rotated 45 degrees and squashed: the tiles are
+    /// diamonds that fill their bounding box, and increasing x goes
+    /// down-right, while increasing y goes down-left. Tile (0, 0) is
+    /// at the top.
+    Isometric,
+    /// Pointy-topped hexagons, with every odd row shifted right by
+    /// half a tile. The tile's height is the distance between the
+    /// two points.
+    HexPointyTop,
+    /// Flat-topped hexagons, with every odd column shifted down by
+    /// half a tile. The tile's width is the distance between the two
+    /// points.
+    HexFlatTop,
+}
+
+impl TileLayout {
+    /// Returns the top-left corner of the tile at (x, y), when the
+    /// tiles are `tile_width` by `tile_height`.
+    fn tile_position(self, x: i32, y: i32, (tile_width, tile_height): (f32, f32)) -> (f32, f32) {
+        let (fx, fy) = (x as f32, y as f32);
+        match self {
+            TileLayout::Orthogonal => (fx * tile_width, fy * tile_height),
+            TileLayout::Isometric => ((fx - fy) * tile_width / 2.0, (fx + fy) * tile_height / 2.0),
+            TileLayout::HexPointyTop => {
+                let shift = if y % 2 != 0 { tile_width / 2.0 } else { 0.0 };
+                (fx * tile_width + shift, fy * tile_height * 0.75)
+            }
+            TileLayout::HexFlatTop => {
+                let shift = if x % 2 != 0 { tile_height / 2.0 } else { 0.0 };
+                (fx * tile_width * 0.75, fy * tile_height + shift)
+            }
+        }
+    }
+}
+
+/// A sequence of tiles that is shown in place of a tile, see
+/// [`Tilemap::add_animation`](struct.Tilemap.html#method.add_animation).
+#[derive(Clone, Debug, Default)]
+pub struct TileAnimation {
+    /// The tile ids of the frames, and how long each frame is shown,
+    /// in seconds.
+    pub frames: Vec<(u32, f32)>,
+}
+
+impl TileAnimation {
+    /// Returns the tile shown at `time` seconds from the start of the
+    /// animation, which loops.
+    fn get_tile(&self, time: f32) -> Option<u32> {
+        let length: f32 = self.frames.iter().map(|(_, duration)| duration).sum();
+        if length <= 0.0 {
+            return self.frames.first().map(|(tile, _)| *tile);
+        }
+        let mut time = time.rem_euclid(length);
+        for (tile, duration) in &self.frames {
+            if time < *duration {
+                return Some(*tile);
+            }
+            time -= duration;
+        }
+        self.frames.last().map(|(tile, _)| *tile)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Chunk {
+    buffer: Option<StaticBufferHandle>,
+    dirty: bool,
+    /// The indices of the animations that were shown in this chunk
+    /// when it was last built.
+    animations: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Layer {
+    z: f32,
+    tiles: Vec<Option<u32>>,
+    chunks: Vec<Chunk>,
+}
+
+/// A grid of tiles, drawn from a spritesheet in chunks that are
+/// uploaded to the GPU only when they change.
+///
+/// Drawing a large map with
+/// [`Spritesheet::draw`](struct.Spritesheet.html#method.draw) means
+/// building and uploading every tile, every frame. Tilemaps split
+/// their layers into 32x32 tile chunks, which are kept in static
+/// buffers, and only the chunks that overlap the frame are drawn.
+/// Editing a tile only rebuilds the chunk it is in. The buffers are
+/// freed at the start of the frame after the tilemap is dropped.
+///
+/// The tilemap's layers are drawn back to front (by their Z
+/// coordinates) after the other sprites of the spritesheet, so
+/// alpha-blended layers blend correctly with each other.
+///
+/// # Example
+/// ```no_run
+/// # let mut ctx = fae::Context::new();
+/// # let spritesheet = fae::SpritesheetBuilder::default().build(&mut ctx);
+/// use fae::{TileLayout, Tilemap, Tileset};
+/// // The spritesheet contains 16x16 tiles, in 8 columns and 8 rows.
+/// let tileset = Tileset::grid(16, 16, 8, 8);
+/// let layout = TileLayout::Orthogonal;
+/// let mut tilemap = Tilemap::new(&spritesheet, tileset, layout, (32.0, 32.0), (512, 512));
+/// let ground = tilemap.add_layer(0.0);
+/// for y in 0..512 {
+///     for x in 0..512 {
+///         tilemap.set_tile(ground, x, y, Some(0));
+///     }
+/// }
+///
+/// // Later, in rendering code:
+/// # let (width, height, dpi_factor, camera_x, camera_y) = (0.0, 0.0, 0.0, 0.0, 0.0);
+/// let mut ctx = ctx.start_frame(width, height, dpi_factor);
+/// tilemap.draw(&mut ctx, -camera_x, -camera_y);
+/// ```
+#[derive(Debug)]
+pub struct Tilemap {
+    call: DrawCallHandle,
+    tileset: Tileset,
+    layout: TileLayout,
+    tile_size: (f32, f32),
+    size: (i32, i32),
+    /// The area covered by each chunk, used for culling.
+    chunk_bounds: Vec<Rect>,
+    layers: Vec<Layer>,
+    animations: Vec<(u32, TileAnimation)>,
+    /// The tile currently shown by each animation.
+    animation_frames: Vec<u32>,
+    time: f32,
+}
+
+impl Tilemap {
+    /// Creates an empty tilemap of `columns` by `rows` tiles, that
+    /// draws the tiles of `tileset` from `spritesheet`. Each tile is
+    /// drawn `tile_size` (width, height) logical pixels large.
+    ///
+    /// The tilemap has no layers, add them with
+    /// [`Tilemap::add_layer`](#method.add_layer).
+    pub fn new(
+        spritesheet: &Spritesheet,
+        tileset: Tileset,
+        layout: TileLayout,
+        tile_size: (f32, f32),
+        (columns, rows): (i32, i32),
+    ) -> Tilemap {
+        let (columns, rows) = (columns.max(0), rows.max(0));
+        let (chunk_columns, chunk_rows) = get_chunk_counts(columns, rows);
+        let mut chunk_bounds = Vec::with_capacity((chunk_columns * chunk_rows) as usize);
+        for chunk_y in 0..chunk_rows {
+            for chunk_x in 0..chunk_columns {
+                let (x0, y0) = (chunk_x * CHUNK_SIZE, chunk_y * CHUNK_SIZE);
+                let (x1, y1) = ((x0 + CHUNK_SIZE).min(columns), (y0 + CHUNK_SIZE).min(rows));
+                let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+                let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        let (tile_x, tile_y) = layout.tile_position(x, y, tile_size);
+                        min_x = min_x.min(tile_x);
+                        min_y = min_y.min(tile_y);
+                        max_x = max_x.max(tile_x + tile_size.0);
+                        max_y = max_y.max(tile_y + tile_size.1);
+                    }
+                }
+                chunk_bounds.push((min_x, min_y, max_x - min_x, max_y - min_y).into());
+            }
+        }
+
+        Tilemap {
+            call: spritesheet.handle.clone(),
+            tileset,
+            layout,
+            tile_size,
+            size: (columns, rows),
+            chunk_bounds,
+            layers: Vec::new(),
+            animations: Vec::new(),
+            animation_frames: Vec::new(),
+            time: 0.0,
+        }
+    }
+
+    /// Adds an empty layer at the Z coordinate `z`, and returns its
+    /// index.
+    pub fn add_layer(&mut self, z: f32) -> usize {
+        let (columns, rows) = self.size;
+        let chunk_count = self.chunk_bounds.len();
+        self.layers.push(Layer {
+            z,
+            tiles: vec![None; (columns * rows) as usize],
+            chunks: vec![
+                Chunk {
+                    buffer: None,
+                    dirty: true,
+                    animations: Vec::new(),
+                };
+                chunk_count
+            ],
+        });
+        self.layers.len() - 1
+    }
+
+    /// Returns the amount of layers in the tilemap.
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns the size of the tilemap in tiles (columns, rows).
+    pub fn size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    /// Sets the tile at (x, y) on the layer. `None` is an empty
+    /// tile. Does nothing if the layer or the coordinates are out of
+    /// bounds.
+    pub fn set_tile(&mut self, layer: usize, x: i32, y: i32, tile: Option<u32>) {
+        let (columns, rows) = self.size;
+        if x < 0 || y < 0 || x >= columns || y >= rows {
+            return;
+        }
+        if let Some(layer) = self.layers.get_mut(layer) {
+            let tile_index = (x + y * columns) as usize;
+            if layer.tiles[tile_index] != tile {
+                layer.tiles[tile_index] = tile;
+                let (chunk_columns, _) = get_chunk_counts(columns, rows);
+                let chunk_index = x / CHUNK_SIZE + y / CHUNK_SIZE * chunk_columns;
+                layer.chunks[chunk_index as usize].dirty = true;
+            }
+        }
+    }
+
+    /// Returns the tile at (x, y) on the layer, or None if the tile
+    /// is empty or out of bounds.
+    pub fn get_tile(&self, layer: usize, x: i32, y: i32) -> Option<u32> {
+        let (columns, rows) = self.size;
+        if x < 0 || y < 0 || x >= columns || y >= rows {
+            return None;
+        }
+        let layer = self.layers.get(layer)?;
+        layer.tiles[(x + y * columns) as usize]
+    }
+
+    /// Returns the area the tile at (x, y) is drawn in, relative to
+    /// the tilemap's position.
+    pub fn tile_area(&self, x: i32, y: i32) -> Rect {
+        let (tile_x, tile_y) = self.layout.tile_position(x, y, self.tile_size);
+        (tile_x, tile_y, self.tile_size.0, self.tile_size.1).into()
+    }
+
+    /// Makes the tiles with the id `tile` play the animation instead
+    /// of showing the tile itself. The animation is advanced with
+    /// [`Tilemap::advance`](#method.advance).
+    pub fn add_animation(&mut self, tile: u32, animation: TileAnimation) {
+        let frame = animation.get_tile(self.time).unwrap_or(tile);
+        self.animations.push((tile, animation));
+        self.animation_frames.push(frame);
+        for layer in &mut self.layers {
+            for chunk in &mut layer.chunks {
+                chunk.dirty = true;
+            }
+        }
+    }
+
+    /// Advances the tile animations by `seconds`. Only the chunks
+    /// with animations that changed frames are rebuilt.
+    pub fn advance(&mut self, seconds: f32) {
+        self.time += seconds;
+        let mut changed = Vec::new();
+        for (i, (tile, animation)) in self.animations.iter().enumerate() {
+            let frame = animation.get_tile(self.time).unwrap_or(*tile);
+            if self.animation_frames[i] != frame {
+                self.animation_frames[i] = frame;
+                changed.push(i);
+            }
+        }
+        if changed.is_empty() {
+            return;
+        }
+        for layer in &mut self.layers {
+            for chunk in &mut layer.chunks {
+                if chunk.animations.iter().any(|i| changed.contains(i)) {
+                    chunk.dirty = true;
+                }
+            }
+        }
+    }
+
+    /// Draws the tilemap with its top-left corner at (x, y). Only the
+    /// chunks that overlap the frame are drawn, and the ones that
+    /// have changed since they were last drawn are rebuilt.
+    pub fn draw(&mut self, ctx: &mut GraphicsContext, x: f32, y: f32) {
+        let view = Rect {
+            x: -x,
+            y: -y,
+            width: ctx.width,
+            height: ctx.height,
+        };
+        let renderer = &mut *ctx.renderer;
+        let (texture_width, texture_height) = renderer.get_texture_size(&self.call);
+        let params = ChunkParams {
+            call: &self.call,
+            tileset: &self.tileset,
+            layout: self.layout,
+            tile_size: self.tile_size,
+            size: self.size,
+            texture_size: (texture_width as f32, texture_height as f32),
+            animations: &self.animations,
+            animation_frames: &self.animation_frames,
+        };
+
+        let mut layer_order: Vec<usize> = (0..self.layers.len()).collect();
+        layer_order.sort_by(|a, b| {
+            let (a, b) = (self.layers[*a].z, self.layers[*b].z);
+            a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for layer_index in layer_order {
+            let Layer { z, tiles, chunks } = &mut self.layers[layer_index];
+            for (i, chunk) in chunks.iter_mut().enumerate() {
+                if !overlaps(self.chunk_bounds[i], view) {
+                    continue;
+                }
+                let call = params.call;
+                let buffer = chunk
+                    .buffer
+                    .get_or_insert_with(|| renderer.create_static_buffer(call));
+                if chunk.dirty {
+                    chunk.dirty = false;
+                    chunk.animations.clear();
+                    let animations = &mut chunk.animations;
+                    renderer.build_static_buffer(buffer, |renderer| {
+                        params.build_chunk(renderer, i as i32, tiles, *z, animations);
+                    });
+                }
+                renderer.draw_static_buffer(buffer, (x, y));
+            }
+        }
+    }
+}
+
+/// The parts of a tilemap needed to build its chunks.
+struct ChunkParams<'a> {
+    call: &'a DrawCallHandle,
+    tileset: &'a Tileset,
+    layout: TileLayout,
+    tile_size: (f32, f32),
+    size: (i32, i32),
+    texture_size: (f32, f32),
+    animations: &'a [(u32, TileAnimation)],
+    animation_frames: &'a [u32],
+}
+
+impl ChunkParams<'_> {
+    /// Draws the tiles of the chunk, and records the animations shown
+    /// in it.
+    fn build_chunk(
+        &self,
+        renderer: &mut Renderer,
+        chunk_index: i32,
+        tiles: &[Option<u32>],
+        z: f32,
+        animations: &mut Vec<usize>,
+    ) {
+        let (columns, rows) = self.size;
+        let (chunk_columns, _) = get_chunk_counts(columns, rows);
+        let (x0, y0) = (
+            chunk_index % chunk_columns * CHUNK_SIZE,
+            chunk_index / chunk_columns * CHUNK_SIZE,
+        );
+        let (x1, y1) = ((x0 + CHUNK_SIZE).min(columns), (y0 + CHUNK_SIZE).min(rows));
+        let (width, height) = self.tile_size;
+        let (texture_width, texture_height) = self.texture_size;
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let mut tile = match tiles[(x + y * columns) as usize] {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                if let Some(i) = self.animations.iter().position(|(t, _)| *t == tile) {
+                    tile = self.animation_frames[i];
+                    if !animations.contains(&i) {
+                        animations.push(i);
+                    }
+                }
+                let region = match self.tileset.regions.get(tile as usize) {
+                    Some(region) => region,
+                    None => continue,
+                };
+                let (tile_x, tile_y) = self.layout.tile_position(x, y, self.tile_size);
+                let (tx0, ty0, tx1, ty1) = region.into_corners();
+                renderer.draw_quad(
+                    (tile_x, tile_y, tile_x + width, tile_y + height),
+                    (
+                        tx0 / texture_width,
+                        ty0 / texture_height,
+                        tx1 / texture_width,
+                        ty1 / texture_height,
+                    ),
+                    (1.0, 1.0, 1.0, 1.0),
+                    (0.0, 0.0, 0.0),
+                    z,
+                    &[],
+                    self.call,
+                );
+            }
+        }
+    }
+}
+
+/// Returns the amount of chunks needed horizontally and vertically to
+/// cover `columns` by `rows` tiles.
+fn get_chunk_counts(columns: i32, rows: i32) -> (i32, i32) {
+    (
+        (columns + CHUNK_SIZE - 1) / CHUNK_SIZE,
+        (rows + CHUNK_SIZE - 1) / CHUNK_SIZE,
+    )
+}
+
+fn overlaps(a: Rect, b: Rect) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+#[test]
+fn tile_layouts_and_animations() {
+    let size = (32.0, 16.0);
+    assert_eq!(
+        TileLayout::Orthogonal.tile_position(2, 3, size),
+        (64.0, 48.0)
+    );
+    assert_eq!(
+        TileLayout::Isometric.tile_position(2, 3, size),
+        (-16.0, 40.0)
+    );
+    assert_eq!(
+        TileLayout::HexPointyTop.tile_position(2, 3, size),
+        (80.0, 36.0)
+    );
+    assert_eq!(
+        TileLayout::HexFlatTop.tile_position(3, 2, size),
+        (72.0, 40.0)
+    );
+
+    let animation = TileAnimation {
+        frames: vec![(4, 0.5), (5, 0.25)],
+    };
+    assert_eq!(animation.get_tile(0.0), Some(4));
+    assert_eq!(animation.get_tile(0.6), Some(5));
+    assert_eq!(animation.get_tile(0.8), Some(4));
+    assert_eq!(get_chunk_counts(512, 33), (16, 2));
+}
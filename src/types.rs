@@ -70,6 +70,100 @@ impl From<RectPx> for Rect {
     }
 }
 
+/// A region of a texture, along with how the sprite in it was
+/// trimmed and rotated when it was packed into the texture.
+///
+/// Texture packers often trim the transparent borders off of sprites,
+/// and rotate some of them 90 degrees clockwise to fit them in better.
+/// When a region is passed to
+/// [`Sprite::region`](struct.Sprite.html#method.region), the
+/// sprite's coordinates are considered to be the area of the whole,
+/// untrimmed sprite, and the trimmed part is drawn in the right place
+/// inside it, rotated back upright.
+///
+/// # Usage
+/// ```
+/// # use fae::Region;
+/// // A 32x32 sprite, trimmed down to 20x24 pixels, 6 pixels from the
+/// // left and 4 from the top, stored rotated in the texture:
+/// let region = Region {
+///     area: (64.0, 0.0, 24.0, 20.0).into(),
+///     rotated: true,
+///     trim_offset: (6.0, 4.0),
+///     source_size: (32.0, 32.0),
+/// };
+/// // Plain rects are untrimmed, unrotated regions:
+/// let region: Region = (0, 0, 16, 16).into();
+/// assert_eq!(region.source_size, (16.0, 16.0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    /// The area of the texture (in actual pixels, in the texture's
+    /// coordinate space) the trimmed sprite occupies. If the sprite is
+    /// rotated, so is this area, i.e. its width is the sprite's
+    /// height and vice versa.
+    pub area: Rect,
+    /// Whether the sprite is stored rotated 90 degrees clockwise.
+    pub rotated: bool,
+    /// The position of the trimmed sprite's top-left corner inside the
+    /// untrimmed sprite.
+    pub trim_offset: (f32, f32),
+    /// The size of the untrimmed sprite.
+    pub source_size: (f32, f32),
+}
+
+impl Region {
+    /// Returns the size of the trimmed sprite, upright.
+    pub(crate) fn trimmed_size(&self) -> (f32, f32) {
+        if self.rotated {
+            (self.area.height, self.area.width)
+        } else {
+            (self.area.width, self.area.height)
+        }
+    }
+
+    /// Returns the corners of the trimmed sprite, when the untrimmed
+    /// sprite covers the given corners.
+    pub(crate) fn place(&self, (x0, y0, x1, y1): (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
+        let scale = |length: f32, source_length: f32| {
+            if source_length > 0.0 {
+                length / source_length
+            } else {
+                0.0
+            }
+        };
+        let scale_x = scale(x1 - x0, self.source_size.0);
+        let scale_y = scale(y1 - y0, self.source_size.1);
+        let (width, height) = self.trimmed_size();
+        let x = x0 + self.trim_offset.0 * scale_x;
+        let y = y0 + self.trim_offset.1 * scale_y;
+        (x, y, x + width * scale_x, y + height * scale_y)
+    }
+}
+
+impl From<Rect> for Region {
+    fn from(area: Rect) -> Self {
+        Region {
+            area,
+            rotated: false,
+            trim_offset: (0.0, 0.0),
+            source_size: (area.width, area.height),
+        }
+    }
+}
+
+impl From<(f32, f32, f32, f32)> for Region {
+    fn from(from: (f32, f32, f32, f32)) -> Self {
+        Rect::from(from).into()
+    }
+}
+
+impl From<(i32, i32, i32, i32)> for Region {
+    fn from(from: (i32, i32, i32, i32)) -> Self {
+        Rect::from(from).into()
+    }
+}
+
 /// Like Rect, but i32-based. Internal use only, at least currently.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub(crate) struct RectPx {
@@ -0,0 +1,307 @@
+//! Spritesheets that draw video frames stored as separate Y, U and V
+//! planes, converting them into RGB in the fragment shader.
+use crate::api::{Context, GraphicsContext, Spritesheet, SpritesheetBuilder};
+use crate::gl;
+use crate::gl::types::*;
+use crate::image::Image;
+use crate::renderer::{LUMINANCE, LUMINANCE_ALPHA};
+use crate::shaders::Shaders;
+use crate::sprite::Sprite;
+
+const YUV_FRAGMENT_SHADER: &str = include_str!("shaders/yuv.frag");
+
+/// How the planes of a video frame are laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum YuvFormat {
+    /// Three planes: a full-size Y plane, and half-width,
+    /// half-height U and V planes. Also known as YUV420p.
+    I420,
+    /// Two planes: a full-size Y plane, and a half-width,
+    /// half-height plane of interleaved U and V samples.
+    Nv12,
+}
+
+/// The color matrix used to convert YUV into RGB.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum YuvMatrix {
+    /// ITU-R BT.601, used by standard definition video.
+    Bt601,
+    /// ITU-R BT.709, used by high definition video.
+    Bt709,
+}
+
+/// The range of the samples of a video frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum YuvRange {
+    /// Y goes from 16 to 235, U and V from 16 to 240. Most video is
+    /// encoded like this.
+    Limited,
+    /// Y, U and V go from 0 to 255. Also known as JPEG or PC range.
+    Full,
+}
+
+/// One of the planes of a video frame, see
+/// [`VideoSpritesheet::upload_plane`](struct.VideoSpritesheet.html#method.upload_plane).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum YuvPlane {
+    /// The luma plane, one byte per pixel.
+    Y,
+    /// The U (Cb) plane of an [`I420`](enum.YuvFormat.html#variant.I420) frame.
+    U,
+    /// The V (Cr) plane of an [`I420`](enum.YuvFormat.html#variant.I420) frame.
+    V,
+    /// The interleaved U and V plane of an
+    /// [`Nv12`](enum.YuvFormat.html#variant.Nv12) frame, two bytes
+    /// per sample.
+    Uv,
+}
+
+/// A spritesheet for drawing video frames, e.g. the output of a
+/// video decoder, without converting them into RGB on the CPU.
+///
+/// The planes of each frame are uploaded into their own textures
+/// with [`upload_plane`](#method.upload_plane), and the conversion
+/// into RGB is done by the spritesheet's built-in fragment shader,
+/// with the matrix and range given at creation. The sprites are
+/// drawn like with any other spritesheet, with the texture
+/// coordinates in the Y plane's pixels.
+///
+/// # Example
+/// ```no_run
+/// # let mut ctx = fae::Context::new();
+/// # let (y_plane, u_plane, v_plane) = (vec![0; 640 * 360], vec![0; 320 * 180], vec![0; 320 * 180]);
+/// use fae::{SpritesheetBuilder, VideoSpritesheet, YuvFormat, YuvMatrix, YuvPlane, YuvRange};
+///
+/// let video = VideoSpritesheet::new(
+///     &mut ctx,
+///     &SpritesheetBuilder::default(),
+///     640,
+///     360,
+///     YuvFormat::I420,
+///     YuvMatrix::Bt709,
+///     YuvRange::Limited,
+/// );
+///
+/// // For every frame:
+/// # let (width, height, dpi_factor) = (0.0, 0.0, 0.0);
+/// let mut ctx = ctx.start_frame(width, height, dpi_factor);
+/// video.upload_plane(&mut ctx, YuvPlane::Y, &y_plane);
+/// video.upload_plane(&mut ctx, YuvPlane::U, &u_plane);
+/// video.upload_plane(&mut ctx, YuvPlane::V, &v_plane);
+/// video.draw(&mut ctx)
+///     .coordinates((0.0, 0.0, 640.0, 360.0))
+///     .texture_coordinates((0, 0, 640, 360))
+///     .finish();
+/// ```
+#[derive(Clone, Debug)]
+pub struct VideoSpritesheet {
+    spritesheet: Spritesheet,
+    format: YuvFormat,
+    width: i32,
+    height: i32,
+}
+
+impl VideoSpritesheet {
+    /// Creates a spritesheet for `width` x `height` video frames.
+    ///
+    /// The image and shaders of the `builder` are ignored, the other
+    /// settings are applied as usual. If `srgb` is enabled, the
+    /// converted colors are treated as sRGB, like the colors of an
+    /// sRGB image.
+    pub fn new(
+        ctx: &mut Context,
+        builder: &SpritesheetBuilder,
+        width: i32,
+        height: i32,
+        format: YuvFormat,
+        matrix: YuvMatrix,
+        range: YuvRange,
+    ) -> VideoSpritesheet {
+        let legacy = ctx.renderer.legacy;
+        let (luma_format, chroma_format) = plane_formats(format, legacy);
+        let mut shaders = Shaders::default();
+        let frag = YUV_FRAGMENT_SHADER.to_string();
+        shaders.shader_330.fragment_shader = frag.clone();
+        shaders.shader_300_es.fragment_shader = frag.clone();
+        shaders.shader_110.fragment_shader = frag.clone();
+        shaders.shader_100_es.fragment_shader = frag;
+        let (matrix, offset) = yuv_to_rgb(matrix, range);
+        shaders.define("FAE_YUV_MATRIX", &glsl_constructor("mat3", &matrix));
+        shaders.define("FAE_YUV_OFFSET", &glsl_constructor("vec3", &offset));
+        if format == YuvFormat::Nv12 {
+            shaders.define("FAE_YUV_NV12", "1");
+        }
+        // Only GL_FRAMEBUFFER_SRGB converts linear colors back into
+        // sRGB, elsewhere the video's colors are written as they are.
+        if builder.srgb && !legacy && ctx.renderer.capabilities.srgb_framebuffer {
+            shaders.define("FAE_YUV_LINEARIZE", "1");
+        }
+
+        #[allow(unused_mut)]
+        let mut builder = SpritesheetBuilder {
+            image: Some(Image::with_null_texture(width, height, luma_format)),
+            shaders,
+            ..builder.without_image()
+        };
+        #[cfg(feature = "hot-reload")]
+        {
+            builder.shader_files = None;
+        }
+        let spritesheet = builder.build(ctx);
+
+        let (chroma_width, chroma_height) = chroma_size(width, height);
+        let chroma = Image::with_null_texture(chroma_width, chroma_height, chroma_format);
+        let smoothing = builder.magnification_smoothing;
+        let renderer = &mut ctx.renderer;
+        match format {
+            YuvFormat::I420 => {
+                renderer.add_extra_texture(&spritesheet.handle, "tex_u", &chroma, smoothing);
+                renderer.add_extra_texture(&spritesheet.handle, "tex_v", &chroma, smoothing);
+            }
+            YuvFormat::Nv12 => {
+                renderer.add_extra_texture(&spritesheet.handle, "tex_uv", &chroma, smoothing);
+            }
+        }
+
+        VideoSpritesheet {
+            spritesheet,
+            format,
+            width,
+            height,
+        }
+    }
+
+    /// Replaces the contents of one of the planes with `pixels`,
+    /// which should be tightly packed rows from top to bottom: 8 bits
+    /// per sample, and for the
+    /// [`Uv`](enum.YuvPlane.html#variant.Uv) plane, U and V
+    /// interleaved. The chroma planes are half the width and height
+    /// of the frame, rounded up.
+    ///
+    /// If the plane is not a part of the spritesheet's format, or the
+    /// length of `pixels` doesn't match the size of the plane, this
+    /// function does nothing and returns false.
+    ///
+    /// If the builder had
+    /// [`pixel_buffer_uploads`](struct.SpritesheetBuilder.html#structfield.pixel_buffer_uploads)
+    /// enabled, the upload is asynchronous if the context supports
+    /// pixel buffer objects.
+    pub fn upload_plane(&self, ctx: &mut GraphicsContext, plane: YuvPlane, pixels: &[u8]) -> bool {
+        let (chroma_width, chroma_height) = chroma_size(self.width, self.height);
+        let chroma_len = (chroma_width * chroma_height) as usize;
+        let (index, expected_len) = match (self.format, plane) {
+            (_, YuvPlane::Y) => (0, (self.width * self.height) as usize),
+            (YuvFormat::I420, YuvPlane::U) => (1, chroma_len),
+            (YuvFormat::I420, YuvPlane::V) => (2, chroma_len),
+            (YuvFormat::Nv12, YuvPlane::Uv) => (1, chroma_len * 2),
+            _ => return false,
+        };
+        if pixels.len() != expected_len {
+            return false;
+        }
+        ctx.renderer
+            .upload_texture(&self.spritesheet.handle, index, pixels);
+        true
+    }
+
+    /// Creates a Sprite struct for drawing the current frame. See
+    /// [`Spritesheet::draw`](struct.Spritesheet.html#method.draw).
+    pub fn draw<'a, 'b>(&'b self, ctx: &'a mut GraphicsContext) -> Sprite<'a, 'b> {
+        self.spritesheet.draw(ctx)
+    }
+
+    /// Returns the spritesheet that draws the frames, which holds the
+    /// Y plane as its texture.
+    pub fn spritesheet(&self) -> &Spritesheet {
+        &self.spritesheet
+    }
+
+    /// Returns the width and height of the frames.
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+}
+
+/// Returns the texture formats of the luma and chroma planes.
+fn plane_formats(format: YuvFormat, legacy: bool) -> (GLuint, GLuint) {
+    match (format, legacy) {
+        (YuvFormat::I420, false) => (gl::RED, gl::RED),
+        (YuvFormat::Nv12, false) => (gl::RED, gl::RG),
+        (YuvFormat::I420, true) => (LUMINANCE, LUMINANCE),
+        (YuvFormat::Nv12, true) => (LUMINANCE, LUMINANCE_ALPHA),
+    }
+}
+
+fn chroma_size(width: i32, height: i32) -> (i32, i32) {
+    ((width.max(0) + 1) / 2, (height.max(0) + 1) / 2)
+}
+
+/// Returns the YUV to RGB conversion as a column-major 3x3 matrix,
+/// and the offset that is subtracted from the YUV values before
+/// multiplying them with the matrix. The values are normalized, like
+/// in the shader.
+fn yuv_to_rgb(matrix: YuvMatrix, range: YuvRange) -> ([f32; 9], [f32; 3]) {
+    let (kr, kb) = match matrix {
+        YuvMatrix::Bt601 => (0.299, 0.114),
+        YuvMatrix::Bt709 => (0.2126, 0.0722),
+    };
+    let kg = 1.0 - kr - kb;
+    let (luma_scale, chroma_scale, luma_offset) = match range {
+        YuvRange::Limited => (255.0 / 219.0, 255.0 / 224.0, 16.0 / 255.0),
+        YuvRange::Full => (1.0, 1.0, 0.0),
+    };
+    let v_to_r = 2.0 * (1.0 - kr) * chroma_scale;
+    let u_to_g = -2.0 * kb * (1.0 - kb) / kg * chroma_scale;
+    let v_to_g = -2.0 * kr * (1.0 - kr) / kg * chroma_scale;
+    let u_to_b = 2.0 * (1.0 - kb) * chroma_scale;
+    let matrix = [
+        luma_scale, luma_scale, luma_scale, // Y
+        0.0, u_to_g, u_to_b, // U
+        v_to_r, v_to_g, 0.0, // V
+    ];
+    (matrix, [luma_offset, 128.0 / 255.0, 128.0 / 255.0])
+}
+
+fn glsl_constructor(type_name: &str, values: &[f32]) -> String {
+    let values: Vec<String> = values.iter().map(|value| format!("{:.6}", value)).collect();
+    format!("{}({})", type_name, values.join(", "))
+}
+
+#[test]
+fn yuv_conversion_matches_the_standards() {
+    fn convert(matrix: YuvMatrix, range: YuvRange, yuv: [f32; 3]) -> [f32; 3] {
+        let (m, offset) = yuv_to_rgb(matrix, range);
+        let yuv = [yuv[0] - offset[0], yuv[1] - offset[1], yuv[2] - offset[2]];
+        let mut rgb = [0.0; 3];
+        for (row, channel) in rgb.iter_mut().enumerate() {
+            *channel = m[row] * yuv[0] + m[3 + row] * yuv[1] + m[6 + row] * yuv[2];
+        }
+        rgb
+    }
+    let assert_close = |a: [f32; 3], b: [f32; 3]| {
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!((a - b).abs() < 0.01, "{:?} != {:?}", a, b);
+        }
+    };
+
+    let gray = 128.0 / 255.0;
+    for &matrix in &[YuvMatrix::Bt601, YuvMatrix::Bt709] {
+        let white = [235.0 / 255.0, gray, gray];
+        assert_close(convert(matrix, YuvRange::Limited, white), [1.0; 3]);
+        let black = [16.0 / 255.0, gray, gray];
+        assert_close(convert(matrix, YuvRange::Limited, black), [0.0; 3]);
+        assert_close(convert(matrix, YuvRange::Full, [1.0, gray, gray]), [1.0; 3]);
+    }
+
+    // Pure red in full range BT.601 (as in JPEG) and limited range BT.709.
+    let red_601 = [76.0 / 255.0, 85.0 / 255.0, 1.0];
+    assert_close(
+        convert(YuvMatrix::Bt601, YuvRange::Full, red_601),
+        [1.0, 0.0, 0.0],
+    );
+    let red_709 = [63.0 / 255.0, 102.0 / 255.0, 240.0 / 255.0];
+    assert_close(
+        convert(YuvMatrix::Bt709, YuvRange::Limited, red_709),
+        [1.0, 0.0, 0.0],
+    );
+}
@@ -0,0 +1,99 @@
+//! Rendering at a fixed resolution, scaled up to the window.
+use crate::types::Rect;
+
+/// Describes a fixed resolution the frame is drawn at, regardless of
+/// the window's size, which is then scaled up to fill the window.
+///
+/// Set with
+/// [`Context::set_virtual_resolution`](struct.Context.html#method.set_virtual_resolution).
+/// Meant for pixel art: sprites are drawn in virtual pixels, and the
+/// whole frame is scaled up with nearest-neighbor filtering.
+///
+/// # Example
+/// ```no_run
+/// # let mut ctx = fae::Context::new();
+/// use fae::{Scaling, VirtualResolution};
+/// ctx.set_virtual_resolution(Some(VirtualResolution::new(320, 180, Scaling::Integer)));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VirtualResolution {
+    /// The width of the frame in virtual pixels.
+    pub width: i32,
+    /// The height of the frame in virtual pixels.
+    pub height: i32,
+    /// How the frame is scaled to the window.
+    pub scaling: Scaling,
+    /// The color of the letterboxing bars, ie. the parts of the
+    /// window that the frame doesn't cover. Components are (red,
+    /// green, blue, alpha).
+    pub letterbox_color: (f32, f32, f32, f32),
+}
+
+impl VirtualResolution {
+    /// Creates a virtual resolution with black letterboxing.
+    pub fn new(width: i32, height: i32, scaling: Scaling) -> VirtualResolution {
+        VirtualResolution {
+            width,
+            height,
+            scaling,
+            letterbox_color: (0.0, 0.0, 0.0, 1.0),
+        }
+    }
+
+    /// Returns the area of the window (in physical pixels, from the
+    /// top-left corner) where the frame is drawn, when the window is
+    /// `window_width` by `window_height` physical pixels.
+    pub(crate) fn get_frame_area(&self, window_width: f32, window_height: f32) -> Rect {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let (scaled_width, scaled_height) = match self.scaling {
+            Scaling::Integer => {
+                let scale = (window_width / width).min(window_height / height);
+                let scale = scale.floor().max(1.0);
+                (width * scale, height * scale)
+            }
+            Scaling::Fit => {
+                let scale = (window_width / width).min(window_height / height);
+                (width * scale, height * scale)
+            }
+            Scaling::Stretch => (window_width, window_height),
+        };
+        Rect {
+            // Rounded to keep the virtual pixels aligned with the
+            // physical ones when using integer scaling.
+            x: ((window_width - scaled_width) / 2.0).round(),
+            y: ((window_height - scaled_height) / 2.0).round(),
+            width: scaled_width,
+            height: scaled_height,
+        }
+    }
+}
+
+/// Describes how a [`VirtualResolution`](struct.VirtualResolution.html)
+/// is scaled to the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scaling {
+    /// Scale by the largest whole number that fits the window (at
+    /// least 1), and letterbox the rest. Every virtual pixel is the
+    /// same size, which is usually what pixel art wants.
+    Integer,
+    /// Scale as much as possible while preserving the aspect ratio,
+    /// and letterbox the rest.
+    Fit,
+    /// Scale to cover the whole window, ignoring the aspect ratio.
+    Stretch,
+}
+
+#[test]
+fn frame_area_is_scaled_and_centered() {
+    let mut resolution = VirtualResolution::new(320, 180, Scaling::Integer);
+    let area = resolution.get_frame_area(1000.0, 600.0);
+    assert_eq!(area, (20.0, 30.0, 960.0, 540.0).into());
+
+    resolution.scaling = Scaling::Fit;
+    let area = resolution.get_frame_area(1000.0, 600.0);
+    assert_eq!(area, (0.0, 19.0, 1000.0, 562.5).into());
+
+    resolution.scaling = Scaling::Stretch;
+    let area = resolution.get_frame_area(1000.0, 600.0);
+    assert_eq!(area, (0.0, 0.0, 1000.0, 600.0).into());
+}